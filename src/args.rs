@@ -1,31 +1,246 @@
-use clap::Parser;
-use clap::ArgGroup;
+use clap::{ArgAction, Args, Parser, Subcommand};
+use clap::ValueEnum;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-#[command(group(
-ArgGroup::new("mode")
-.required(true)
-.args(["pack", "unpack"]),
-))]
-pub struct Args {
-    /// Source Folder to unpack / pack from
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Command
+}
+
+#[derive(Args, Debug)]
+pub struct GlobalArgs {
+    /// Print more details: -v every converted file and copied key, -vv also how external tools are started
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only print warnings and errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// When to colour the output
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Format of the log, json prints one object per line on stderr for CI pipelines
+    #[arg(long, value_enum, global = true, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Path to the Path.json File (defaults to the armapack.toml setting or searching in program directory)
+    #[arg(long, global = true)]
+    pub path_json: Option<String>,
+
+    /// Path to the armapack.toml project config (defaults to searching the current folder and its parents)
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Number of files / PBOs to process in parallel (defaults to the number of CPU cores)
+    #[arg(short, long, global = true)]
+    pub jobs: Option<usize>
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Pack a mod from editable files into an Arma ready mod
+    Pack(PackArgs),
+
+    /// Unpack an Arma ready mod into editable files
+    Unpack(UnpackArgs),
+
+    /// Show the header properties and entries of a PBO
+    Inspect(InspectArgs),
+
+    /// Check the integrity of a PBO or of every PBO in a packed mod and that all of them are signed
+    Verify(VerifyArgs),
+
+    /// Sign PBOs with a private key
+    Sign(SignArgs),
+
+    /// Convert a single file into Arma ready format or back
+    Convert(ConvertArgs),
+
+    /// Create a new mod project with an armapack.toml, mod.cpp and the default ignore files
+    Init(InitArgs),
+
+    /// Create a new PBO folder with pbo.json and config.cpp in the addons folder of a mod project
+    NewAddon(NewAddonArgs),
+
+    /// Check that the external tools from the path.json can be started and which features are available
+    Doctor(DoctorArgs)
+}
+
+#[derive(Args, Debug)]
+pub struct PackArgs {
+    /// Source Folder to pack from (defaults to pack.source in armapack.toml)
     #[arg(short, long)]
-    pub source: String,
+    pub source: Option<String>,
 
-    /// Destination Folder to unpack / pack to
+    /// Destination Folder to pack to (defaults to pack.destination in armapack.toml)
     #[arg(short, long)]
-    pub destination: String,
+    pub destination: Option<String>,
+
+    /// Fail packing if any PBO would not be signed
+    #[arg(long)]
+    pub require_signed: bool,
+
+    /// Fail packing on file references to missing files instead of only warning about them
+    #[arg(long)]
+    pub strict_references: bool,
+
+    /// Convert, pack and sign everything from scratch instead of reusing the .armapack-cache
+    #[arg(long)]
+    pub no_cache: bool,
 
-    /// Unpack Source Folder to Destination Folder
+    /// Keep running after packing and repack whenever a file in the Source Folder changes
+    #[arg(long, conflicts_with = "no_cache")]
+    pub watch: bool,
+
+    /// Print what packing would do without touching the Destination Folder
+    #[arg(long, conflicts_with = "watch")]
+    pub dry_run: bool,
+
+    /// Output format of the --dry-run build plan
+    #[arg(long, value_enum, default_value_t = PlanFormat::Text, requires = "dry_run")]
+    pub plan_format: PlanFormat,
+
+    /// Write a JSON build report to this file after packing (defaults to pack.report in armapack.toml)
+    #[arg(long, conflicts_with_all = ["watch", "dry_run"])]
+    pub report: Option<String>
+}
+
+#[derive(Args, Debug)]
+pub struct UnpackArgs {
+    /// Source Folder to unpack from (defaults to unpack.source in armapack.toml)
     #[arg(short, long)]
-    pub unpack: bool,
+    pub source: Option<String>,
 
-    /// Pack Source Folder to Destination Folder
+    /// Destination Folder to unpack to (defaults to unpack.destination in armapack.toml)
     #[arg(short, long)]
-    pub pack: bool,
+    pub destination: Option<String>,
+
+    /// Print what unpacking would do without touching the Destination Folder
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Output format of the --dry-run build plan
+    #[arg(long, value_enum, default_value_t = PlanFormat::Text, requires = "dry_run")]
+    pub plan_format: PlanFormat
+}
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// PBO File to inspect
+    pub pbo: String,
 
-    /// Path to the Path.json File (defaults to searching in program directory)
+    /// Print the PBO as JSON
     #[arg(long)]
-    pub path_json: Option<String>
+    pub json: bool
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// PBO File or packed mod folder to verify
+    pub path: String
+}
+
+#[derive(Args, Debug)]
+pub struct SignArgs {
+    /// PBO Files or folders containing PBO Files to sign
+    #[arg(required = true)]
+    pub pbos: Vec<String>,
+
+    /// Private key (.biprivatekey) to sign with
+    #[arg(short, long)]
+    pub key: String
+}
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// File to convert
+    pub file: String,
+
+    /// Output File (defaults to the input file with the extension of the converted format)
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Convert from Arma ready format back into an editable format
+    #[arg(long)]
+    pub debinarize: bool
+}
+
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    /// Mod project folder to create, or to add the missing project files to
+    #[arg(default_value = ".")]
+    pub folder: String,
+
+    /// Destination Folder packed mods are written to
+    #[arg(short, long)]
+    pub destination: Option<String>,
+
+    /// Name of the mod (defaults to the folder name)
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// PBO prefix root, new addons get the prefix <PREFIX>\<addon name>
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Overwrite an existing armapack.toml and other existing files
+    #[arg(long)]
+    pub force: bool
+}
+
+#[derive(Args, Debug)]
+pub struct NewAddonArgs {
+    /// Name of the PBO folder, also used as CfgPatches class
+    pub name: String,
+
+    /// Mod project folder to create the addon in (defaults to pack.source in armapack.toml or the current folder)
+    #[arg(long = "mod")]
+    pub mod_folder: Option<String>,
+
+    /// PBO prefix (defaults to the prefix derived from armapack.toml)
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Also create a key.json signing the PBO with this authority
+    #[arg(long)]
+    pub authority: Option<String>,
+
+    /// Overwrite existing files
+    #[arg(long)]
+    pub force: bool
+}
+
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Only check that the tools exist instead of also starting each of them once
+    #[arg(long)]
+    pub no_probe: bool
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFormat
+{
+    Text,
+    Json
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice
+{
+    Auto,
+    Always,
+    Never
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat
+{
+    Text,
+    Json
 }
\ No newline at end of file
@@ -0,0 +1,220 @@
+use std::fs;
+use std::io;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use sha1::{Digest, Sha1};
+use crate::utils::{self, to_hex};
+
+pub const CACHE_FOLDER_NAME: &str = ".armapack-cache";
+
+/// Makes the temporary files of parallel stores unique within this process
+static TEMPORARY_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Content addressed store for converted files, packed pbos and signatures.
+/// Every entry is keyed by a hash of everything that influences its content, so entries never have to be invalidated
+/// and the whole cache folder can be deleted at any time.
+pub struct BuildCache
+{
+    root: PathBuf
+}
+
+impl BuildCache
+{
+    pub fn new<P: AsRef<Path>>(root: P) -> Self
+    {
+        Self {
+            root: PathBuf::from(root.as_ref())
+        }
+    }
+
+    pub fn for_mod_folder<P: AsRef<Path>>(mod_folder: P) -> Self
+    {
+        Self::new(mod_folder.as_ref().join(CACHE_FOLDER_NAME))
+    }
+
+    /// Key for converting a single file with the given converter identity and settings
+    pub fn conversion_key<P: AsRef<Path>>(source_file: P, converter_identity: &str, settings: &str) -> Result<String, Error>
+    {
+        let mut hasher = Sha1::new();
+
+        hasher.update(b"conversion\0");
+        hasher.update(converter_identity.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(settings.as_bytes());
+        hasher.update(b"\0");
+
+        Self::hash_file_into(source_file, &mut hasher)?;
+
+        Ok(to_hex(&hasher.finalize()))
+    }
+
+    /// Key for packing a folder, covering every file path and content inside it
+    pub fn folder_key<P: AsRef<Path>>(folder: P, packer_identity: &str, settings: &str) -> Result<String, Error>
+    {
+        let folder = folder.as_ref();
+
+        let mut files: Vec<PathBuf> = vec![];
+
+        Self::collect_files(folder, &mut files)?;
+
+        files.sort();
+
+        let mut hasher = Sha1::new();
+
+        hasher.update(b"folder\0");
+        hasher.update(packer_identity.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(settings.as_bytes());
+        hasher.update(b"\0");
+
+        for file in files
+        {
+            let relative_path = file.strip_prefix(folder).unwrap_or(&file);
+
+            hasher.update(relative_path.to_string_lossy().replace('\\', "/").as_bytes());
+            hasher.update(b"\0");
+
+            Self::hash_file_into(&file, &mut hasher)?;
+        }
+
+        Ok(to_hex(&hasher.finalize()))
+    }
+
+    /// Key for signing a file with a private key
+    pub fn signature_key<A: AsRef<Path>, B: AsRef<Path>>(signed_file: A, private_key: B, signer_identity: &str) -> Result<String, Error>
+    {
+        let mut hasher = Sha1::new();
+
+        hasher.update(b"signature\0");
+        hasher.update(signer_identity.as_bytes());
+        hasher.update(b"\0");
+
+        Self::hash_file_into(private_key, &mut hasher)?;
+        Self::hash_file_into(signed_file, &mut hasher)?;
+
+        Ok(to_hex(&hasher.finalize()))
+    }
+
+    /// Copies the cached entry to the destination, returns false if there is no such entry
+    pub fn fetch<P: AsRef<Path>>(&self, category: &str, key: &str, destination: P) -> Result<bool, Error>
+    {
+        let entry_path = self.entry_path(category, key);
+
+        if !entry_path.is_file()
+        {
+            return Ok(false);
+        }
+
+        if let Some(parent) = destination.as_ref().parent()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::copy(entry_path, destination)?;
+
+        Ok(true)
+    }
+
+    /// Stores a copy of the file as the entry for the key
+    pub fn store<P: AsRef<Path>>(&self, category: &str, key: &str, file: P) -> Result<(), Error>
+    {
+        let entry_path = self.entry_path(category, key);
+
+        let entry_folder = self.root.join(category);
+
+        fs::create_dir_all(&entry_folder)?;
+
+        // copy into a temporary file first, so an aborted build never leaves a truncated entry behind
+        let counter = TEMPORARY_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temporary_path = entry_folder.join(format!("{key}.{}.{counter}.tmp", std::process::id()));
+
+        fs::copy(file, &temporary_path)?;
+
+        if let Err(error) = fs::rename(&temporary_path, &entry_path)
+        {
+            let _ = fs::remove_file(&temporary_path);
+
+            // another store of the same key won the race, its entry has the same content
+            if !entry_path.is_file()
+            {
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry_path(&self, category: &str, key: &str) -> PathBuf
+    {
+        self.root.join(category).join(key)
+    }
+
+    /// Hashes the length before the contents, so the boundaries between the files of a folder are unambiguous
+    fn hash_file_into<P: AsRef<Path>>(file: P, hasher: &mut Sha1) -> Result<(), Error>
+    {
+        hasher.update(fs::metadata(&file)?.len().to_le_bytes());
+
+        utils::hash_file_into(file, hasher)
+    }
+
+    fn collect_files(folder: &Path, files: &mut Vec<PathBuf>) -> io::Result<()>
+    {
+        for entry in folder.read_dir()?
+        {
+            let entry = entry?;
+
+            if entry.file_type()?.is_dir()
+            {
+                Self::collect_files(&entry.path(), files)?;
+            }
+            else {
+                files.push(entry.path());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+    use std::thread;
+    use super::BuildCache;
+
+    #[test]
+    fn parallel_stores_of_the_same_key_succeed()
+    {
+        let folder = std::env::temp_dir().join(format!("armapack-build-cache-test-{}", std::process::id()));
+
+        fs::create_dir_all(&folder).unwrap();
+
+        let source_file = folder.join("source.txt");
+
+        fs::write(&source_file, "content").unwrap();
+
+        let cache = BuildCache::new(folder.join("cache"));
+
+        thread::scope(|scope| {
+            let stores: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| cache.store("conversions", "key", &source_file)))
+                .collect();
+
+            for store in stores
+            {
+                store.join().unwrap().unwrap();
+            }
+        });
+
+        let entries: Vec<_> = fs::read_dir(folder.join("cache").join("conversions")).unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+
+        assert_eq!(entries, vec!["key"]);
+        assert_eq!(fs::read_to_string(folder.join("cache").join("conversions").join("key")).unwrap(), "content");
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
\ No newline at end of file
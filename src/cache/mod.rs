@@ -0,0 +1 @@
+pub mod build_cache;
\ No newline at end of file
@@ -0,0 +1,217 @@
+use std::path::{Path, PathBuf};
+use crate::args::GlobalArgs;
+use crate::converters::converter_registry::ConverterRegistry;
+use crate::diagnostics::error_chain;
+use crate::json_files::path_json::model::path_json::PathJson;
+use crate::json_files::path_json::path_json_handler::PathJsonHandler;
+use crate::toml_files::armapack_toml::armapack_toml_handler::ArmapackTomlHandler;
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+
+/// Everything commands share besides their own arguments: the project config and where to find the path.json
+pub struct CommandContext
+{
+    pub config: ArmapackToml,
+
+    /// Folder of the armapack.toml, relative paths in the config are resolved against it
+    config_folder: Option<PathBuf>,
+
+    path_json: Option<String>
+}
+
+impl CommandContext
+{
+    pub fn load(global: &GlobalArgs) -> Result<Self, String>
+    {
+        let config_file = match &global.config
+        {
+            Some(config_file) => {
+                let config_file = PathBuf::from(config_file);
+
+                if !config_file.is_file()
+                {
+                    return Err(format!("No armapack.toml found at: {config_file:?}"));
+                }
+
+                Some(config_file)
+            }
+            None => {
+                let current_folder = std::env::current_dir()
+                    .map_err(|error| format!("Cannot get current folder: {error}"))?;
+
+                Self::find_config_file(&current_folder)
+            }
+        };
+
+        let Some(config_file) = config_file else {
+            return Ok(Self {
+                config: ArmapackToml::default(),
+                config_folder: None,
+                path_json: global.path_json.clone(),
+            });
+        };
+
+        let mut config = ArmapackTomlHandler::default().read_toml(&config_file)
+            .map_err(|error| format!("Cannot read project config at: {config_file:?}: {}", error_chain(&error)))?;
+
+        ConverterRegistry::check_rules(&config.converters)
+            .and_then(|_| config.check_prefix_pattern())
+            .map_err(|message| format!("Cannot use project config at: {config_file:?}: {message}"))?;
+
+        let config_folder = config_file.parent().map(PathBuf::from);
+
+        if let Some(config_folder) = &config_folder
+        {
+            Self::resolve_command_paths(&mut config, config_folder);
+            Self::resolve_dependency_paths(&mut config, config_folder);
+        }
+
+        Ok(Self {
+            config,
+            config_folder,
+            path_json: global.path_json.clone(),
+        })
+    }
+
+    /// Context for building the mod in the source folder: the armapack.toml in the mod root is its manifest and takes the
+    /// place of the project config found for the current folder
+    pub fn for_mod_folder(&self, mod_folder: &Path) -> Result<Self, String>
+    {
+        let manifest_file = mod_folder.join(ArmapackToml::FILE_NAME);
+
+        if !manifest_file.is_file()
+        {
+            return Ok(Self {
+                config: self.config.clone(),
+                config_folder: self.config_folder.clone(),
+                path_json: self.path_json.clone(),
+            });
+        }
+
+        let mut config = ArmapackTomlHandler::default().read_toml(&manifest_file)
+            .map_err(|error| format!("Cannot read project manifest at: {manifest_file:?}: {}", error_chain(&error)))?;
+
+        ConverterRegistry::check_rules(&config.converters)
+            .and_then(|_| config.check_prefix_pattern())
+            .map_err(|message| format!("Cannot use project manifest at: {manifest_file:?}: {message}"))?;
+
+        Self::resolve_command_paths(&mut config, mod_folder);
+        Self::resolve_dependency_paths(&mut config, mod_folder);
+
+        Ok(Self {
+            config,
+            config_folder: Some(PathBuf::from(mod_folder)),
+            path_json: self.path_json.clone(),
+        })
+    }
+
+    /// Converter commands and hooks run in other folders, so relative program paths (e.g. tools/wav2ogg.sh) are resolved
+    /// against the config folder. Bare program names are still searched on $PATH.
+    fn resolve_command_paths(config: &mut ArmapackToml, config_folder: &Path)
+    {
+        let config_folder = std::path::absolute(config_folder).unwrap_or_else(|_| PathBuf::from(config_folder));
+
+        let hooks = &mut config.hooks;
+
+        let converter_commands = config.converters.rules.iter_mut().filter_map(|rule| rule.command.as_mut());
+
+        let hook_commands = [&mut hooks.before_copy, &mut hooks.after_convert, &mut hooks.before_pbo, &mut hooks.after_sign, &mut hooks.after_pack]
+            .into_iter()
+            .filter_map(|command| command.as_mut());
+
+        for command in converter_commands.chain(hook_commands)
+        {
+            let Some(program) = command.first_mut() else {
+                continue;
+            };
+
+            let program_path = Path::new(program.as_str());
+
+            if program_path.is_relative() && program_path.components().count() > 1
+            {
+                *program = config_folder.join(program_path).display().to_string();
+            }
+        }
+    }
+
+    /// Dependency folders are relative to the config folder, not to the folder the tool runs in
+    fn resolve_dependency_paths(config: &mut ArmapackToml, config_folder: &Path)
+    {
+        let config_folder = std::path::absolute(config_folder).unwrap_or_else(|_| PathBuf::from(config_folder));
+
+        for dependency in &mut config.mod_info.dependencies
+        {
+            *dependency = config_folder.join(dependency.as_str()).display().to_string();
+        }
+    }
+
+    /// The armapack.toml in the folder or the closest of its parents
+    pub fn find_config_file(folder: &Path) -> Option<PathBuf>
+    {
+        folder.ancestors()
+            .map(|folder| folder.join(ArmapackToml::FILE_NAME))
+            .find(|config_file| config_file.is_file())
+    }
+
+    /// The path given on the command line, or else the one from the config resolved against the config folder
+    pub fn resolve_path(&self, argument: Option<&String>, config_value: Option<&String>) -> Option<PathBuf>
+    {
+        if let Some(argument) = argument
+        {
+            return Some(PathBuf::from(argument));
+        }
+
+        let config_value = Path::new(config_value?);
+
+        match &self.config_folder
+        {
+            Some(config_folder) => Some(config_folder.join(config_value)),
+            None => Some(PathBuf::from(config_value))
+        }
+    }
+
+    /// Like [Self::resolve_path] but fails with a message naming the option and config key if neither is set
+    pub fn require_path(&self, argument: Option<&String>, config_value: Option<&String>, option: &str, config_key: &str) -> Result<PathBuf, String>
+    {
+        self.resolve_path(argument, config_value)
+            .ok_or_else(|| format!("No {option} given and no {config_key} set in {}", ArmapackToml::FILE_NAME))
+    }
+
+    /// Reads the path.json from the command line path, the config or the program directory, in that order.
+    /// A default path.json is written to the program directory if there is none yet.
+    pub fn load_path_json(&self) -> Result<PathJson, String>
+    {
+        let handler = PathJsonHandler::default();
+
+        if let Some(path) = self.resolve_path(self.path_json.as_ref(), self.config.path_json.as_ref())
+        {
+            if !path.exists()
+            {
+                return Err(format!("No path.json found at: {path:?}"));
+            }
+
+            return handler.read_json(&path)
+                .map_err(|error| format!("Cannot read path.json at: {path:?}: {}", error_chain(&error)));
+        }
+
+        let program_path = std::env::current_exe()
+            .map_err(|error| format!("Cannot get path to current executable: {error}"))?;
+
+        let program_directory = program_path.parent()
+            .ok_or("Cannot get directory of current executable")?;
+
+        let path = program_directory.join("path.json");
+
+        if path.exists()
+        {
+            return handler.read_json(path)
+                .map_err(|error| format!("Cannot read path.json in program directory: {program_directory:?}: {}", error_chain(&error)));
+        }
+
+        let paths = PathJson::default();
+
+        handler.write_json(&paths, path)
+            .map_err(|error| format!("Cannot write default path.json to program directory: {program_directory:?}: {}", error_chain(&error)))?;
+
+        Ok(paths)
+    }
+}
\ No newline at end of file
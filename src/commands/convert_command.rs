@@ -0,0 +1,36 @@
+use std::path::Path;
+use std::process::ExitCode;
+use crate::args::ConvertArgs;
+use crate::commands::command_context::CommandContext;
+use crate::commands::{configuration_error, pipeline_error};
+use crate::console;
+use crate::packers::mod_packer::{ArmaToolsModPacker, PackOptions};
+
+pub fn run(args: &ConvertArgs, context: &CommandContext) -> ExitCode
+{
+    let paths = match context.load_path_json()
+    {
+        Ok(paths) => paths,
+        Err(message) => return configuration_error(&message)
+    };
+
+    // the converter rules of the project config apply to single files as well
+    let options = PackOptions {
+        manifest: context.config.clone(),
+        ..PackOptions::default()
+    };
+
+    let mod_packer = ArmaToolsModPacker::create(paths, options);
+
+    let output = args.output.as_deref().map(Path::new);
+
+    match mod_packer.convert_single_file(Path::new(&args.file), output, args.debinarize)
+    {
+        Ok(output) => {
+            console::info(&format!("Converted: {:?} -> {output:?}", args.file));
+
+            ExitCode::SUCCESS
+        }
+        Err(error) => pipeline_error(&error)
+    }
+}
\ No newline at end of file
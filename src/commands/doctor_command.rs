@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+use std::time::Duration;
+use crate::args::DoctorArgs;
+use crate::commands::command_context::CommandContext;
+use crate::commands::{configuration_error, PROBLEMS_FOUND_EXIT_CODE};
+use crate::console;
+use crate::json_files::path_json::model::launcher::Launcher;
+use crate::tools::tool_resolver::{ProbeResult, ResolvedTool};
+
+/// How long a tool may take to answer the probe before it counts as hanging (e.g. waiting for a dialog)
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pipeline features and the path.json tool they need, None if the tool handles them natively
+const FEATURES: [(&str, Option<&str>); 10] = [
+    ("copy files, .modignore and .convertignore", None),
+    ("build cache and dry run", None),
+    ("inspect pbo headers", None),
+    ("verify pbo checksums and signatures", None),
+    ("convert images to .paa and back", Some("paa_converter_path")),
+    ("binarize and debinarize config.cpp", Some("config_converter_path")),
+    ("binarize and debinarize .rvmat", Some("rvmat_converter_path")),
+    ("pack pbos", Some("pbo_packer_path")),
+    ("unpack pbos", Some("pbo_packer_path")),
+    ("sign pbos", Some("pbo_signer_path")),
+];
+
+/// Checks that every tool in the path.json can be started, probes it and reports which features are available
+pub fn run(args: &DoctorArgs, context: &CommandContext) -> ExitCode
+{
+    let paths = match context.load_path_json()
+    {
+        Ok(paths) => paths,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let mut working_tools = BTreeMap::<&str, bool>::new();
+
+    println!("Tools:");
+
+    for (name, tool_path) in paths.tools()
+    {
+        let working = check_tool(name, tool_path, paths.launcher.as_ref(), !args.no_probe);
+
+        working_tools.insert(name, working);
+    }
+
+    println!();
+    println!("Features:");
+
+    for (feature, tool) in FEATURES
+    {
+        match tool
+        {
+            None => println!("  {} {feature} (native)", console::success_label("available  ")),
+            Some(tool) if working_tools.get(tool) == Some(&true) => {
+                println!("  {} {feature} (external: {tool})", console::success_label("available  "));
+            }
+            Some(tool) => println!("  {} {feature} (external: {tool})", console::failure_label("unavailable"))
+        }
+    }
+
+    let broken_tools = working_tools.values().filter(|working| !**working).count();
+
+    if broken_tools > 0
+    {
+        eprintln!();
+        eprintln!("{broken_tools} tools cannot be used, fix their paths in the path.json");
+        return ExitCode::from(PROBLEMS_FOUND_EXIT_CODE);
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Prints the status of a single tool, returns whether it can be used
+fn check_tool(name: &str, tool_path: &str, launcher: Option<&Launcher>, probe: bool) -> bool
+{
+    let tool = match ResolvedTool::resolve(tool_path, launcher)
+    {
+        Ok(tool) => tool,
+        Err(error) => {
+            println!("  {} {name}: {error}", console::failure_label("missing"));
+            return false;
+        }
+    };
+
+    let location = match &tool.launcher_path
+    {
+        Some(launcher_path) => format!("{} (via {})", tool.path.display(), launcher_path.display()),
+        None => tool.path.display().to_string()
+    };
+
+    if !probe
+    {
+        println!("  {} {name}: {location}", console::success_label("found  "));
+        return true;
+    }
+
+    match tool.probe(PROBE_TIMEOUT)
+    {
+        ProbeResult::Responded(code) => {
+            let exit_code = code.map(|code| code.to_string()).unwrap_or_else(|| String::from("none"));
+
+            println!("  {} {name}: {location}", console::success_label("ok     "));
+
+            if console::is_verbose()
+            {
+                println!("      probe exited with code {exit_code}");
+            }
+
+            true
+        }
+        ProbeResult::TimedOut(timeout) => {
+            println!("  {} {name}: {location} did not exit within {}s", console::failure_label("hangs  "), timeout.as_secs());
+            false
+        }
+        ProbeResult::Failed(error) => {
+            println!("  {} {name}: {location} cannot be started: {error}", console::failure_label("broken "));
+            false
+        }
+    }
+}
\ No newline at end of file
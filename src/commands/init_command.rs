@@ -0,0 +1,97 @@
+use std::path::Path;
+use std::process::ExitCode;
+use crate::args::InitArgs;
+use crate::commands::configuration_error;
+use crate::commands::scaffold;
+use crate::console;
+use crate::diagnostics::error_chain;
+use crate::toml_files::armapack_toml::armapack_toml_handler::ArmapackTomlHandler;
+use crate::toml_files::armapack_toml::model::armapack_toml::{ArmapackToml, ModSection, PackSection};
+
+/// Creates a ready to pack mod project: armapack.toml, mod.cpp, the default ignore files and an empty addons folder.
+/// Existing files besides the armapack.toml are kept unless --force is given.
+pub fn run(args: &InitArgs) -> ExitCode
+{
+    let folder = Path::new(&args.folder);
+
+    if folder.exists() && !folder.is_dir()
+    {
+        return configuration_error(&format!("Mod project folder {folder:?} is not a folder"));
+    }
+
+    let folder_name = std::path::absolute(folder)
+        .ok()
+        .and_then(|folder| folder.file_name().map(|file_name| file_name.to_string_lossy().to_string()))
+        .unwrap_or_else(|| String::from("Mod"));
+
+    let mod_name = args.name.clone().unwrap_or_else(|| String::from(folder_name.trim_start_matches('@')));
+
+    let destination = match &args.destination
+    {
+        Some(destination) => destination.clone(),
+        None => format!("../@{}", folder_name.trim_start_matches('@'))
+    };
+
+    let config = ArmapackToml {
+        mod_info: ModSection {
+            name: Some(mod_name.clone()),
+            prefix: args.prefix.clone(),
+            ..ModSection::default()
+        },
+        pack: PackSection {
+            source: Some(String::from(".")),
+            destination: Some(destination),
+            ..PackSection::default()
+        },
+        ..ArmapackToml::default()
+    };
+
+    if let Err(message) = config.check_prefix_pattern()
+    {
+        return configuration_error(&message);
+    }
+
+    if let Err(error) = std::fs::create_dir_all(folder)
+    {
+        console::error(&format!("Cannot create mod project folder {folder:?}: {error}"));
+        return ExitCode::FAILURE;
+    }
+
+    let config_file = folder.join(ArmapackToml::FILE_NAME);
+
+    if config_file.exists() && !args.force
+    {
+        return configuration_error(&format!("{config_file:?} already exists, pass --force to overwrite it"));
+    }
+
+    if let Err(error) = ArmapackTomlHandler::default().write_toml(&config, &config_file)
+    {
+        console::error(&format!("Cannot write {config_file:?}: {}", error_chain(&error)));
+        return ExitCode::FAILURE;
+    }
+
+    console::info(&format!("Created: {config_file:?}"));
+
+    let files = [
+        (folder.join("mod.cpp"), scaffold::mod_cpp(&mod_name)),
+        (folder.join(".modignore"), String::from(scaffold::DEFAULT_MOD_IGNORE)),
+        (folder.join(".convertignore"), String::from(scaffold::DEFAULT_CONVERT_IGNORE)),
+    ];
+
+    for (path, contents) in files
+    {
+        if let Err(message) = scaffold::write_file(&path, &contents, args.force)
+        {
+            console::error(&message);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Err(error) = std::fs::create_dir_all(folder.join("addons"))
+    {
+        console::error(&format!("Cannot create addons folder in {folder:?}: {error}"));
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
\ No newline at end of file
@@ -0,0 +1,66 @@
+use std::process::ExitCode;
+use crate::args::InspectArgs;
+use crate::console;
+use crate::diagnostics::error_chain;
+use crate::pbo::pbo_file::PboFile;
+
+/// Exit code for pbos that cannot be read, the same as for failed packing
+const UNREADABLE_PBO_EXIT_CODE: u8 = 5;
+
+pub fn run(args: &InspectArgs) -> ExitCode
+{
+    let pbo = match PboFile::read(&args.pbo)
+    {
+        Ok(pbo) => pbo,
+        Err(error) => {
+            console::error(&format!("Cannot read PBO {:?}: {}", args.pbo, error_chain(&error)));
+            return ExitCode::from(UNREADABLE_PBO_EXIT_CODE);
+        }
+    };
+
+    if args.json
+    {
+        return match serde_json::to_string_pretty(&pbo)
+        {
+            Ok(json) => {
+                println!("{json}");
+                ExitCode::SUCCESS
+            }
+            Err(error) => {
+                console::error(&format!("Cannot serialize PBO: {error}"));
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    println!("PBO: {}", args.pbo);
+
+    println!();
+    println!("Properties:");
+
+    for property in &pbo.properties
+    {
+        println!("  {} = {}", property.name, property.value);
+    }
+
+    println!();
+    println!("Entries ({}):", pbo.entries.len());
+
+    let name_width = pbo.entries.iter().map(|entry| entry.name.to_string().len()).max().unwrap_or(0);
+
+    for entry in &pbo.entries
+    {
+        println!("  {:name_width$}  {:>10} bytes  {:>10} stored  {:<12}  timestamp {}", entry.name, entry.original_size, entry.data_size, entry.packing_method, entry.timestamp);
+    }
+
+    println!();
+
+    match pbo.checksum_matches()
+    {
+        Some(true) => println!("Checksum: {} (valid)", pbo.computed_checksum),
+        Some(false) => println!("Checksum: {} (MISMATCH, content hashes to {})", pbo.stored_checksum.as_deref().unwrap_or_default(), pbo.computed_checksum),
+        None => println!("Checksum: missing")
+    }
+
+    ExitCode::SUCCESS
+}
\ No newline at end of file
@@ -0,0 +1,38 @@
+use std::process::ExitCode;
+use crate::console;
+use crate::diagnostics::report_error;
+use crate::packers::mod_packer_error::ModPackerError;
+
+pub mod command_context;
+pub mod pack_command;
+pub mod unpack_command;
+pub mod inspect_command;
+pub mod verify_command;
+pub mod sign_command;
+pub mod convert_command;
+pub mod init_command;
+pub mod new_addon_command;
+pub mod doctor_command;
+pub mod scaffold;
+
+/// Exit code for problems with the tool configuration itself (e.g. path.json)
+pub const CONFIGURATION_EXIT_CODE: u8 = 2;
+
+/// Exit code for checks that found problems (e.g. a dry run or verify)
+pub const PROBLEMS_FOUND_EXIT_CODE: u8 = 1;
+
+/// Prints a problem with the configuration or arguments and returns the matching exit code
+pub fn configuration_error(message: &str) -> ExitCode
+{
+    console::error(message);
+
+    ExitCode::from(CONFIGURATION_EXIT_CODE)
+}
+
+/// Reports a failed pipeline and returns the exit code for its kind of failure
+pub fn pipeline_error(error: &ModPackerError) -> ExitCode
+{
+    report_error(error);
+
+    ExitCode::from(error.exit_code())
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use crate::args::NewAddonArgs;
+use crate::commands::command_context::CommandContext;
+use crate::commands::{configuration_error, scaffold};
+use crate::console;
+use crate::json_files::key_json::model::key_json::KeyJson;
+use crate::json_files::pbo_json::model::pbo_compress::PBOCompress;
+use crate::json_files::pbo_json::model::pbo_header::PBOHeader;
+use crate::json_files::pbo_json::model::pbo_json::PBOJson;
+use crate::packers::pbo_prefixes;
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+
+/// Creates addons/<name> in the mod project with a pbo.json, a config.cpp with its CfgPatches entry and optionally a key.json
+pub fn run(args: &NewAddonArgs, context: &CommandContext) -> ExitCode
+{
+    if !scaffold::is_valid_addon_name(&args.name)
+    {
+        return configuration_error(&format!("Invalid addon name \"{}\", only letters, digits and underscores are allowed and it must not start with a digit", args.name));
+    }
+
+    let mod_folder = context.resolve_path(args.mod_folder.as_ref(), context.config.pack.source.as_ref())
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    if !mod_folder.is_dir()
+    {
+        return configuration_error(&format!("Mod project folder {mod_folder:?} does not exist"));
+    }
+
+    let context = match context.for_mod_folder(&mod_folder)
+    {
+        Ok(context) => context,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let manifest = &context.config;
+
+    let Some(prefix) = args.prefix.clone().or_else(|| manifest.addon_prefix(&args.name)) else {
+        return configuration_error(&format!("No --prefix given and no mod.prefix set in {}", ArmapackToml::FILE_NAME));
+    };
+
+    if let Some(problem) = pbo_prefixes::prefix_problem(&prefix)
+    {
+        return configuration_error(&format!("Invalid prefix \"{prefix}\", it {problem}"));
+    }
+
+    let addon_folder = mod_folder.join("addons").join(&args.name);
+
+    let pbo_json = PBOJson {
+        headers: vec![PBOHeader {
+            name: String::from("prefix"),
+            value: prefix,
+        }],
+        compress: Some(PBOCompress {
+            include: vec![],
+            exclude: vec![],
+        }),
+    };
+
+    let display_name = match &manifest.mod_info.name
+    {
+        Some(mod_name) => format!("{mod_name} - {}", args.name),
+        None => args.name.clone()
+    };
+
+    let mut files = vec![
+        (addon_folder.join("pbo.json"), to_json(&pbo_json)),
+        (addon_folder.join("config.cpp"), scaffold::config_cpp(&args.name, &display_name)),
+    ];
+
+    if let Some(authority) = &args.authority
+    {
+        let key_json = KeyJson {
+            authority_name: Some(authority.clone()),
+            authority_names: vec![],
+        };
+
+        files.push((addon_folder.join("key.json"), to_json(&key_json)));
+    }
+
+    for (path, contents) in files
+    {
+        if let Err(message) = scaffold::write_file(&path, &contents, args.force)
+        {
+            console::error(&message);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String
+{
+    // serializing the plain models cannot fail
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
\ No newline at end of file
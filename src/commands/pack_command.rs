@@ -0,0 +1,231 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use serde_json::json;
+use crate::args::{PackArgs, PlanFormat};
+use crate::commands::command_context::CommandContext;
+use crate::commands::{configuration_error, pipeline_error, PROBLEMS_FOUND_EXIT_CODE};
+use crate::console;
+use crate::diagnostics::error_chain;
+use crate::packers::build_plan::BuildPlan;
+use crate::packers::build_report::BuildReport;
+use crate::packers::mod_packer::{ArmaToolsModPacker, PackOptions, PackSummary};
+use crate::packers::mod_packer_error::ModPackerError;
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+use crate::watch::mod_watcher::ModWatcher;
+
+pub fn run(args: &PackArgs, context: &CommandContext) -> ExitCode
+{
+    let source = match context.require_path(args.source.as_ref(), context.config.pack.source.as_ref(), "--source", "pack.source")
+    {
+        Ok(source) => source,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let context = match context.for_mod_folder(&source)
+    {
+        Ok(context) => context,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let manifest = &context.config;
+
+    let destination = match context.resolve_path(args.destination.as_ref(), manifest.pack.destination.as_ref())
+    {
+        Some(destination) => destination,
+        None => match &manifest.mod_info.name
+        {
+            // without an output folder, the mod is packed next to the project
+            Some(mod_name) => std::path::absolute(&source).unwrap_or_else(|_| source.clone()).with_file_name(format!("@{mod_name}")),
+            None => return configuration_error(&format!("No --destination given and neither pack.destination nor mod.name set in {}", ArmapackToml::FILE_NAME))
+        }
+    };
+
+    // the report is a record of a single run, watching and dry runs do not write one
+    let report_file = match context.resolve_path(args.report.as_ref(), manifest.pack.report.as_ref())
+    {
+        Some(report_file) if !args.watch && !args.dry_run => match check_report_file(&report_file, &destination)
+        {
+            Ok(report_file) => Some(report_file),
+            Err(message) => return configuration_error(&message)
+        },
+        _ => None
+    };
+
+    let entry_timestamp = match source_date_epoch()
+    {
+        Ok(entry_timestamp) => entry_timestamp,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let options = PackOptions {
+        require_signed: args.require_signed || manifest.pack.require_signed.unwrap_or(false),
+        strict_references: args.strict_references || manifest.pack.strict_references.unwrap_or(false),
+        // watch mode relies on the cache to sign the pbos it did not repack, so pack.cache cannot turn it off
+        use_cache: args.watch || (!args.no_cache && manifest.pack.cache.unwrap_or(true)),
+        manifest: manifest.clone(),
+        report_file,
+        entry_timestamp
+    };
+
+    if args.dry_run
+    {
+        let plan = BuildPlan::for_pack(&source, &destination, &options);
+
+        return print_build_plan(plan, args.plan_format);
+    }
+
+    let paths = match context.load_path_json()
+    {
+        Ok(paths) => paths,
+        Err(message) => return configuration_error(&message)
+    };
+
+    match (&manifest.mod_info.name, &manifest.mod_info.version)
+    {
+        (Some(mod_name), Some(version)) => console::info(&format!("Packing {mod_name} {version}")),
+        (Some(mod_name), None) => console::info(&format!("Packing {mod_name}")),
+        _ => {}
+    }
+
+    let mod_packer = ArmaToolsModPacker::create(paths, options);
+
+    if args.watch
+    {
+        let watch_result = ModWatcher::create(&mod_packer, &source, &destination)
+            .and_then(|mut watcher| watcher.run());
+
+        if let Err(error) = watch_result
+        {
+            console::error(&error_chain(&error));
+            return ExitCode::FAILURE;
+        }
+
+        return ExitCode::SUCCESS;
+    }
+
+    let started = Instant::now();
+
+    match mod_packer.pack_with_summary(&source, &destination)
+    {
+        Ok(summary) => {
+            print_summary(&destination, &summary, started.elapsed());
+
+            write_build_report(&source, &destination, mod_packer.options(), &summary, started)
+        }
+        Err(error) => pipeline_error(&error)
+    }
+}
+
+fn print_summary(destination: &Path, summary: &PackSummary, duration: Duration)
+{
+    let message = format!(
+        "Packed {destination:?} in {:.2}s: converted {} (reused {}), packed {} pbos (reused {}), signed {} (reused {})",
+        duration.as_secs_f32(),
+        summary.converted_files,
+        summary.reused_conversions,
+        summary.packed_pbos.len(),
+        summary.reused_pbos,
+        summary.signatures,
+        summary.reused_signatures
+    );
+
+    let fields = json!({
+        "event": "summary",
+        "destination": destination,
+        "seconds": duration.as_secs_f64(),
+        "converted_files": summary.converted_files,
+        "reused_conversions": summary.reused_conversions,
+        "packed_pbos": summary.packed_pbos,
+        "reused_pbos": summary.reused_pbos,
+        "signatures": summary.signatures,
+        "reused_signatures": summary.reused_signatures,
+        "unsigned_pbos": summary.unsigned_pbos,
+    });
+
+    console::info_with(&message, fields);
+}
+
+/// The timestamp for pbo entries from SOURCE_DATE_EPOCH (https://reproducible-builds.org/specs/source-date-epoch/), 0 if unset
+fn source_date_epoch() -> Result<u32, String>
+{
+    let Some(value) = std::env::var_os("SOURCE_DATE_EPOCH") else {
+        return Ok(0);
+    };
+
+    let value = value.to_string_lossy();
+
+    value.trim().parse::<u32>()
+        .map_err(|_| format!("SOURCE_DATE_EPOCH must be a unix timestamp in seconds, got \"{value}\""))
+}
+
+/// The absolute path of the report file, which must not end up in the shipped mod
+fn check_report_file(report_file: &Path, destination: &Path) -> Result<PathBuf, String>
+{
+    let report_file = std::path::absolute(report_file)
+        .map_err(|error| format!("Invalid report path {report_file:?}: {error}"))?;
+
+    let destination = std::path::absolute(destination).unwrap_or_else(|_| PathBuf::from(destination));
+
+    if report_file.starts_with(&destination)
+    {
+        return Err(format!("The build report {report_file:?} must not be inside the destination folder {destination:?}"));
+    }
+
+    Ok(report_file)
+}
+
+fn write_build_report(source: &Path, destination: &Path, options: &PackOptions, summary: &PackSummary, started: Instant) -> ExitCode
+{
+    let Some(report_file) = &options.report_file else {
+        return ExitCode::SUCCESS;
+    };
+
+    let report = BuildReport::create(source, destination, options, summary, started.elapsed());
+
+    let written = report.to_json()
+        .map_err(|error| error.to_string())
+        .and_then(|json| {
+            if let Some(parent) = report_file.parent()
+            {
+                std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+            }
+
+            std::fs::write(report_file, json).map_err(|error| error.to_string())
+        });
+
+    if let Err(error) = written
+    {
+        console::error(&format!("Cannot write build report {report_file:?}: {error}"));
+        return ExitCode::FAILURE;
+    }
+
+    console::info(&format!("Build report: {report_file:?}"));
+
+    ExitCode::SUCCESS
+}
+
+/// Prints the build plan, exits with a failure if the real run would fail on one of the listed problems
+pub fn print_build_plan(plan: Result<BuildPlan, ModPackerError>, format: PlanFormat) -> ExitCode
+{
+    let plan = match plan
+    {
+        Ok(plan) => plan,
+        Err(error) => return pipeline_error(&error)
+    };
+
+    match format
+    {
+        PlanFormat::Text => print!("{}", plan.to_text()),
+        PlanFormat::Json => match plan.to_json()
+        {
+            Ok(json) => println!("{json}"),
+            Err(error) => {
+                console::error(&format!("Cannot serialize build plan: {error}"));
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if plan.has_problems() { ExitCode::from(PROBLEMS_FOUND_EXIT_CODE) } else { ExitCode::SUCCESS }
+}
\ No newline at end of file
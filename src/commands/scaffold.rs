@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::Path;
+use crate::console;
+
+/// The .modignore shipped with the release files
+pub const DEFAULT_MOD_IGNORE: &str = include_str!("../../.modignore");
+
+/// The .convertignore shipped with the release files
+pub const DEFAULT_CONVERT_IGNORE: &str = include_str!("../../.convertignore");
+
+/// mod.cpp with the keys the launcher shows, the name is quoted like every Arma config string
+pub fn mod_cpp(mod_name: &str) -> String
+{
+    format!(concat!(
+        "name = \"{mod_name}\";\n",
+        "author = \"\";\n",
+        "overview = \"\";\n",
+        "actionName = \"\";\n",
+        "action = \"\";\n"
+    ), mod_name = mod_name.replace('"', "\"\""))
+}
+
+/// config.cpp with the CfgPatches entry every addon needs to be loaded
+pub fn config_cpp(addon_name: &str, display_name: &str) -> String
+{
+    format!(concat!(
+        "class CfgPatches\n",
+        "{{\n",
+        "    class {addon_name}\n",
+        "    {{\n",
+        "        name = \"{display_name}\";\n",
+        "        author = \"\";\n",
+        "        requiredVersion = 0.1;\n",
+        "        requiredAddons[] = {{}};\n",
+        "        units[] = {{}};\n",
+        "        weapons[] = {{}};\n",
+        "    }};\n",
+        "}};\n"
+    ), addon_name = addon_name, display_name = display_name.replace('"', "\"\""))
+}
+
+/// Addon names end up as pbo file names and CfgPatches class names, so only letters, digits and underscores are allowed
+pub fn is_valid_addon_name(addon_name: &str) -> bool
+{
+    addon_name.chars().next().is_some_and(|first| !first.is_ascii_digit()) &&
+        addon_name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+/// Writes a generated file, an existing file is kept unless it may be overwritten. Returns whether the file was written.
+pub fn write_file(path: &Path, contents: &str, overwrite: bool) -> Result<bool, String>
+{
+    if path.exists() && !overwrite
+    {
+        console::info(&format!("Kept:    {path:?}"));
+
+        return Ok(false);
+    }
+
+    if let Some(parent) = path.parent()
+    {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("Cannot create folder {parent:?}: {error}"))?;
+    }
+
+    fs::write(path, contents)
+        .map_err(|error| format!("Cannot write {path:?}: {error}"))?;
+
+    console::info(&format!("Created: {path:?}"));
+
+    Ok(true)
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use crate::args::SignArgs;
+use crate::commands::command_context::CommandContext;
+use crate::commands::{configuration_error, pipeline_error};
+use crate::console;
+use crate::packers::mod_packer_error::{ModPackerError, PipelineStage, StageContext};
+use crate::signing::pbo_signer::{ArmaToolsPBOSigner, PBOSigner};
+use crate::utils::has_extension;
+
+pub fn run(args: &SignArgs, context: &CommandContext) -> ExitCode
+{
+    let paths = match context.load_path_json()
+    {
+        Ok(paths) => paths,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let private_key = Path::new(&args.key);
+
+    if !has_extension(private_key, "biprivatekey")
+    {
+        return configuration_error(&format!("{private_key:?} is not a .biprivatekey file"));
+    }
+
+    let authority = private_key.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+    let timeout = paths.timeout();
+
+    let signer = ArmaToolsPBOSigner {
+        tool_path: paths.pbo_signer_path,
+        launcher: paths.launcher,
+        timeout,
+    };
+
+    match sign_pbos(&signer, &args.pbos, private_key, &authority)
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => pipeline_error(&error)
+    }
+}
+
+fn sign_pbos(signer: &ArmaToolsPBOSigner<String>, pbo_paths: &[String], private_key: &Path, authority: &str) -> Result<(), ModPackerError>
+{
+    let stage = PipelineStage::Sign;
+
+    let private_key = private_key.canonicalize().in_stage(stage, private_key)?;
+
+    let mut pbo_files: Vec<PathBuf> = vec![];
+
+    for pbo_path in pbo_paths
+    {
+        collect_pbo_files(Path::new(pbo_path), &mut pbo_files)?;
+    }
+
+    let mut errors: Vec<ModPackerError> = vec![];
+
+    for pbo_file in pbo_files
+    {
+        let pbo_file = pbo_file.canonicalize().in_stage(stage, &pbo_file)?;
+
+        let output_folder = pbo_file.parent().unwrap_or(Path::new("."));
+
+        match signer.sign(&pbo_file, &private_key, output_folder)
+        {
+            Ok(()) => console::info(&format!("Signed: {pbo_file:?} with \"{authority}\"")),
+            Err(source) => errors.push(ModPackerError::Signing {
+                stage,
+                path: pbo_file,
+                authority: String::from(authority),
+                source,
+            })
+        }
+    }
+
+    ModPackerError::collect(stage, Path::new("."), errors)
+}
+
+fn collect_pbo_files(path: &Path, pbo_files: &mut Vec<PathBuf>) -> Result<(), ModPackerError>
+{
+    let stage = PipelineStage::Sign;
+
+    if !path.is_dir()
+    {
+        pbo_files.push(PathBuf::from(path));
+        return Ok(());
+    }
+
+    let mut folder_pbo_files: Vec<PathBuf> = vec![];
+
+    for entry in path.read_dir().in_stage(stage, path)?
+    {
+        let entry = entry.in_stage(stage, path)?;
+
+        if entry.path().is_file() && entry.path().extension().is_some_and(|extension| extension == "pbo")
+        {
+            folder_pbo_files.push(entry.path());
+        }
+    }
+
+    folder_pbo_files.sort();
+
+    pbo_files.extend(folder_pbo_files);
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,49 @@
+use std::process::ExitCode;
+use crate::args::UnpackArgs;
+use crate::commands::command_context::CommandContext;
+use crate::commands::pack_command::print_build_plan;
+use crate::commands::{configuration_error, pipeline_error};
+use crate::packers::build_plan::BuildPlan;
+use crate::packers::mod_packer::{ArmaToolsModPacker, PackOptions};
+use crate::packers::Packer;
+
+pub fn run(args: &UnpackArgs, context: &CommandContext) -> ExitCode
+{
+    let unpack_config = &context.config.unpack;
+
+    let source = match context.require_path(args.source.as_ref(), unpack_config.source.as_ref(), "--source", "unpack.source")
+    {
+        Ok(source) => source,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let destination = match context.require_path(args.destination.as_ref(), unpack_config.destination.as_ref(), "--destination", "unpack.destination")
+    {
+        Ok(destination) => destination,
+        Err(message) => return configuration_error(&message)
+    };
+
+    if args.dry_run
+    {
+        return print_build_plan(BuildPlan::for_unpack(&source, &destination, &context.config), args.plan_format);
+    }
+
+    let paths = match context.load_path_json()
+    {
+        Ok(paths) => paths,
+        Err(message) => return configuration_error(&message)
+    };
+
+    let options = PackOptions {
+        manifest: context.config.clone(),
+        ..PackOptions::default()
+    };
+
+    let mod_packer = ArmaToolsModPacker::create(paths, options);
+
+    match mod_packer.unpack(&source, &destination)
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => pipeline_error(&error)
+    }
+}
\ No newline at end of file
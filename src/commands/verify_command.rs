@@ -0,0 +1,114 @@
+use std::path::Path;
+use std::process::ExitCode;
+use crate::args::VerifyArgs;
+use crate::commands::PROBLEMS_FOUND_EXIT_CODE;
+use crate::console;
+use crate::diagnostics::error_chain;
+use crate::pbo::pbo_file::PboFile;
+use crate::utils::files_with_extension;
+
+/// Checks a single pbo, or every pbo in the addons folder of a packed mod and that each is signed by one of the mod's keys.
+/// Signatures are only checked for presence, not cryptographically.
+pub fn run(args: &VerifyArgs) -> ExitCode
+{
+    let path = Path::new(&args.path);
+
+    let mut problems: Vec<String> = vec![];
+
+    if path.is_dir()
+    {
+        verify_mod_folder(path, &mut problems);
+    }
+    else {
+        verify_pbo(path, &mut problems);
+    }
+
+    if problems.is_empty()
+    {
+        println!("{}", console::success_label("No problems found"));
+        return ExitCode::SUCCESS;
+    }
+
+    for problem in &problems
+    {
+        console::error(problem);
+    }
+
+    eprintln!("{} problems found", problems.len());
+
+    ExitCode::from(PROBLEMS_FOUND_EXIT_CODE)
+}
+
+/// Returns whether the pbo could be read
+fn verify_pbo(pbo_path: &Path, problems: &mut Vec<String>) -> bool
+{
+    let pbo = match PboFile::read(pbo_path)
+    {
+        Ok(pbo) => pbo,
+        Err(error) => {
+            problems.push(format!("Cannot read PBO {pbo_path:?}: {}", error_chain(&error)));
+            return false;
+        }
+    };
+
+    match pbo.checksum_matches()
+    {
+        Some(true) => {}
+        Some(false) => problems.push(format!("Checksum of {pbo_path:?} does not match its content, the file is corrupt")),
+        None => console::warning(&format!("{pbo_path:?} has no checksum"))
+    }
+
+    console::info(&format!("Checked: {pbo_path:?} ({} entries)", pbo.entries.len()));
+
+    true
+}
+
+fn verify_mod_folder(mod_folder: &Path, problems: &mut Vec<String>)
+{
+    let addons_folder = mod_folder.join("addons");
+
+    let pbo_files = files_with_extension(&addons_folder, "pbo");
+
+    if pbo_files.is_empty()
+    {
+        problems.push(format!("No PBOs found in {addons_folder:?}"));
+        return;
+    }
+
+    let authorities: Vec<String> = files_with_extension(&mod_folder.join("keys"), "bikey").iter()
+        .map(|key_file| key_file.file_stem().unwrap_or_default().to_string_lossy().to_string())
+        .collect();
+
+    let signatures = files_with_extension(&addons_folder, "bisign");
+
+    for pbo_file in &pbo_files
+    {
+        if !verify_pbo(pbo_file, problems)
+        {
+            continue;
+        }
+
+        let pbo_file_name = pbo_file.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        let is_signed = authorities.iter()
+            .any(|authority| addons_folder.join(format!("{pbo_file_name}.{authority}.bisign")).is_file());
+
+        if !is_signed
+        {
+            problems.push(format!("{pbo_file:?} has no signature matching a .bikey in the keys folder"));
+        }
+    }
+
+    for signature in &signatures
+    {
+        let signature_name = signature.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        let has_key = authorities.iter()
+            .any(|authority| signature_name.ends_with(&format!(".{authority}.bisign")));
+
+        if !has_key
+        {
+            problems.push(format!("No .bikey in the keys folder matches the signature {signature:?}"));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,318 @@
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use serde_json::{json, Map, Value};
+use crate::args::{ColorChoice, LogFormat};
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Normal as u8);
+static JSON: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+static PROGRESS_BARS: AtomicBool = AtomicBool::new(false);
+
+/// How much is printed, every level includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel
+{
+    /// Only warnings and errors
+    Quiet,
+
+    /// Progress of the stages and a summary at the end
+    Normal,
+
+    /// Every file converted, key copied and hook run
+    Verbose,
+
+    /// The paths and command lines used for external tools
+    Trace
+}
+
+impl LogLevel
+{
+    fn name(self) -> &'static str
+    {
+        match self
+        {
+            LogLevel::Quiet => "quiet",
+            LogLevel::Normal => "info",
+            LogLevel::Verbose => "verbose",
+            LogLevel::Trace => "trace"
+        }
+    }
+}
+
+/// Applies the global verbosity, log format and colour options for the rest of the program.
+/// One --verbose switches to verbose, two or more to trace.
+pub fn configure(verbose: u8, quiet: bool, color: ColorChoice, format: LogFormat)
+{
+    let level = match (quiet, verbose)
+    {
+        (true, _) => LogLevel::Quiet,
+        (false, 0) => LogLevel::Normal,
+        (false, 1) => LogLevel::Verbose,
+        (false, _) => LogLevel::Trace
+    };
+
+    let json = format == LogFormat::Json;
+
+    let color = match color
+    {
+        _ if json => false,
+        ColorChoice::Auto => std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false
+    };
+
+    // bars redraw a single line, so they would be torn apart by the per-file lines of verbose output
+    let progress_bars = !json && level == LogLevel::Normal && std::io::stderr().is_terminal();
+
+    LEVEL.store(level as u8, Ordering::Relaxed);
+    JSON.store(json, Ordering::Relaxed);
+    COLOR.store(color, Ordering::Relaxed);
+    PROGRESS_BARS.store(progress_bars, Ordering::Relaxed);
+}
+
+pub fn level() -> LogLevel
+{
+    match LEVEL.load(Ordering::Relaxed)
+    {
+        0 => LogLevel::Quiet,
+        1 => LogLevel::Normal,
+        2 => LogLevel::Verbose,
+        _ => LogLevel::Trace
+    }
+}
+
+/// Progress messages are suppressed, only warnings and errors are printed
+pub fn is_quiet() -> bool
+{
+    level() == LogLevel::Quiet
+}
+
+/// Details like every converted file are printed
+pub fn is_verbose() -> bool
+{
+    level() >= LogLevel::Verbose
+}
+
+/// Log records are printed as one JSON object per line on stderr, keeping stdout free for the output of commands
+pub fn is_json() -> bool
+{
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Progress messages, printed unless quiet
+pub fn info(message: &str)
+{
+    log(LogLevel::Normal, message, Map::new());
+}
+
+/// Like [info] with additional fields for the JSON log, the text log only shows the message
+pub fn info_with(message: &str, fields: Value)
+{
+    log(LogLevel::Normal, message, into_fields(fields));
+}
+
+/// Printed with --verbose
+pub fn verbose(message: &str)
+{
+    log(LogLevel::Verbose, message, Map::new());
+}
+
+/// Printed with -vv
+pub fn trace(message: &str)
+{
+    log(LogLevel::Trace, message, Map::new());
+}
+
+/// Always printed, to stderr
+pub fn warning(message: &str)
+{
+    if is_json()
+    {
+        write_record("warning", message, Map::new());
+        return;
+    }
+
+    eprintln!("{} {message}", warning_label());
+}
+
+/// Always printed, to stderr
+pub fn error(message: &str)
+{
+    error_with(message, Value::Null);
+}
+
+/// Like [error] with additional fields for the JSON log, the text log only shows the message
+pub fn error_with(message: &str, fields: Value)
+{
+    if is_json()
+    {
+        write_record("error", message, into_fields(fields));
+        return;
+    }
+
+    eprintln!("{} {message}", error_label());
+}
+
+/// Output of external tools and hooks, printed unless quiet
+pub fn tool_output(stdout: &str, stderr: &str)
+{
+    if is_quiet()
+    {
+        return;
+    }
+
+    if is_json()
+    {
+        for line in stdout.lines().chain(stderr.lines()).filter(|line| !line.trim().is_empty())
+        {
+            write_record(LogLevel::Normal.name(), line, into_fields(json!({ "event": "tool_output" })));
+        }
+
+        return;
+    }
+
+    print!("{stdout}");
+    eprint!("{stderr}");
+}
+
+fn log(level: LogLevel, message: &str, fields: Map<String, Value>)
+{
+    if self::level() < level
+    {
+        return;
+    }
+
+    if is_json()
+    {
+        write_record(level.name(), message, fields);
+        return;
+    }
+
+    println!("{message}");
+}
+
+fn write_record(level: &str, message: &str, fields: Map<String, Value>)
+{
+    let mut record = Map::new();
+
+    record.insert(String::from("level"), Value::from(level));
+    record.insert(String::from("message"), Value::from(message));
+    record.extend(fields);
+
+    eprintln!("{}", Value::Object(record));
+}
+
+fn into_fields(fields: Value) -> Map<String, Value>
+{
+    match fields
+    {
+        Value::Object(fields) => fields,
+        _ => Map::new()
+    }
+}
+
+/// Counts the finished items of a stage, drawn as a bar on terminals and reported as progress records in the JSON log.
+/// Items may finish on any thread.
+pub struct Progress
+{
+    stage: String,
+    total: usize,
+    done: AtomicUsize
+}
+
+impl Progress
+{
+    const BAR_WIDTH: usize = 30;
+
+    pub fn start(stage: &str, total: usize) -> Self
+    {
+        let progress = Self {
+            stage: String::from(stage),
+            total,
+            done: AtomicUsize::new(0),
+        };
+
+        if total > 0
+        {
+            progress.report(0);
+        }
+
+        progress
+    }
+
+    pub fn advance(&self)
+    {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+
+        self.report(done);
+    }
+
+    /// Ends the bar's line, further output starts on a new one
+    pub fn finish(self)
+    {
+        if self.total > 0 && PROGRESS_BARS.load(Ordering::Relaxed)
+        {
+            eprintln!();
+        }
+    }
+
+    fn report(&self, done: usize)
+    {
+        if is_quiet()
+        {
+            return;
+        }
+
+        if is_json()
+        {
+            let fields = json!({ "event": "progress", "stage": self.stage, "done": done, "total": self.total });
+
+            write_record(LogLevel::Normal.name(), &format!("{} {done}/{}", self.stage, self.total), into_fields(fields));
+            return;
+        }
+
+        if !PROGRESS_BARS.load(Ordering::Relaxed)
+        {
+            return;
+        }
+
+        let filled = (done * Self::BAR_WIDTH).checked_div(self.total).unwrap_or(Self::BAR_WIDTH).min(Self::BAR_WIDTH);
+
+        let bar = format!("{}{}", "#".repeat(filled), ".".repeat(Self::BAR_WIDTH - filled));
+
+        let mut stderr = std::io::stderr().lock();
+
+        let _ = write!(stderr, "\r{:<8} [{bar}] {done}/{}", self.stage, self.total);
+        let _ = stderr.flush();
+    }
+}
+
+pub fn error_label() -> String
+{
+    paint("error:", "1;31")
+}
+
+pub fn warning_label() -> String
+{
+    paint("warning:", "1;33")
+}
+
+pub fn success_label(text: &str) -> String
+{
+    paint(text, "32")
+}
+
+pub fn failure_label(text: &str) -> String
+{
+    paint(text, "31")
+}
+
+fn paint(text: &str, style: &str) -> String
+{
+    if COLOR.load(Ordering::Relaxed)
+    {
+        return format!("\x1b[{style}m{text}\x1b[0m");
+    }
+
+    String::from(text)
+}
\ No newline at end of file
@@ -0,0 +1,147 @@
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::converters::converter_registry::ConversionDirection;
+use crate::converters::{FileConverter, RegisteredConverter};
+use crate::tools::tool_invocation::ToolInvocation;
+use crate::tools::tool_runner::{ToolKind, ToolRunner};
+use crate::utils::sha1_of_file;
+
+const INPUT_PLACEHOLDER: &str = "{input}";
+const OUTPUT_PLACEHOLDER: &str = "{output}";
+
+/// Runs a user defined command from the armapack.toml, e.g. a script converting .wav to .ogg.
+/// The first element is the program, {input} and {output} in the arguments are replaced by the absolute file paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandConverter
+{
+    pub command: Vec<String>,
+
+    /// Timeout from the path.json, like for the built-in converters
+    pub timeout: Option<Duration>
+}
+
+impl CommandConverter
+{
+    /// The program name, shown as the converter in build plans
+    pub fn name(&self) -> String {
+        let program = self.command.first().map(String::as_str).unwrap_or_default();
+
+        Path::new(program).file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from(program))
+    }
+
+    fn run(&self, source: &Path, destination: &Path) -> Result<(), Error> {
+        let Some((program, arguments)) = self.command.split_first() else {
+            return Err(Error::other("Converter command is empty"));
+        };
+
+        let input_path = source.canonicalize()?;
+        let output_path = std::path::absolute(destination)?;
+
+        let mut invocation = ToolInvocation::new(program, None);
+
+        for argument in arguments {
+            invocation = match argument.as_str() {
+                INPUT_PLACEHOLDER => invocation.path_arg(&input_path),
+                OUTPUT_PLACEHOLDER => invocation.path_arg(&output_path),
+                _ => invocation.arg(argument
+                    .replace(INPUT_PLACEHOLDER, &input_path.to_string_lossy())
+                    .replace(OUTPUT_PLACEHOLDER, &output_path.to_string_lossy()))
+            };
+        }
+
+        if let Some(input_folder) = input_path.parent() {
+            invocation = invocation.current_dir(input_folder);
+        }
+
+        ToolRunner::new(ToolKind::ConverterCommand, self.timeout)
+            .run(&invocation)
+            .map_err(Error::other)?;
+
+        // a command exiting successfully without writing its output would otherwise only fail once the file is missing from the pbo
+        if !output_path.is_file() {
+            let message = format!("{} exited successfully but did not create {:?}", self.name(), PathBuf::from(destination));
+
+            return Err(Error::new(std::io::ErrorKind::NotFound, message));
+        }
+
+        Ok(())
+    }
+}
+
+impl FileConverter for CommandConverter
+{
+    type BinarizeError = Error;
+    type DebinarizeError = Error;
+
+    fn binarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::BinarizeError> {
+        self.run(source.as_ref(), destination.as_ref())
+    }
+
+    fn debinarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::DebinarizeError> {
+        self.run(source.as_ref(), destination.as_ref())
+    }
+}
+
+impl RegisteredConverter for CommandConverter
+{
+    fn convert(&self, source: &Path, destination: &Path, direction: ConversionDirection) -> Result<(), Error> {
+        match direction {
+            ConversionDirection::Pack => self.binarize(source, destination),
+            ConversionDirection::Unpack => self.debinarize(source, destination)
+        }
+    }
+
+    /// The command and the contents of the files in it, so editing the script (or a file passed to it) invalidates cached conversions
+    fn identity(&self) -> String {
+        let file_hashes: Vec<String> = self.command.iter()
+            .filter(|part| Path::new(part).is_file())
+            .filter_map(|part| sha1_of_file(part).ok())
+            .collect();
+
+        format!("{}|{}|{}", ToolKind::ConverterCommand, self.command.join(" "), file_hashes.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::converters::RegisteredConverter;
+    use super::CommandConverter;
+
+    #[test]
+    fn editing_the_script_changes_the_identity() {
+        let folder = std::env::temp_dir().join(format!("armapack-command-converter-test-{}", std::process::id()));
+
+        fs::create_dir_all(&folder).unwrap();
+
+        let script = folder.join("wav2ogg.sh");
+        let settings = folder.join("settings.txt");
+
+        fs::write(&script, "ffmpeg -i \"$1\" \"$2\"").unwrap();
+        fs::write(&settings, "quality 5").unwrap();
+
+        let converter = CommandConverter {
+            command: vec![script.display().to_string(), String::from("{input}"), settings.display().to_string(), String::from("{output}")],
+            timeout: None,
+        };
+
+        let original = converter.identity();
+
+        assert_eq!(converter.identity(), original);
+
+        fs::write(&script, "ffmpeg -i \"$1\" -q 9 \"$2\"").unwrap();
+
+        let edited_script = converter.identity();
+
+        assert_ne!(edited_script, original);
+
+        fs::write(&settings, "quality 9").unwrap();
+
+        assert_ne!(converter.identity(), edited_script);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
\ No newline at end of file
@@ -1,16 +1,19 @@
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 use std::path::Path;
-use std::process::Command;
-use crate::converters::FileConverter;
-
-pub trait ConfigConverter : FileConverter {}
+use std::time::Duration;
+use crate::converters::converter_registry::ConversionDirection;
+use crate::converters::{FileConverter, RegisteredConverter};
+use crate::json_files::path_json::model::launcher::Launcher;
+use crate::tools::tool_invocation::ToolInvocation;
+use crate::tools::tool_runner::{ToolKind, ToolRunner};
 
 pub struct ArmaToolsConfigConverter<P: AsRef<Path>>
 {
-    pub tool_path: P
-}
+    pub tool_path: P,
 
-impl<P: AsRef<Path>> ConfigConverter for ArmaToolsConfigConverter<P> {}
+    pub launcher: Option<Launcher>,
+    pub timeout: Option<Duration>
+}
 
 impl<P: AsRef<Path>> FileConverter for ArmaToolsConfigConverter<P>
 {
@@ -18,81 +21,44 @@ impl<P: AsRef<Path>> FileConverter for ArmaToolsConfigConverter<P>
     type DebinarizeError = Error;
 
     fn binarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::BinarizeError> {
-        let cpp_path = source.as_ref().canonicalize().unwrap();
-        let bin_path = destination.as_ref();
-
-        let cpp_path_ref = &cpp_path;
-        let bin_path_ref = &bin_path;
-
-        println!("Binarizing {cpp_path_ref:?} to {bin_path_ref:?}");
-
-        let output = Command::new("CfgConvert.exe")
-            .arg("-bin")
-            .arg("-dst")
-            .arg(bin_path.as_os_str())
-            .arg(cpp_path.as_os_str())
-            .output()?;
-
-        if !output.status.success() {
-            let code = output.status.code().unwrap_or(0);
-
-            let stdout = String::from_utf8(output.stdout);
-            let stderr = String::from_utf8(output.stderr);
-
-            if let Ok(stdout) = stdout {
-                println!("{}", stdout);
-            }
-
-            if let Ok(stderr) = stderr {
-                eprintln!("{}", stderr);
-            }
-
-            println!("Paa path: {:?}", cpp_path.as_os_str());
+        self.run_tool("-bin", source, destination)
+    }
 
-            let output = format!("Cpp Binarization Process failed, error code: {code}");
+    fn debinarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::DebinarizeError> {
+        self.run_tool("-txt", source, destination)
+    }
+}
 
-            return Err(Error::new(ErrorKind::Other, output));
+impl<P: AsRef<Path> + Send + Sync> RegisteredConverter for ArmaToolsConfigConverter<P>
+{
+    fn convert(&self, source: &Path, destination: &Path, direction: ConversionDirection) -> Result<(), Error> {
+        match direction {
+            ConversionDirection::Pack => self.binarize(source, destination),
+            ConversionDirection::Unpack => self.debinarize(source, destination)
         }
-
-        Ok(())
     }
 
-    fn debinarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::DebinarizeError> {
-        let bin_path = source.as_ref().canonicalize().unwrap();
-        let cpp_path = destination.as_ref();
-
-        let cpp_path_ref = &cpp_path;
-        let bin_path_ref = &bin_path;
+    fn identity(&self) -> String {
+        format!("{}|{}", ToolKind::ConfigConverter, self.tool_path.as_ref().display())
+    }
+}
 
-        println!("Debinarizing {bin_path_ref:?} to {cpp_path_ref:?}");
+impl<P: AsRef<Path>> ArmaToolsConfigConverter<P>
+{
+    /// Runs the configured CfgConvert with the output format flag (-bin or -txt)
+    fn run_tool<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, format: &str, source: SourcePath, destination: DestinationPath) -> Result<(), Error> {
+        let source_path = source.as_ref().canonicalize()?;
+        let destination_path = destination.as_ref();
 
-        let output = Command::new("CfgConvert.exe")
-            .arg("-txt")
+        let invocation = ToolInvocation::new(self.tool_path.as_ref(), self.launcher.as_ref())
+            .arg(format)
             .arg("-dst")
-            .arg(cpp_path.as_os_str())
-            .arg(bin_path.as_os_str())
-            .output()?;
+            .path_arg(destination_path)
+            .path_arg(&source_path);
 
-        if !output.status.success() {
-            let code = output.status.code().unwrap_or(0);
-
-            let stdout = String::from_utf8(output.stdout);
-            let stderr = String::from_utf8(output.stderr);
-
-            if let Ok(stdout) = stdout {
-                println!("{}", stdout);
-            }
-
-            if let Ok(stderr) = stderr {
-                eprintln!("{}", stderr);
-            }
-
-            println!("Paa path: {:?}", cpp_path.as_os_str());
-
-            let output = format!("Bin Debinarization Process failed, error code: {code}");
-
-            return Err(Error::new(ErrorKind::Other, output));
-        }
+        ToolRunner::new(ToolKind::ConfigConverter, self.timeout)
+            .run(&invocation)
+            .map_err(Error::other)?;
 
         Ok(())
     }
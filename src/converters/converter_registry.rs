@@ -0,0 +1,406 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use crate::converters::command_converter::CommandConverter;
+use crate::converters::RegisteredConverter;
+use crate::toml_files::armapack_toml::model::armapack_toml::{ConverterRuleSection, ConvertersSection};
+
+/// The converters shipped with the tool, selected by name in the converter rules of the armapack.toml
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltinConverter
+{
+    Paa,
+    Config,
+    Rvmat
+}
+
+impl BuiltinConverter
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            BuiltinConverter::Paa => "paa_converter",
+            BuiltinConverter::Config => "config_converter",
+            BuiltinConverter::Rvmat => "rvmat_converter"
+        }
+    }
+
+    /// Disabled converters copy their files as-is
+    pub fn is_enabled(&self, converters: &ConvertersSection) -> bool
+    {
+        let enabled = match self
+        {
+            BuiltinConverter::Paa => converters.paa,
+            BuiltinConverter::Config => converters.config,
+            BuiltinConverter::Rvmat => converters.rvmat
+        };
+
+        enabled.unwrap_or(true)
+    }
+}
+
+/// Packing turns raw files into Arma ready formats, unpacking turns them back into editable formats
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionDirection
+{
+    #[default]
+    Pack,
+    Unpack
+}
+
+/// The files a rule applies to
+#[derive(Debug, Clone)]
+pub enum RuleInput
+{
+    /// Files with the extension, in any case
+    Extension(String),
+
+    /// Files whose name (or path, if the pattern contains a /) matches the glob, in any case
+    Pattern(GlobMatcher)
+}
+
+/// What converts the files of a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleConverter
+{
+    Builtin(BuiltinConverter),
+    Command(CommandConverter)
+}
+
+impl RuleConverter
+{
+    pub fn name(&self) -> String
+    {
+        match self
+        {
+            RuleConverter::Builtin(converter) => String::from(converter.name()),
+            RuleConverter::Command(command) => command.name()
+        }
+    }
+
+    /// Commands cannot be disabled, their rule is removed instead
+    pub fn is_enabled(&self, converters: &ConvertersSection) -> bool
+    {
+        match self
+        {
+            RuleConverter::Builtin(converter) => converter.is_enabled(converters),
+            RuleConverter::Command(_) => true
+        }
+    }
+}
+
+/// Converts the files matching the input into files with the output extension
+#[derive(Debug, Clone)]
+pub struct ConverterRule
+{
+    pub input: RuleInput,
+    pub converter: RuleConverter,
+    pub direction: ConversionDirection,
+
+    /// None converts the file in place, keeping its path
+    pub output_extension: Option<String>,
+
+    /// File names without extension the rule does not apply to, e.g. texHeaders.bin is not a config
+    pub excluded_stems: Vec<String>
+}
+
+impl ConverterRule
+{
+    fn new(extension: &str, converter: BuiltinConverter, direction: ConversionDirection, output_extension: Option<&str>) -> Self
+    {
+        Self {
+            input: RuleInput::Extension(String::from(extension)),
+            converter: RuleConverter::Builtin(converter),
+            direction,
+            output_extension: output_extension.map(String::from),
+            excluded_stems: vec![],
+        }
+    }
+
+    pub fn matches(&self, path: &Path, direction: ConversionDirection) -> bool
+    {
+        if self.direction != direction
+        {
+            return false;
+        }
+
+        let matches_input = match &self.input
+        {
+            RuleInput::Extension(extension) => path.extension().is_some_and(|file_extension| file_extension.to_string_lossy().eq_ignore_ascii_case(extension)),
+            RuleInput::Pattern(pattern) => pattern.is_match(path)
+        };
+
+        if !matches_input
+        {
+            return false;
+        }
+
+        let file_stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        !self.excluded_stems.iter().any(|excluded_stem| file_stem.eq_ignore_ascii_case(excluded_stem))
+    }
+
+    pub fn output_path(&self, input: &Path) -> PathBuf
+    {
+        match &self.output_extension
+        {
+            Some(output_extension) => input.with_extension(output_extension),
+            None => PathBuf::from(input)
+        }
+    }
+
+    /// The tools cannot overwrite their input, so in place conversions write to this file first (e.g. .brvmat)
+    pub fn temporary_path(&self, input: &Path) -> PathBuf
+    {
+        let extension = input.extension().unwrap_or_default().to_string_lossy();
+
+        let prefix = match self.direction
+        {
+            ConversionDirection::Pack => "b",
+            ConversionDirection::Unpack => "db"
+        };
+
+        input.with_extension(format!("{prefix}{extension}"))
+    }
+}
+
+/// Maps files to the rule converting them and holds the built-in converter implementations the rules refer to.
+/// Rules added later take precedence, so rules from the armapack.toml override the built-in ones.
+#[derive(Default)]
+pub struct ConverterRegistry
+{
+    rules: Vec<ConverterRule>,
+    converters: BTreeMap<BuiltinConverter, Box<dyn RegisteredConverter>>
+}
+
+impl ConverterRegistry
+{
+    /// The built-in rules: images to .paa, config.cpp to config.bin and .rvmat in place, plus their way back
+    pub fn builtin() -> Self
+    {
+        let pack = ConversionDirection::Pack;
+        let unpack = ConversionDirection::Unpack;
+
+        let mut config_unpack_rule = ConverterRule::new("bin", BuiltinConverter::Config, unpack, Some("cpp"));
+        config_unpack_rule.excluded_stems.push(String::from("texheaders"));
+
+        let rules = vec![
+            ConverterRule::new("pac", BuiltinConverter::Paa, pack, Some("paa")),
+            ConverterRule::new("tga", BuiltinConverter::Paa, pack, Some("paa")),
+            ConverterRule::new("jpg", BuiltinConverter::Paa, pack, Some("paa")),
+            ConverterRule::new("png", BuiltinConverter::Paa, pack, Some("paa")),
+            ConverterRule::new("cpp", BuiltinConverter::Config, pack, Some("bin")),
+            ConverterRule::new("rvmat", BuiltinConverter::Rvmat, pack, None),
+            ConverterRule::new("paa", BuiltinConverter::Paa, unpack, Some("png")),
+            config_unpack_rule,
+            ConverterRule::new("rvmat", BuiltinConverter::Rvmat, unpack, None),
+        ];
+
+        Self {
+            rules,
+            converters: BTreeMap::new(),
+        }
+    }
+
+    /// The built-in rules followed by the rules of the armapack.toml, invalid rules are reported by [Self::check_rules]
+    /// when the armapack.toml is loaded and left out here
+    pub fn for_manifest(converters: &ConvertersSection) -> Self
+    {
+        let mut registry = Self::builtin();
+
+        for section in &converters.rules
+        {
+            if let Ok(rule) = registry.rule_from_section(section)
+            {
+                registry.add_rule(rule);
+            }
+        }
+
+        registry
+    }
+
+    /// Checks the converter rules of the armapack.toml, naming the first invalid rule
+    pub fn check_rules(converters: &ConvertersSection) -> Result<(), String>
+    {
+        let registry = Self::builtin();
+
+        for (index, section) in converters.rules.iter().enumerate()
+        {
+            registry.rule_from_section(section)
+                .map_err(|message| format!("Invalid converters.rules[{index}]: {message}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Rules for a built-in converter without an output extension use the one of the converter's built-in rule,
+    /// commands without one convert in place
+    fn rule_from_section(&self, section: &ConverterRuleSection) -> Result<ConverterRule, String>
+    {
+        let input = match (&section.extension, &section.pattern)
+        {
+            (Some(extension), None) => RuleInput::Extension(extension.trim_start_matches('.').to_string()),
+            (None, Some(pattern)) => RuleInput::Pattern(Self::compile_pattern(pattern)?),
+            _ => return Err(String::from("set either extension or pattern"))
+        };
+
+        let converter = match (section.converter, &section.command)
+        {
+            (Some(converter), None) => RuleConverter::Builtin(converter),
+            (None, Some(command)) if command.is_empty() => return Err(String::from("command is empty")),
+            (None, Some(command)) => RuleConverter::Command(CommandConverter { command: command.clone(), timeout: None }),
+            _ => return Err(String::from("set either converter or command"))
+        };
+
+        let output_extension = match (&section.output_extension, &converter)
+        {
+            (Some(output_extension), _) => Some(output_extension.trim_start_matches('.').to_string()),
+            (None, RuleConverter::Builtin(_)) => self.rules.iter()
+                .find(|builtin_rule| builtin_rule.converter == converter && builtin_rule.direction == section.direction)
+                .and_then(|builtin_rule| builtin_rule.output_extension.clone()),
+            (None, RuleConverter::Command(_)) => None
+        };
+
+        Ok(ConverterRule {
+            input,
+            converter,
+            direction: section.direction,
+            output_extension,
+            excluded_stems: vec![],
+        })
+    }
+
+    /// Patterns without a / match the file name in any folder, like in .gitignore files
+    fn compile_pattern(pattern: &str) -> Result<GlobMatcher, String>
+    {
+        let pattern = pattern.trim_start_matches('/');
+
+        let full_pattern = if pattern.starts_with("**/") { String::from(pattern) } else { format!("**/{pattern}") };
+
+        GlobBuilder::new(&full_pattern)
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build()
+            .map(|glob| glob.compile_matcher())
+            .map_err(|error| format!("invalid pattern \"{pattern}\": {error}"))
+    }
+
+    pub fn add_rule(&mut self, rule: ConverterRule)
+    {
+        self.rules.push(rule);
+    }
+
+    /// Applies the timeout from the path.json to the commands of all rules
+    pub fn set_command_timeout(&mut self, timeout: Option<Duration>)
+    {
+        for rule in &mut self.rules
+        {
+            if let RuleConverter::Command(command) = &mut rule.converter
+            {
+                command.timeout = timeout;
+            }
+        }
+    }
+
+    pub fn register<C: RegisteredConverter + 'static>(&mut self, converter: BuiltinConverter, implementation: C)
+    {
+        self.converters.insert(converter, Box::new(implementation));
+    }
+
+    /// The rule converting the file in the direction, None if no rule applies
+    pub fn find(&self, path: &Path, direction: ConversionDirection) -> Option<&ConverterRule>
+    {
+        self.rules.iter().rev().find(|rule| rule.matches(path, direction))
+    }
+
+    /// The implementation converting the files of a rule, None if the built-in converter was not registered
+    pub fn converter<'a>(&'a self, converter: &'a RuleConverter) -> Option<&'a dyn RegisteredConverter>
+    {
+        match converter
+        {
+            RuleConverter::Builtin(converter) => self.converters.get(converter).map(|implementation| implementation.as_ref()),
+            RuleConverter::Command(command) => Some(command)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::path::Path;
+    use crate::converters::command_converter::CommandConverter;
+    use crate::toml_files::armapack_toml::model::armapack_toml::ConvertersSection;
+    use super::{BuiltinConverter, ConversionDirection, ConverterRegistry, RuleConverter};
+
+    fn converter_for(registry: &ConverterRegistry, path: &str, direction: ConversionDirection) -> Option<RuleConverter>
+    {
+        registry.find(Path::new(path), direction).map(|rule| rule.converter.clone())
+    }
+
+    #[test]
+    fn extensions_match_in_any_case()
+    {
+        let registry = ConverterRegistry::builtin();
+
+        assert_eq!(converter_for(&registry, "data/tex.PNG", ConversionDirection::Pack), Some(RuleConverter::Builtin(BuiltinConverter::Paa)));
+        assert_eq!(converter_for(&registry, "Config.Cpp", ConversionDirection::Pack), Some(RuleConverter::Builtin(BuiltinConverter::Config)));
+        assert_eq!(converter_for(&registry, "data/mat.RVMAT", ConversionDirection::Unpack), Some(RuleConverter::Builtin(BuiltinConverter::Rvmat)));
+        assert_eq!(converter_for(&registry, "TEXHEADERS.BIN", ConversionDirection::Unpack), None);
+        assert_eq!(converter_for(&registry, "readme.md", ConversionDirection::Pack), None);
+    }
+
+    #[test]
+    fn patterns_match_in_any_case()
+    {
+        let converters: ConvertersSection = toml::from_str(r#"
+            [[rules]]
+            pattern = "sounds/*.wav"
+            command = ["convert.sh", "{input}", "{output}"]
+            output_extension = "ogg"
+        "#).unwrap();
+
+        let registry = ConverterRegistry::for_manifest(&converters);
+
+        let command = RuleConverter::Command(CommandConverter {
+            command: vec![String::from("convert.sh"), String::from("{input}"), String::from("{output}")],
+            timeout: None,
+        });
+
+        assert_eq!(converter_for(&registry, "addons/main/Sounds/Shot.WAV", ConversionDirection::Pack), Some(command));
+        assert_eq!(converter_for(&registry, "addons/main/music/shot.wav", ConversionDirection::Pack), None);
+    }
+
+    #[test]
+    fn manifest_rules_take_priority_over_builtin_rules()
+    {
+        let converters: ConvertersSection = toml::from_str(r#"
+            [[rules]]
+            extension = "png"
+            command = ["optimize.sh", "{input}", "{output}"]
+
+            [[rules]]
+            extension = ".tif"
+            converter = "paa"
+        "#).unwrap();
+
+        let registry = ConverterRegistry::for_manifest(&converters);
+
+        let png_rule = registry.find(Path::new("data/tex.png"), ConversionDirection::Pack).unwrap();
+
+        assert!(matches!(&png_rule.converter, RuleConverter::Command(command) if command.command[0] == "optimize.sh"));
+        assert_eq!(png_rule.output_extension, None);
+
+        let tif_rule = registry.find(Path::new("data/tex.TIF"), ConversionDirection::Pack).unwrap();
+
+        assert_eq!(tif_rule.converter, RuleConverter::Builtin(BuiltinConverter::Paa));
+        assert_eq!(tif_rule.output_extension.as_deref(), Some("paa"));
+
+        // the built-in rules still apply to everything the manifest does not override
+        assert_eq!(converter_for(&registry, "data/tex.jpg", ConversionDirection::Pack), Some(RuleConverter::Builtin(BuiltinConverter::Paa)));
+    }
+}
\ No newline at end of file
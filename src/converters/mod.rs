@@ -1,9 +1,11 @@
 use std::error::Error;
 use std::path::Path;
+use crate::converters::converter_registry::ConversionDirection;
 
 pub mod config_converter;
-pub mod rvmat_converter;
 pub mod paa_converter;
+pub mod converter_registry;
+pub mod command_converter;
 
 pub trait FileConverter
 {
@@ -13,4 +15,13 @@ pub trait FileConverter
     fn binarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::BinarizeError>;
 
     fn debinarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::DebinarizeError>;
+}
+
+/// [FileConverter] without generic parameters, so converters can be held by a [converter_registry::ConverterRegistry]
+pub trait RegisteredConverter : Send + Sync
+{
+    fn convert(&self, source: &Path, destination: &Path, direction: ConversionDirection) -> Result<(), std::io::Error>;
+
+    /// Identifies the converter and its tool in the build cache
+    fn identity(&self) -> String;
 }
\ No newline at end of file
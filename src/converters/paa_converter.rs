@@ -1,18 +1,21 @@
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 use std::path::Path;
-use std::process::Command;
-use crate::converters::FileConverter;
+use std::time::Duration;
+use crate::converters::converter_registry::ConversionDirection;
+use crate::converters::{FileConverter, RegisteredConverter};
+use crate::json_files::path_json::model::launcher::Launcher;
+use crate::tools::tool_invocation::ToolInvocation;
+use crate::tools::tool_runner::{ToolKind, ToolRunner};
 use crate::utils::check_source_and_destination;
 
-pub trait PAAConverter : FileConverter {}
-
 pub struct ArmaToolsPAAConverter<P: AsRef<Path>> {
-    pub tool_path: P
+    pub tool_path: P,
+    pub launcher: Option<Launcher>,
+    pub timeout: Option<Duration>
 }
 
 type ArmaToolsPAAConverterError = Error;
 
-impl<P: AsRef<Path>> PAAConverter for ArmaToolsPAAConverter<P> {}
 
 impl<P: AsRef<Path>> FileConverter for ArmaToolsPAAConverter<P>
 {
@@ -20,45 +23,43 @@ impl<P: AsRef<Path>> FileConverter for ArmaToolsPAAConverter<P>
     type DebinarizeError = ArmaToolsPAAConverterError;
 
     fn binarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::BinarizeError> {
-        self.convert(source, destination)
+        self.run_tool(source, destination)
     }
 
     fn debinarize<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), Self::DebinarizeError> {
-        self.convert(source, destination)
+        self.run_tool(source, destination)
     }
 }
 
-impl<P: AsRef<Path>> ArmaToolsPAAConverter<P>
+impl<P: AsRef<Path> + Send + Sync> RegisteredConverter for ArmaToolsPAAConverter<P>
 {
-    fn convert<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), ArmaToolsPAAConverterError> {
-        check_source_and_destination(source.as_ref(), destination.as_ref(), false, false)?;
-
-        let paa_path = source.as_ref().canonicalize().unwrap();
-        let png_path = destination.as_ref().canonicalize().unwrap();
-
-        let output = Command::new(&self.tool_path.as_ref())
-            .arg(paa_path.as_os_str())
-            .arg(png_path.as_os_str())
-            .output()?;
-
-        if !output.status.success() {
-            let code = output.status.code().unwrap_or(0);
+    fn convert(&self, source: &Path, destination: &Path, direction: ConversionDirection) -> Result<(), Error> {
+        match direction {
+            ConversionDirection::Pack => self.binarize(source, destination),
+            ConversionDirection::Unpack => self.debinarize(source, destination)
+        }
+    }
 
-            let stdout = String::from_utf8(output.stdout);
-            let stderr = String::from_utf8(output.stderr);
+    fn identity(&self) -> String {
+        format!("{}|{}", ToolKind::PaaConverter, self.tool_path.as_ref().display())
+    }
+}
 
-            if let Ok(stdout) = stdout {
-                println!("{}", stdout);
-            }
+impl<P: AsRef<Path>> ArmaToolsPAAConverter<P>
+{
+    fn run_tool<SourcePath: AsRef<Path>, DestinationPath: AsRef<Path>>(&self, source: SourcePath, destination: DestinationPath) -> Result<(), ArmaToolsPAAConverterError> {
+        check_source_and_destination(source.as_ref(), destination.as_ref(), false, false)?;
 
-            if let Ok(stderr) = stderr {
-                eprintln!("{}", stderr);
-            }
+        let paa_path = source.as_ref().canonicalize()?;
+        let png_path = destination.as_ref().canonicalize()?;
 
-            let output = format!("PAA Conversion Process failed, error code: {code}");
+        let invocation = ToolInvocation::new(self.tool_path.as_ref(), self.launcher.as_ref())
+            .path_arg(&paa_path)
+            .path_arg(&png_path);
 
-            return Err(Error::new(ErrorKind::Other, output));
-        }
+        ToolRunner::new(ToolKind::PaaConverter, self.timeout)
+            .run(&invocation)
+            .map_err(Error::other)?;
 
         Ok(())
     }
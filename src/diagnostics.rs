@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use serde_json::json;
+use crate::console;
+use crate::packers::mod_packer_error::{ModPackerError, PipelineStage};
+use crate::tools::tool_runner::ToolError;
+
+/// Prints the error with its stage, path and causes.
+/// Aggregated errors are printed as a summary grouped by stage and file, or as one record per error in the JSON log.
+pub fn report_error(error: &ModPackerError)
+{
+    let errors = error.flatten();
+
+    if console::is_json()
+    {
+        for error in errors
+        {
+            report_error_record(error);
+        }
+
+        return;
+    }
+
+    if errors.len() == 1
+    {
+        console::error(&error.to_string());
+        eprintln!("  stage: {}", error.stage());
+        eprintln!("  path: {}", error.path().display());
+
+        let mut source = error.source();
+
+        while let Some(cause) = source
+        {
+            eprintln!("  caused by: {cause}");
+            source = cause.source();
+        }
+
+        report_tool_details(error, "  ");
+
+        return;
+    }
+
+    let mut grouped_errors: BTreeMap<PipelineStage, BTreeMap<&Path, Vec<&ModPackerError>>> = BTreeMap::new();
+
+    for error in &errors
+    {
+        grouped_errors.entry(error.stage())
+            .or_default()
+            .entry(error.path())
+            .or_default()
+            .push(error);
+    }
+
+    console::error(&format!("{} problems found", errors.len()));
+
+    for (stage, files) in grouped_errors
+    {
+        eprintln!();
+        eprintln!("stage {stage}: {} files failed", files.len());
+
+        for (path, file_errors) in files
+        {
+            eprintln!("  {}", path.display());
+
+            for file_error in file_errors
+            {
+                eprintln!("    - {}", error_chain(file_error));
+
+                report_tool_details(file_error, "      ");
+            }
+        }
+    }
+}
+
+fn report_error_record(error: &ModPackerError)
+{
+    let mut causes: Vec<String> = vec![];
+
+    let mut source = error.source();
+
+    while let Some(cause) = source
+    {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+
+    let mut fields = json!({
+        "stage": error.stage().to_string(),
+        "path": error.path(),
+        "exit_code": error.exit_code(),
+        "causes": causes,
+    });
+
+    if let Some(tool_error) = find_tool_error(error)
+    {
+        fields["command"] = json!(tool_error.invocation().command_line);
+
+        if let Some(output) = tool_error.output()
+        {
+            fields["tool_output"] = json!(output.stdout.lines().chain(output.stderr.lines()).filter(|line| !line.trim().is_empty()).collect::<Vec<&str>>());
+        }
+    }
+
+    console::error_with(&error.to_string(), fields);
+}
+
+/// Prints the output of a failed external tool in the error chain and, with --verbose, how it was called
+fn report_tool_details(error: &(dyn Error + 'static), indent: &str)
+{
+    let Some(tool_error) = find_tool_error(error) else {
+        return;
+    };
+
+    if let Some(output) = tool_error.output()
+    {
+        for line in output.stdout.lines().chain(output.stderr.lines()).filter(|line| !line.trim().is_empty())
+        {
+            eprintln!("{indent}| {line}");
+        }
+    }
+
+    if console::is_verbose()
+    {
+        let invocation = tool_error.invocation();
+
+        eprintln!("{indent}command: {}", invocation.command_line);
+
+        if let Some(working_directory) = &invocation.working_directory
+        {
+            eprintln!("{indent}working directory: {working_directory}");
+        }
+    }
+}
+
+/// The first failed external tool in the error chain
+fn find_tool_error<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a ToolError>
+{
+    let mut source: Option<&(dyn Error + 'static)> = Some(error);
+
+    while let Some(cause) = source
+    {
+        if let Some(tool_error) = ToolError::find(cause)
+        {
+            return Some(tool_error);
+        }
+
+        source = cause.source();
+    }
+
+    None
+}
+
+/// Joins an error and all of its sources into a single line
+pub fn error_chain(error: &dyn Error) -> String
+{
+    let mut message = error.to_string();
+
+    let mut source = error.source();
+
+    while let Some(cause) = source
+    {
+        message.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+
+    message
+}
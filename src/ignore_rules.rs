@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use serde::Serialize;
+
+/// The ignore file and pattern that caused a path to be ignored
+#[derive(Serialize, Debug, Clone)]
+pub struct IgnoreMatch
+{
+    pub ignore_file: PathBuf,
+    pub line: Option<usize>,
+    pub pattern: String
+}
+
+/// All ignore files with a given name (e.g. .modignore) below a folder, evaluated with the same precedence as the
+/// [WalkBuilder] used for packing: the ignore file closest to a path decides, and whitelist patterns (`!pattern`) un-ignore.
+pub struct IgnoreRules
+{
+    matchers: Vec<(PathBuf, Gitignore)>
+}
+
+impl IgnoreRules
+{
+    pub fn load<P: AsRef<Path>>(root_folder: P, ignore_file_name: &str) -> Result<Self, ignore::Error>
+    {
+        let walk = WalkBuilder::new(&root_folder)
+            .standard_filters(false)
+            .add_custom_ignore_filename(ignore_file_name)
+            .build();
+
+        let mut matchers: Vec<(PathBuf, Gitignore)> = vec![];
+
+        for entry in walk
+        {
+            let entry = entry?;
+
+            if entry.file_name() != ignore_file_name || !entry.path().is_file()
+            {
+                continue;
+            }
+
+            let Some(folder) = entry.path().parent() else {
+                continue;
+            };
+
+            let mut builder = GitignoreBuilder::new(folder);
+
+            if let Some(error) = builder.add(entry.path())
+            {
+                return Err(error);
+            }
+
+            matchers.push((PathBuf::from(folder), builder.build()?));
+        }
+
+        // deepest folders first, they take precedence over their parents
+        matchers.sort_by_key(|(folder, _)| std::cmp::Reverse(folder.components().count()));
+
+        Ok(Self {
+            matchers
+        })
+    }
+
+    /// Returns the rule ignoring the path, or None if the path is not ignored
+    pub fn matched<P: AsRef<Path>>(&self, path: P, is_dir: bool) -> Option<IgnoreMatch>
+    {
+        let path = path.as_ref();
+
+        for (folder, matcher) in &self.matchers
+        {
+            if !path.starts_with(folder)
+            {
+                continue;
+            }
+
+            let matched = matcher.matched_path_or_any_parents(path, is_dir);
+
+            if matched.is_whitelist()
+            {
+                return None;
+            }
+
+            if let Some(glob) = matched.inner()
+            {
+                let ignore_file = glob.from().map(PathBuf::from).unwrap_or_else(|| folder.clone());
+
+                let line = Self::find_pattern_line(&ignore_file, glob.original());
+
+                return Some(IgnoreMatch {
+                    ignore_file,
+                    line,
+                    pattern: String::from(glob.original()),
+                });
+            }
+        }
+
+        None
+    }
+
+    pub fn is_ignored<P: AsRef<Path>>(&self, path: P, is_dir: bool) -> bool
+    {
+        self.matched(path, is_dir).is_some()
+    }
+
+    /// 1-based line of the pattern in the ignore file
+    fn find_pattern_line(ignore_file: &Path, pattern: &str) -> Option<usize>
+    {
+        let content = fs::read_to_string(ignore_file).ok()?;
+
+        content.lines()
+            .position(|line| line.trim() == pattern)
+            .map(|index| index + 1)
+    }
+}
@@ -4,5 +4,71 @@ use serde::{Deserialize, Serialize};
 #[serde(deny_unknown_fields)]
 pub struct KeyJson
 {
-    pub authority_name: String
-}
\ No newline at end of file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authority_names: Vec<String>
+}
+
+impl KeyJson
+{
+    /// All authorities this file asks to sign with, in declaration order and without duplicates
+    pub fn authorities(&self) -> Vec<String>
+    {
+        let mut authorities: Vec<String> = vec![];
+
+        let declared = self.authority_name.iter().chain(self.authority_names.iter());
+
+        for authority in declared
+        {
+            if !authorities.contains(authority)
+            {
+                authorities.push(authority.clone());
+            }
+        }
+
+        authorities
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::KeyJson;
+
+    fn authorities(json: &str) -> Vec<String>
+    {
+        serde_json::from_str::<KeyJson>(json).unwrap().authorities()
+    }
+
+    #[test]
+    fn old_format_has_a_single_authority()
+    {
+        assert_eq!(authorities(r#"{ "authority_name": "main" }"#), vec!["main"]);
+    }
+
+    #[test]
+    fn new_format_lists_the_authorities_in_order()
+    {
+        assert_eq!(authorities(r#"{ "authority_names": ["second", "first"] }"#), vec!["second", "first"]);
+    }
+
+    #[test]
+    fn single_authority_comes_before_the_list()
+    {
+        assert_eq!(authorities(r#"{ "authority_name": "main", "authority_names": ["other"] }"#), vec!["main", "other"]);
+    }
+
+    #[test]
+    fn duplicates_sign_only_once()
+    {
+        assert_eq!(authorities(r#"{ "authority_name": "main", "authority_names": ["other", "main", "other"] }"#), vec!["main", "other"]);
+    }
+
+    #[test]
+    fn no_authorities_without_either_field()
+    {
+        assert!(authorities("{}").is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Command the external tools are started through, e.g. wine, a container or a custom script.
+/// In the arguments, {tool} is replaced by the tool path and {cwd} by the working directory of the tool.
+/// Without {tool}, the tool path is appended after the arguments.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Launcher
+{
+    pub program: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<String>,
+
+    /// Whether paths passed to the tool are translated to Windows paths (/home/user -> Z:\home\user), defaults to true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows_paths: Option<bool>
+}
+
+impl Launcher
+{
+    pub fn wine() -> Self
+    {
+        Self {
+            program: String::from("wine"),
+            arguments: vec![],
+            windows_paths: Some(true),
+        }
+    }
+
+    pub fn uses_windows_paths(&self) -> bool
+    {
+        self.windows_paths.unwrap_or(true)
+    }
+}
\ No newline at end of file
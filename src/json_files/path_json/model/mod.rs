@@ -1 +1,2 @@
-pub mod path_json;
\ No newline at end of file
+pub mod path_json;
+pub mod launcher;
\ No newline at end of file
@@ -1,4 +1,6 @@
+use std::time::Duration;
 use serde::{Serialize, Deserialize};
+use crate::json_files::path_json::model::launcher::Launcher;
 
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -8,7 +10,35 @@ pub struct PathJson
     pub rvmat_converter_path: String,
     pub config_converter_path: String,
     pub pbo_packer_path: String,
-    pub pbo_signer_path: String
+    pub pbo_signer_path: String,
+
+    /// Starts every tool through this command instead of directly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launcher: Option<Launcher>,
+
+    /// Seconds a single tool call may take before it is stopped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>
+}
+
+impl PathJson
+{
+    pub fn timeout(&self) -> Option<Duration>
+    {
+        self.timeout_seconds.map(Duration::from_secs)
+    }
+
+    /// Every configured tool as (field name, path)
+    pub fn tools(&self) -> Vec<(&'static str, &str)>
+    {
+        vec![
+            ("paa_converter_path", &self.paa_converter_path),
+            ("rvmat_converter_path", &self.rvmat_converter_path),
+            ("config_converter_path", &self.config_converter_path),
+            ("pbo_packer_path", &self.pbo_packer_path),
+            ("pbo_signer_path", &self.pbo_signer_path)
+        ]
+    }
 }
 
 impl Default for PathJson
@@ -20,7 +50,9 @@ impl Default for PathJson
             rvmat_converter_path: "./external_tools/config/CfgConvert.exe".to_string(),
             config_converter_path: "./external_tools/config/CfgConvert.exe".to_string(),
             pbo_packer_path: "./external_tools/pbo/pboc.exe".to_string(),
-            pbo_signer_path: "./external_tools/signing/dsSignFile.exe".to_string()
+            pbo_signer_path: "./external_tools/signing/dsSignFile.exe".to_string(),
+            launcher: None,
+            timeout_seconds: None
         }
     }
 }
\ No newline at end of file
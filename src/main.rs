@@ -1,66 +1,64 @@
-#![feature(io_error_more)]
-
 mod args;
+mod cache;
+mod commands;
+mod console;
 mod packers;
+mod pbo;
 mod converters;
 mod utils;
 mod json_files;
+mod toml_files;
 mod signing;
+mod diagnostics;
+mod ignore_rules;
+mod watch;
+mod tools;
 
-use std::path::Path;
+use std::process::ExitCode;
 use clap::Parser;
-use crate::args::Args;
-use crate::packers::mod_packer::ArmaToolsModPacker;
-use crate::packers::Packer;
-use crate::json_files::path_json::model::path_json::PathJson;
-use crate::json_files::path_json::path_json_handler::PathJsonHandler;
+use crate::args::{Cli, Command};
+use crate::commands::command_context::CommandContext;
+use crate::commands::{configuration_error, convert_command, doctor_command, init_command, inspect_command, new_addon_command, pack_command, sign_command, unpack_command, verify_command};
 
-fn main() {
-    let args = Args::parse();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
 
-    let paths: PathJson;
+    console::configure(cli.global.verbose, cli.global.quiet, cli.global.color, cli.global.log_format);
 
-    if let Some(path_string) = args.path_json
+    // init creates the project config, so it must not fail on a broken one
+    if let Command::Init(args) = &cli.command
     {
-        let path = Path::new(path_string.as_str());
-
-        if !path.exists()
-        {
-            eprintln!("No path.json found at: {path_string}");
-            return;
-        }
-
-        let handler = PathJsonHandler::default();
-
-        paths = handler.read_json(path).expect(format!("Cannot read path.json at: {path_string}").as_str());
+        return init_command::run(args);
     }
-    else {
-        let program_path = std::env::current_exe().expect("Cannot get path to current executable");
 
-        let program_directory = program_path.parent().unwrap();
-
-        let path = program_directory.join("path.json");
+    let context = match CommandContext::load(&cli.global)
+    {
+        Ok(context) => context,
+        Err(message) => return configuration_error(&message)
+    };
 
-        let handler = PathJsonHandler::default();
+    if let Some(jobs) = cli.global.jobs.or(context.config.jobs)
+    {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global();
 
-        if path.exists()
-        {
-            paths = handler.read_json(path).expect(format!("Cannot read path.json in program directory: {program_directory:?}").as_str());
-        }
-        else
+        if let Err(error) = thread_pool
         {
-            paths = PathJson::default();
-
-            handler.write_json(&paths, path).expect(format!("Cannot write default path.json to program directory: {program_directory:?}").as_str());
+            return configuration_error(&format!("Cannot create worker pool with {jobs} jobs: {error}"));
         }
     }
 
-    let mod_packer = ArmaToolsModPacker::create(paths);
-
-    if args.unpack {
-        mod_packer.unpack(args.source, args.destination).unwrap();
-    }
-    else if args.pack {
-        mod_packer.pack(args.source, args.destination).unwrap();
+    match &cli.command
+    {
+        Command::Pack(args) => pack_command::run(args, &context),
+        Command::Unpack(args) => unpack_command::run(args, &context),
+        Command::Inspect(args) => inspect_command::run(args),
+        Command::Verify(args) => verify_command::run(args),
+        Command::Sign(args) => sign_command::run(args, &context),
+        Command::Convert(args) => convert_command::run(args, &context),
+        Command::Init(args) => init_command::run(args),
+        Command::NewAddon(args) => new_addon_command::run(args, &context),
+        Command::Doctor(args) => doctor_command::run(args, &context)
     }
 }
\ No newline at end of file
@@ -0,0 +1,158 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::console;
+use crate::packers::mod_packer_error::PipelineStage;
+use crate::toml_files::armapack_toml::model::armapack_toml::{ArmapackToml, HooksSection};
+use crate::tools::tool_invocation::ToolInvocation;
+use crate::tools::tool_runner::{ToolKind, ToolRunner};
+
+/// The points in the packing pipeline hooks from the armapack.toml run at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildHook
+{
+    BeforeCopy,
+    AfterConvert,
+    BeforePbo,
+    AfterSign,
+    AfterPack
+}
+
+impl Display for BuildHook
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let name = match self
+        {
+            BuildHook::BeforeCopy => "before_copy",
+            BuildHook::AfterConvert => "after_convert",
+            BuildHook::BeforePbo => "before_pbo",
+            BuildHook::AfterSign => "after_sign",
+            BuildHook::AfterPack => "after_pack"
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl BuildHook
+{
+    pub fn command(self, hooks: &HooksSection) -> Option<&Vec<String>>
+    {
+        let command = match self
+        {
+            BuildHook::BeforeCopy => &hooks.before_copy,
+            BuildHook::AfterConvert => &hooks.after_convert,
+            BuildHook::BeforePbo => &hooks.before_pbo,
+            BuildHook::AfterSign => &hooks.after_sign,
+            BuildHook::AfterPack => &hooks.after_pack
+        };
+
+        command.as_ref().filter(|command| !command.is_empty())
+    }
+
+    /// The stage a failing hook is reported in
+    pub fn stage(self) -> PipelineStage
+    {
+        match self
+        {
+            BuildHook::BeforeCopy => PipelineStage::Copy,
+            BuildHook::AfterConvert => PipelineStage::Convert,
+            BuildHook::BeforePbo => PipelineStage::Pack,
+            BuildHook::AfterSign => PipelineStage::Sign,
+            BuildHook::AfterPack => PipelineStage::Swap
+        }
+    }
+}
+
+/// The folders and files of the build, passed to hooks as ARMAPACK_* environment variables
+#[derive(Debug, Clone, Default)]
+pub struct HookContext
+{
+    pub mod_folder: PathBuf,
+
+    /// The folder the mod is built in, None once it replaced the destination
+    pub staging_folder: Option<PathBuf>,
+
+    pub destination_folder: PathBuf,
+    pub addon: Option<String>,
+
+    /// The pbo folder in the staging folder, before it is packed
+    pub addon_folder: Option<PathBuf>,
+
+    pub pbo: Option<PathBuf>
+}
+
+impl HookContext
+{
+    pub fn for_addon(&self, addon: &str, addon_folder: &Path, pbo: &Path) -> Self
+    {
+        Self {
+            addon: Some(String::from(addon)),
+            addon_folder: Some(PathBuf::from(addon_folder)),
+            pbo: Some(PathBuf::from(pbo)),
+            ..self.clone()
+        }
+    }
+
+    pub fn without_staging_folder(&self) -> Self
+    {
+        Self {
+            staging_folder: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Runs the hook configured in the manifest, if any, in the mod folder. Its output is printed unless quiet.
+pub fn run_hook(hook: BuildHook, manifest: &ArmapackToml, context: &HookContext) -> Result<(), io::Error>
+{
+    let Some((program, arguments)) = hook.command(&manifest.hooks).and_then(|command| command.split_first()) else {
+        return Ok(());
+    };
+
+    let mut invocation = ToolInvocation::new(program, None)
+        .current_dir(&context.mod_folder)
+        .env("ARMAPACK_HOOK", hook.to_string())
+        .env("ARMAPACK_MOD_FOLDER", &context.mod_folder)
+        .env("ARMAPACK_DESTINATION_FOLDER", &context.destination_folder);
+
+    for argument in arguments
+    {
+        invocation = invocation.arg(argument);
+    }
+
+    let optional_variables = [
+        ("ARMAPACK_STAGING_FOLDER", context.staging_folder.as_ref().map(|folder| folder.display().to_string())),
+        ("ARMAPACK_ADDON", context.addon.clone()),
+        ("ARMAPACK_ADDON_FOLDER", context.addon_folder.as_ref().map(|folder| folder.display().to_string())),
+        ("ARMAPACK_PBO", context.pbo.as_ref().map(|pbo| pbo.display().to_string())),
+        ("ARMAPACK_MOD_NAME", manifest.mod_info.name.clone()),
+        ("ARMAPACK_MOD_VERSION", manifest.mod_info.version.clone()),
+    ];
+
+    for (name, value) in optional_variables
+    {
+        if let Some(value) = value
+        {
+            invocation = invocation.env(name, value);
+        }
+    }
+
+    match &context.addon
+    {
+        Some(addon) => console::verbose(&format!("Running hook {hook} for {addon}")),
+        None => console::verbose(&format!("Running hook {hook}"))
+    }
+
+    let timeout = manifest.hooks.timeout_seconds.map(Duration::from_secs);
+
+    let output = ToolRunner::new(ToolKind::Hook, timeout)
+        .run(&invocation)
+        .map_err(io::Error::other)?;
+
+    console::tool_output(&output.stdout, &output.stderr);
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,727 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use ignore::WalkBuilder;
+use serde::Serialize;
+use crate::cache::build_cache::CACHE_FOLDER_NAME;
+use crate::diagnostics::error_chain;
+use crate::ignore_rules::{IgnoreMatch, IgnoreRules};
+use crate::json_files::key_json::key_json_handler::KeyJsonHandler;
+use crate::converters::converter_registry::{ConversionDirection, ConverterRegistry};
+use crate::packers::mod_packer::{ArmaToolsModPacker, KeySource, PackOptions};
+use crate::packers::mod_packer_error::{ModPackerError, PipelineStage, StageContext};
+use crate::packers::{mod_info_files, pbo_prefixes, pbo_references};
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+use crate::utils::has_extension;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanMode
+{
+    Pack,
+    Unpack
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FileAction
+{
+    /// Copied as-is, optionally because a .convertignore rule excluded it from conversion or its converter is disabled
+    Copy {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        convert_ignored_by: Option<IgnoreMatch>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        disabled_converter: Option<String>
+    },
+
+    Convert {
+        converter: String,
+        output: PathBuf
+    },
+
+    /// Excluded by a .modignore rule
+    Ignore {
+        ignored_by: IgnoreMatch
+    },
+
+    /// Read by the tool itself (ignore files, key files, private keys, ...) and not part of the output
+    Consume {
+        reason: String
+    },
+
+    /// Would fail the conversion stage since no converter exists for the extension
+    Unsupported {
+        extension: String
+    },
+
+    Unpack {
+        output: PathBuf
+    },
+
+    Skip {
+        reason: String
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PlannedFile
+{
+    pub path: PathBuf,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pbo: Option<String>,
+
+    #[serde(flatten)]
+    pub action: FileAction
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PlannedPbo
+{
+    pub name: String,
+    pub folder: PathBuf,
+    pub output: PathBuf,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+
+    /// The key.json or armapack.toml the authorities come from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<PathBuf>,
+
+    pub authorities: Vec<String>,
+    pub signatures: Vec<PathBuf>
+}
+
+/// Everything a pack or unpack run would do, computed without touching the destination folder
+#[derive(Serialize, Debug, Clone)]
+pub struct BuildPlan
+{
+    pub mode: PlanMode,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub files: Vec<PlannedFile>,
+    pub pbos: Vec<PlannedPbo>,
+    pub public_keys: Vec<String>,
+    pub problems: Vec<String>,
+
+    /// Things the real run only warns about, e.g. broken file references without strict references
+    pub warnings: Vec<String>
+}
+
+impl BuildPlan
+{
+    /// Plans packing the source folder with the same ignore rules, pbo detection and key lookup used for packing
+    pub fn for_pack<A: AsRef<Path>, B: AsRef<Path>>(source_folder: A, destination_folder: B, options: &PackOptions) -> Result<Self, ModPackerError>
+    {
+        let stage = PipelineStage::Prepare;
+
+        let source_folder = source_folder.as_ref();
+
+        Self::check_source_folder(source_folder)?;
+
+        let mod_ignore_rules = IgnoreRules::load(source_folder, ".modignore").in_stage(stage, source_folder)?;
+        let convert_ignore_rules = IgnoreRules::load(source_folder, ".convertignore").in_stage(stage, source_folder)?;
+
+        let all_files = Self::collect_files(source_folder, false)?;
+        let copied_files = Self::collect_files(source_folder, true)?;
+
+        let mut plan = Self::create(PlanMode::Pack, source_folder, destination_folder.as_ref());
+
+        let pbo_folders: Vec<PathBuf> = ArmaToolsModPacker::pbo_folders_of(&copied_files).into_iter().collect();
+
+        plan.plan_pbos(&pbo_folders, &copied_files, options);
+
+        let (prefixes, mut prefix_errors) = pbo_prefixes::check_prefixes(&pbo_folders, &options.manifest, PipelineStage::CheckPrefixes);
+
+        // the real run checks the copied mod folder, so files excluded by .modignore must not count here either
+        prefix_errors.extend(pbo_prefixes::check_includes(&pbo_folders, &prefixes, &options.manifest, Some(&copied_files), PipelineStage::CheckPrefixes));
+
+        let converters = ConverterRegistry::for_manifest(&options.manifest.converters);
+
+        let reference_check = pbo_references::check_references(source_folder, &prefixes, &options.manifest, &converters, Some(&copied_files), options.strict_references, PipelineStage::CheckReferences);
+
+        prefix_errors.extend(reference_check.errors);
+
+        plan.warnings = reference_check.warnings;
+
+        plan.problems.extend(prefix_errors.iter().map(|error| error.to_string()));
+
+        let keys_folder = source_folder.join("keys");
+
+        for file in all_files
+        {
+            let file_name = file.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+
+            let pbo = pbo_folders.iter()
+                .find(|pbo_folder| file.starts_with(pbo_folder))
+                .map(|pbo_folder| Self::file_name_of(pbo_folder));
+
+            let is_private_key = has_extension(&file, "biprivatekey");
+
+            let is_manifest = file.parent() == Some(source_folder) && file_name == ArmapackToml::FILE_NAME;
+
+            let is_report = options.report_file.as_ref().is_some_and(|report_file| std::path::absolute(&file).is_ok_and(|path| path == *report_file));
+
+            let action = if file_name == ".modignore" {
+                FileAction::Consume { reason: String::from("ignore rules, not copied") }
+            }
+            else if is_manifest {
+                FileAction::Consume { reason: String::from("project manifest, not copied") }
+            }
+            else if is_report {
+                FileAction::Consume { reason: String::from("build report, not copied") }
+            }
+            else if is_private_key {
+                FileAction::Consume { reason: String::from("private key, read for signing and never copied") }
+            }
+            else if !copied_files.contains(&file) {
+                match mod_ignore_rules.matched(&file, false)
+                {
+                    Some(ignore_match) => FileAction::Ignore { ignored_by: plan.relative_match(ignore_match) },
+                    None => FileAction::Skip { reason: String::from("inside a folder excluded by .modignore") }
+                }
+            }
+            else if file_name == ".convertignore" {
+                FileAction::Consume { reason: String::from("conversion rules, removed after converting") }
+            }
+            else if mod_info_files::is_mod_info_file(source_folder, &file) && mod_info_files::is_generated(&file, &options.manifest) {
+                FileAction::Consume { reason: String::from("replaced by the one generated from the manifest") }
+            }
+            else if mod_info_files::has_mod_info_file_name(&file) {
+                FileAction::Copy { convert_ignored_by: None, disabled_converter: None }
+            }
+            else {
+                let action = plan.conversion_action(&file, &convert_ignore_rules, &converters, &options.manifest);
+
+                let extension = file.extension().map(|extension| extension.to_string_lossy().to_string()).unwrap_or_default();
+
+                let is_copy = matches!(action, FileAction::Copy { .. });
+
+                if is_copy && file_name == "key.json" {
+                    FileAction::Consume { reason: String::from("signing configuration, not shipped") }
+                }
+                else if is_copy && file.parent() == Some(keys_folder.as_path()) && extension == "bikey" && !plan.public_keys.contains(&file_name) {
+                    FileAction::Skip { reason: String::from("public key not used by any pbo") }
+                }
+                else {
+                    action
+                }
+            };
+
+            plan.files.push(PlannedFile {
+                path: plan.relative(&file),
+                pbo,
+                action,
+            });
+        }
+
+        Ok(plan)
+    }
+
+    /// Plans unpacking the source folder, the contents of the pbos themselves are only known once they are unpacked
+    pub fn for_unpack<A: AsRef<Path>, B: AsRef<Path>>(source_folder: A, destination_folder: B, manifest: &ArmapackToml) -> Result<Self, ModPackerError>
+    {
+        let source_folder = source_folder.as_ref();
+
+        Self::check_source_folder(source_folder)?;
+
+        let mut plan = Self::create(PlanMode::Unpack, source_folder, destination_folder.as_ref());
+
+        let converters = ConverterRegistry::for_manifest(&manifest.converters);
+
+        for file in Self::collect_files(source_folder, false)?
+        {
+            let relative_path = plan.relative(&file);
+
+            let action = match file.extension()
+            {
+                None => FileAction::Skip { reason: String::from("no file extension, not copied") },
+                Some(extension) if extension == "pbo" => FileAction::Unpack { output: relative_path.with_extension("") },
+                Some(extension) if extension == "bisign" => FileAction::Skip { reason: String::from("signature, not unpacked") },
+                Some(_) => match converters.find(&file, ConversionDirection::Unpack)
+                {
+                    Some(rule) => FileAction::Convert {
+                        converter: rule.converter.name(),
+                        output: rule.output_path(&relative_path),
+                    },
+                    None => FileAction::Copy { convert_ignored_by: None, disabled_converter: None }
+                }
+            };
+
+            plan.files.push(PlannedFile {
+                path: relative_path,
+                pbo: None,
+                action,
+            });
+        }
+
+        Ok(plan)
+    }
+
+    pub fn has_problems(&self) -> bool
+    {
+        !self.problems.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error>
+    {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn to_text(&self) -> String
+    {
+        let mut text = String::new();
+
+        let mode = match self.mode
+        {
+            PlanMode::Pack => "pack",
+            PlanMode::Unpack => "unpack"
+        };
+
+        let _ = writeln!(text, "Build plan ({mode}): {} -> {}", self.source.display(), self.destination.display());
+
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Files:");
+
+        for file in &self.files
+        {
+            let path = Self::display_path(&file.path);
+
+            let pbo = match &file.pbo
+            {
+                Some(pbo) => format!(" [{pbo}.pbo]"),
+                None => String::new()
+            };
+
+            let line = match &file.action
+            {
+                FileAction::Copy { convert_ignored_by: Some(reason), .. } => {
+                    format!("  copy        {path}{pbo}  (not converted, {})", Self::display_reason(reason))
+                }
+                FileAction::Copy { disabled_converter: Some(converter), .. } => {
+                    format!("  copy        {path}{pbo}  (not converted, {converter} disabled in {})", ArmapackToml::FILE_NAME)
+                }
+                FileAction::Copy { .. } => format!("  copy        {path}{pbo}"),
+                FileAction::Convert { converter, output } => {
+                    format!("  convert     {path} -> {}{pbo}  ({converter})", Self::display_path(output))
+                }
+                FileAction::Ignore { ignored_by } => format!("  ignore      {path}  ({})", Self::display_reason(ignored_by)),
+                FileAction::Consume { reason } => format!("  consume     {path}  ({reason})"),
+                FileAction::Unsupported { extension } => format!("  UNSUPPORTED {path}{pbo}  (no converter for .{extension})"),
+                FileAction::Unpack { output } => format!("  unpack      {path} -> {}", Self::display_path(output)),
+                FileAction::Skip { reason } => format!("  skip        {path}  ({reason})")
+            };
+
+            let _ = writeln!(text, "{line}");
+        }
+
+        if !self.pbos.is_empty()
+        {
+            let _ = writeln!(text);
+            let _ = writeln!(text, "PBOs:");
+
+            for pbo in &self.pbos
+            {
+                let _ = writeln!(text, "  {} <- {}", Self::display_path(&pbo.output), Self::display_path(&pbo.folder));
+
+                if let Some(prefix) = &pbo.prefix
+                {
+                    let _ = writeln!(text, "    prefix {prefix}");
+                }
+
+                match &pbo.key_file
+                {
+                    Some(key_file) => {
+                        let _ = writeln!(text, "    signed by {} ({})", pbo.authorities.join(", "), Self::display_path(key_file));
+                    }
+                    None => {
+                        let _ = writeln!(text, "    unsigned");
+                    }
+                }
+            }
+        }
+
+        if !self.public_keys.is_empty()
+        {
+            let _ = writeln!(text);
+            let _ = writeln!(text, "Public keys: {}", self.public_keys.join(", "));
+        }
+
+        if !self.warnings.is_empty()
+        {
+            let _ = writeln!(text);
+            let _ = writeln!(text, "Warnings:");
+
+            for warning in &self.warnings
+            {
+                let _ = writeln!(text, "  {warning}");
+            }
+        }
+
+        if !self.problems.is_empty()
+        {
+            let _ = writeln!(text);
+            let _ = writeln!(text, "Problems:");
+
+            for problem in &self.problems
+            {
+                let _ = writeln!(text, "  {problem}");
+            }
+        }
+
+        text
+    }
+
+    fn create(mode: PlanMode, source_folder: &Path, destination_folder: &Path) -> Self
+    {
+        Self {
+            mode,
+            source: PathBuf::from(source_folder),
+            destination: PathBuf::from(destination_folder),
+            files: vec![],
+            pbos: vec![],
+            public_keys: vec![],
+            problems: vec![],
+            warnings: vec![],
+        }
+    }
+
+    fn check_source_folder(source_folder: &Path) -> Result<(), ModPackerError>
+    {
+        if source_folder.is_dir()
+        {
+            return Ok(());
+        }
+
+        let error = std::io::Error::new(std::io::ErrorKind::NotFound, "Source Folder does not exist or is not a folder");
+
+        Err(error).in_stage(PipelineStage::Prepare, source_folder)
+    }
+
+    /// All files below the folder in walk order, optionally without the files excluded by .modignore
+    fn collect_files(folder: &Path, apply_mod_ignore: bool) -> Result<BTreeSet<PathBuf>, ModPackerError>
+    {
+        let mut walk_builder = WalkBuilder::new(folder);
+
+        walk_builder
+            .standard_filters(false)
+            .filter_entry(|entry| entry.file_name() != CACHE_FOLDER_NAME);
+
+        if apply_mod_ignore
+        {
+            walk_builder.add_custom_ignore_filename(".modignore");
+        }
+
+        let mut files = BTreeSet::<PathBuf>::new();
+
+        for entry in walk_builder.build()
+        {
+            let entry = entry.in_stage(PipelineStage::Prepare, folder)?;
+
+            if entry.path().is_file()
+            {
+                files.insert(PathBuf::from(entry.path()));
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Resolves the signing of every pbo folder like [KeySource::resolve], the prefix of every pbo and checks the private keys
+    fn plan_pbos(&mut self, pbo_folders: &[PathBuf], copied_files: &BTreeSet<PathBuf>, options: &PackOptions)
+    {
+        let key_json_handler = KeyJsonHandler::default();
+
+        let manifest = &options.manifest;
+
+        let default_key_file = self.source.join("key.json");
+        let keys_folder = self.source.join("keys");
+
+        let mut public_keys = BTreeSet::<String>::new();
+
+        for pbo_folder in pbo_folders
+        {
+            let name = Self::file_name_of(pbo_folder);
+
+            let output = Path::new("addons").join(format!("{name}.pbo"));
+
+            let prefix = pbo_prefixes::pbo_prefix(pbo_folder, manifest);
+
+            let key_source = KeySource::resolve(pbo_folder, &default_key_file, manifest, |path| copied_files.contains(path));
+
+            let mut authorities: Vec<String> = vec![];
+
+            let key_file = match key_source
+            {
+                Some(KeySource::KeyFile(key_file)) => {
+                    match key_json_handler.read_json(&key_file)
+                    {
+                        Ok(key_json) => {
+                            authorities = key_json.authorities();
+
+                            if authorities.is_empty()
+                            {
+                                let error = ModPackerError::MissingAuthority {
+                                    stage: PipelineStage::Sign,
+                                    path: self.relative(&key_file),
+                                };
+
+                                self.problems.push(error.to_string());
+                            }
+                        }
+                        Err(source) => {
+                            let error = ModPackerError::KeyFile {
+                                stage: PipelineStage::Sign,
+                                path: self.relative(&key_file),
+                                source,
+                            };
+
+                            self.problems.push(error_chain(&error));
+                        }
+                    }
+
+                    Some(self.relative(&key_file))
+                }
+                Some(KeySource::Authorities(manifest_authorities)) => {
+                    let manifest_file = PathBuf::from(ArmapackToml::FILE_NAME);
+
+                    authorities = manifest_authorities;
+
+                    if authorities.is_empty()
+                    {
+                        let error = ModPackerError::MissingAuthority {
+                            stage: PipelineStage::Sign,
+                            path: manifest_file.clone(),
+                        };
+
+                        self.problems.push(error.to_string());
+                    }
+
+                    Some(manifest_file)
+                }
+                None => {
+                    if options.require_signed
+                    {
+                        let error = ModPackerError::Unsigned {
+                            stage: PipelineStage::CopyKeys,
+                            path: self.relative(pbo_folder),
+                        };
+
+                        self.problems.push(error.to_string());
+                    }
+
+                    None
+                }
+            };
+
+            for authority in &authorities
+            {
+                let private_key_file = keys_folder.join(format!("{authority}.biprivatekey"));
+
+                if !private_key_file.is_file()
+                {
+                    self.problems.push(format!("Private key {:?} for authority \"{authority}\" of {name}.pbo is missing", self.relative(&private_key_file)));
+                }
+
+                public_keys.insert(format!("{authority}.bikey"));
+            }
+
+            let signatures = authorities.iter()
+                .map(|authority| Path::new("addons").join(format!("{name}.pbo.{authority}.bisign")))
+                .collect();
+
+            self.pbos.push(PlannedPbo {
+                name,
+                folder: self.relative(pbo_folder),
+                output,
+                prefix,
+                key_file,
+                authorities,
+                signatures,
+            });
+        }
+
+        self.public_keys = public_keys.into_iter().collect();
+    }
+
+    /// What the conversion stage would do with a copied file
+    fn conversion_action(&mut self, file: &Path, convert_ignore_rules: &IgnoreRules, converters: &ConverterRegistry, manifest: &ArmapackToml) -> FileAction
+    {
+        if let Some(ignore_match) = convert_ignore_rules.matched(file, false)
+        {
+            return FileAction::Copy { convert_ignored_by: Some(self.relative_match(ignore_match)), disabled_converter: None };
+        }
+
+        match converters.find(file, ConversionDirection::Pack)
+        {
+            Some(rule) if !rule.converter.is_enabled(&manifest.converters) => FileAction::Copy {
+                convert_ignored_by: None,
+                disabled_converter: Some(rule.converter.name()),
+            },
+            Some(rule) => FileAction::Convert {
+                converter: rule.converter.name(),
+                output: self.relative(&rule.output_path(file)),
+            },
+            None => {
+                let extension = file.extension()
+                    .map(|extension| extension.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                let error = ModPackerError::UnsupportedFile {
+                    stage: PipelineStage::Convert,
+                    path: self.relative(file),
+                    extension: extension.clone(),
+                };
+
+                self.problems.push(error.to_string());
+
+                FileAction::Unsupported { extension }
+            }
+        }
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf
+    {
+        path.strip_prefix(&self.source)
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(path))
+    }
+
+    fn relative_match(&self, ignore_match: IgnoreMatch) -> IgnoreMatch
+    {
+        IgnoreMatch {
+            ignore_file: self.relative(&ignore_match.ignore_file),
+            ..ignore_match
+        }
+    }
+
+    fn file_name_of(path: &Path) -> String
+    {
+        path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default()
+    }
+
+    fn display_reason(reason: &IgnoreMatch) -> String
+    {
+        let file = Self::display_path(&reason.ignore_file);
+
+        match reason.line
+        {
+            Some(line) => format!("{file}:{line} \"{}\"", reason.pattern),
+            None => format!("{file} \"{}\"", reason.pattern)
+        }
+    }
+
+    fn display_path(path: &Path) -> String
+    {
+        path.to_string_lossy().replace('\\', "/")
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use crate::packers::mod_packer::PackOptions;
+    use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+    use super::{BuildPlan, FileAction};
+
+    const CONFIG: &str = r#"class CfgVehicles
+{
+    picture = "\z\test\addons\main\data\icon.paa";
+    texture = "\z\test\addons\main\data\draft.paa";
+};"#;
+
+    /// Writes a mod with the pbo folder main whose config references a texture excluded by .modignore
+    fn create_mod(test_name: &str) -> PathBuf
+    {
+        let folder = std::env::temp_dir().join(format!("armapack-plan-test-{test_name}-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&folder);
+
+        let pbo_folder = folder.join("addons").join("main");
+
+        fs::create_dir_all(pbo_folder.join("data")).unwrap();
+        fs::write(pbo_folder.join("pbo.json"), r#"{ "headers": [{ "name": "prefix", "value": "z\\test\\addons\\main" }] }"#).unwrap();
+        fs::write(pbo_folder.join("config.cpp"), CONFIG).unwrap();
+        fs::write(pbo_folder.join("data").join("icon.png"), "").unwrap();
+        fs::write(pbo_folder.join("data").join("draft.paa"), "").unwrap();
+        fs::write(folder.join(".modignore"), "draft.paa\n").unwrap();
+        fs::write(folder.join(".convertignore"), "pbo.json\n").unwrap();
+
+        folder
+    }
+
+    fn plan(folder: &Path, strict_references: bool) -> BuildPlan
+    {
+        let options = PackOptions {
+            require_signed: false,
+            strict_references,
+            use_cache: false,
+            manifest: ArmapackToml::default(),
+            report_file: None,
+            entry_timestamp: 0,
+        };
+
+        BuildPlan::for_pack(folder, folder.with_extension("out"), &options).unwrap()
+    }
+
+    fn action_of<'a>(plan: &'a BuildPlan, path: &str) -> &'a FileAction
+    {
+        let path = PathBuf::from(path);
+
+        &plan.files.iter().find(|file| file.path == path).unwrap().action
+    }
+
+    #[test]
+    fn files_get_the_action_of_the_real_run()
+    {
+        let folder = create_mod("actions");
+
+        fs::write(folder.join("addons").join("main").join("notes.xyz"), "").unwrap();
+
+        let plan = plan(&folder, false);
+
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert!(matches!(action_of(&plan, ".modignore"), FileAction::Consume { .. }));
+        assert!(matches!(action_of(&plan, "addons/main/config.cpp"), FileAction::Convert { converter, .. } if converter == "config_converter"));
+        assert!(matches!(action_of(&plan, "addons/main/data/icon.png"), FileAction::Convert { output, .. } if output == Path::new("addons/main/data/icon.paa")));
+        assert!(matches!(action_of(&plan, "addons/main/pbo.json"), FileAction::Copy { convert_ignored_by: Some(_), .. }));
+        assert!(matches!(action_of(&plan, "addons/main/notes.xyz"), FileAction::Unsupported { extension } if extension == "xyz"));
+
+        match action_of(&plan, "addons/main/data/draft.paa")
+        {
+            FileAction::Ignore { ignored_by } => {
+                assert_eq!(ignored_by.ignore_file, PathBuf::from(".modignore"));
+                assert_eq!(ignored_by.pattern, "draft.paa");
+            }
+            action => panic!("draft.paa is not ignored: {action:?}")
+        }
+
+        assert!(plan.has_problems());
+        assert_eq!(plan.problems.len(), 1, "{:?}", plan.problems);
+        assert!(plan.problems[0].contains("notes.xyz"));
+    }
+
+    #[test]
+    fn references_to_ignored_files_are_broken_as_in_the_real_run()
+    {
+        let folder = create_mod("references");
+
+        let plan_with_warnings = plan(&folder, false);
+        let strict_plan = plan(&folder, true);
+
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert!(!plan_with_warnings.has_problems(), "{:?}", plan_with_warnings.problems);
+        assert_eq!(plan_with_warnings.warnings.len(), 1, "{:?}", plan_with_warnings.warnings);
+        assert!(plan_with_warnings.warnings[0].contains("draft.paa"));
+
+        assert_eq!(strict_plan.problems.len(), 1, "{:?}", strict_plan.problems);
+        assert!(strict_plan.problems[0].contains("draft.paa"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,324 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use crate::diagnostics::error_chain;
+use crate::packers::build_plan::PlannedFile;
+use crate::packers::mod_packer::{PackOptions, PackSummary};
+use crate::pbo::pbo_file::PboFile;
+use crate::utils::{files_with_extension, sha1_of_file};
+
+/// Machine-readable record of a pack run, written next to the mod and never into it
+#[derive(Serialize, Debug, Clone)]
+pub struct BuildReport
+{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mod_name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mod_version: Option<String>,
+
+    pub source: PathBuf,
+    pub destination: PathBuf,
+
+    /// Seconds since the unix epoch
+    pub created_at: u64,
+
+    pub pbos: Vec<ReportedPbo>,
+    pub converted_files: Vec<ReportedConversion>,
+
+    /// Files left out of the mod by .modignore rules or as unused public keys, relative to the source folder
+    pub ignored_files: Vec<PlannedFile>,
+
+    pub warnings: Vec<String>,
+    pub stages: Vec<ReportedStage>,
+    pub total_seconds: f64
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReportedPbo
+{
+    pub name: String,
+
+    /// Relative to the destination folder
+    pub path: PathBuf,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+
+    pub size: u64,
+    pub entries: usize,
+    pub sha1: String,
+
+    /// SHA1 stored at the end of the pbo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+
+    pub signatures: Vec<ReportedSignature>
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReportedSignature
+{
+    pub authority: String,
+    pub path: PathBuf,
+    pub sha1: String
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReportedConversion
+{
+    /// Relative to the source folder
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub converter: String,
+    pub source_sha1: String,
+    pub reused: bool
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReportedStage
+{
+    pub stage: String,
+    pub seconds: f64
+}
+
+impl BuildReport
+{
+    /// Collects the report from the summary of the run and the packed destination folder
+    pub fn create(source_folder: &Path, destination_folder: &Path, options: &PackOptions, summary: &PackSummary, total_duration: Duration) -> Self
+    {
+        let mut warnings: Vec<String> = summary.unsigned_pbos.iter()
+            .map(|pbo_folder| format!("{} was not signed, no key.json applies to it", pbo_folder.display()))
+            .collect();
+
+        warnings.extend(summary.reference_warnings.iter().cloned());
+
+        let addons_folder = destination_folder.join("addons");
+
+        let pbos = files_with_extension(&addons_folder, "pbo").iter()
+            .filter_map(|pbo_path| match Self::report_pbo(destination_folder, pbo_path)
+            {
+                Ok(pbo) => Some(pbo),
+                Err(message) => {
+                    warnings.push(message);
+                    None
+                }
+            })
+            .collect();
+
+        let converted_files = summary.conversions.iter()
+            .map(|conversion| ReportedConversion {
+                source: conversion.source.clone(),
+                output: conversion.output.clone(),
+                converter: conversion.converter.clone(),
+                source_sha1: conversion.source_sha1.clone(),
+                reused: conversion.reused,
+            })
+            .collect();
+
+        let stages = summary.stage_durations.iter()
+            .map(|(stage, duration)| ReportedStage {
+                stage: stage.to_string(),
+                seconds: duration.as_secs_f64(),
+            })
+            .collect();
+
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        Self {
+            mod_name: options.manifest.mod_info.name.clone(),
+            mod_version: options.manifest.mod_info.version.clone(),
+            source: PathBuf::from(source_folder),
+            destination: PathBuf::from(destination_folder),
+            created_at,
+            pbos,
+            converted_files,
+            ignored_files: summary.ignored_files.clone(),
+            warnings,
+            stages,
+            total_seconds: total_duration.as_secs_f64(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error>
+    {
+        serde_json::to_string_pretty(self)
+    }
+
+    fn report_pbo(destination_folder: &Path, pbo_path: &Path) -> Result<ReportedPbo, String>
+    {
+        let pbo = PboFile::read(pbo_path)
+            .map_err(|error| format!("Cannot read {}: {}", pbo_path.display(), error_chain(&error)))?;
+
+        let size = pbo_path.metadata()
+            .map_err(|error| format!("Cannot read {}: {error}", pbo_path.display()))?
+            .len();
+
+        let sha1 = sha1_of_file(pbo_path)
+            .map_err(|error| format!("Cannot read {}: {error}", pbo_path.display()))?;
+
+        let name = pbo_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        let prefix = pbo.properties.iter()
+            .find(|property| property.name.eq_ignore_ascii_case("prefix"))
+            .map(|property| property.value.to_string());
+
+        let signature_prefix = format!("{name}.");
+
+        let signatures = files_with_extension(pbo_path.parent().unwrap_or(destination_folder), "bisign").into_iter()
+            .filter_map(|signature_path| {
+                let signature_name = signature_path.file_name()?.to_string_lossy().to_string();
+
+                let authority = signature_name.strip_prefix(&signature_prefix)?.strip_suffix(".bisign")?.to_string();
+
+                let sha1 = sha1_of_file(&signature_path).ok()?;
+
+                Some(ReportedSignature {
+                    authority,
+                    path: Self::relative(destination_folder, &signature_path),
+                    sha1,
+                })
+            })
+            .collect();
+
+        Ok(ReportedPbo {
+            name,
+            path: Self::relative(destination_folder, pbo_path),
+            prefix,
+            size,
+            entries: pbo.entries.len(),
+            sha1,
+            checksum: pbo.stored_checksum,
+            signatures,
+        })
+    }
+
+    fn relative(folder: &Path, path: &Path) -> PathBuf
+    {
+        path.strip_prefix(folder).map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(path))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use serde_json::json;
+    use sha1::{Digest, Sha1};
+    use crate::ignore_rules::IgnoreMatch;
+    use crate::packers::build_plan::{FileAction, PlannedFile};
+    use crate::packers::mod_packer::{PackOptions, PackSummary};
+    use crate::utils::to_hex;
+    use super::BuildReport;
+
+    /// An uncompressed pbo with the prefix and the (name, data) entries, ending in its checksum
+    fn pbo_bytes(prefix: &str, entries: &[(&str, &str)]) -> Vec<u8>
+    {
+        let mut bytes: Vec<u8> = vec![0];
+
+        bytes.extend_from_slice(&0x5665_7273u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+
+        for string in ["prefix", prefix, ""]
+        {
+            bytes.extend_from_slice(string.as_bytes());
+            bytes.push(0);
+        }
+
+        for (name, data) in entries.iter().chain([("", "")].iter())
+        {
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.push(0);
+
+            for value in [0, data.len() as u32, 0, 0, data.len() as u32]
+            {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        for (_, data) in entries
+        {
+            bytes.extend_from_slice(data.as_bytes());
+        }
+
+        let checksum = Sha1::digest(&bytes);
+
+        bytes.push(0);
+        bytes.extend_from_slice(&checksum);
+
+        bytes
+    }
+
+    #[test]
+    fn report_lists_pbos_signatures_and_ignored_files()
+    {
+        let folder = std::env::temp_dir().join(format!("armapack-build-report-test-{}", std::process::id()));
+
+        let destination = folder.join("@test");
+        let addons_folder = destination.join("addons");
+
+        fs::create_dir_all(&addons_folder).unwrap();
+
+        let pbo = pbo_bytes("z\\test\\addons\\main", &[("config.bin", "config"), ("data\\icon.paa", "icon")]);
+
+        fs::write(addons_folder.join("main.pbo"), &pbo).unwrap();
+        fs::write(addons_folder.join("main.pbo.test_authority.bisign"), "signature").unwrap();
+        fs::write(addons_folder.join("other.pbo.test_authority.bisign"), "signature of another pbo").unwrap();
+
+        let mut options = PackOptions::default();
+
+        options.manifest.mod_info.name = Some(String::from("test"));
+
+        let summary = PackSummary {
+            ignored_files: vec![
+                PlannedFile {
+                    path: PathBuf::from("notes.md"),
+                    pbo: None,
+                    action: FileAction::Ignore {
+                        ignored_by: IgnoreMatch { ignore_file: PathBuf::from(".modignore"), line: Some(2), pattern: String::from("*.md") },
+                    },
+                },
+                PlannedFile {
+                    path: PathBuf::from("keys/unused.bikey"),
+                    pbo: None,
+                    action: FileAction::Skip { reason: String::from("public key not used by any pbo") },
+                },
+            ],
+            ..PackSummary::default()
+        };
+
+        let report = BuildReport::create(&folder.join("mod"), &destination, &options, &summary, Duration::from_secs(2));
+
+        let report: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+
+        fs::remove_dir_all(&folder).unwrap();
+
+        let data_end = pbo.len() - 21;
+
+        assert_eq!(report["mod_name"], "test");
+        assert_eq!(report["pbos"], json!([{
+            "name": "main.pbo",
+            "path": "addons/main.pbo",
+            "prefix": "z\\test\\addons\\main",
+            "size": pbo.len(),
+            "entries": 2,
+            "sha1": to_hex(&Sha1::digest(&pbo)),
+            "checksum": to_hex(&Sha1::digest(&pbo[..data_end])),
+            "signatures": [{
+                "authority": "test_authority",
+                "path": "addons/main.pbo.test_authority.bisign",
+                "sha1": to_hex(&Sha1::digest(b"signature")),
+            }],
+        }]));
+
+        assert_eq!(report["ignored_files"], json!([
+            { "path": "notes.md", "action": "ignore", "ignored_by": { "ignore_file": ".modignore", "line": 2, "pattern": "*.md" } },
+            { "path": "keys/unused.bikey", "action": "skip", "reason": "public key not used by any pbo" },
+        ]));
+
+        assert_eq!(report["warnings"], json!([]));
+        assert_eq!(report["total_seconds"], 2.0);
+    }
+}
\ No newline at end of file
@@ -3,6 +3,13 @@ use std::path::Path;
 
 pub mod pbo_packer;
 pub mod mod_packer;
+pub mod mod_packer_error;
+pub mod build_plan;
+pub mod build_hooks;
+pub mod build_report;
+pub mod pbo_prefixes;
+pub mod pbo_references;
+pub mod mod_info_files;
 
 pub trait Packer
 {
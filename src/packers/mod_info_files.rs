@@ -0,0 +1,610 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::packers::mod_packer_error::{ModPackerError, PipelineStage, StageContext};
+use crate::toml_files::armapack_toml::model::armapack_toml::{ArmapackToml, ModCppSection};
+
+pub const MOD_CPP: &str = "mod.cpp";
+pub const META_CPP: &str = "meta.cpp";
+
+/// Keys the launcher reads from a mod.cpp
+const MOD_CPP_KEYS: [&str; 18] = [
+    "name", "picture", "logo", "logoSmall", "logoOver", "tooltip", "tooltipOwned", "overview", "overviewPicture", "overviewText",
+    "overviewFootnote", "action", "actionName", "author", "dlcColor", "hideName", "hidePicture", "publishedid"
+];
+
+/// Keys the launcher writes to the meta.cpp of a Steam Workshop mod
+const META_CPP_KEYS: [&str; 4] = ["protocol", "publishedid", "name", "timestamp"];
+
+/// Keys holding the path of an image, relative to the mod folder or an in-game path into a pbo
+const PICTURE_KEYS: [&str; 5] = ["picture", "logo", "logoSmall", "logoOver", "overviewPicture"];
+
+const NUMBER_KEYS: [&str; 5] = ["publishedid", "protocol", "timestamp", "hideName", "hidePicture"];
+
+const ARRAY_KEYS: [&str; 1] = ["dlcColor"];
+
+const TEXT_KIND: &str = "a quoted string";
+const NUMBER_KIND: &str = "a number";
+const ARRAY_KIND: &str = "an array in braces";
+
+/// Whether the file is the mod.cpp or meta.cpp of the mod, which is validated and may be generated from the manifest
+pub fn is_mod_info_file(mod_folder: &Path, path: &Path) -> bool
+{
+    path.parent() == Some(mod_folder) && has_mod_info_file_name(path)
+}
+
+/// Whether the file is named mod.cpp or meta.cpp. Arma reads them as text, so they are copied without being binarized
+/// wherever they are, e.g. also inside a pbo folder.
+pub fn has_mod_info_file_name(path: &Path) -> bool
+{
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    file_name.eq_ignore_ascii_case(MOD_CPP) || file_name.eq_ignore_ascii_case(META_CPP)
+}
+
+/// Whether the mod.cpp or meta.cpp is replaced by the one generated from the manifest
+pub fn is_generated(path: &Path, manifest: &ArmapackToml) -> bool
+{
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    manifest.mod_cpp.as_ref().is_some_and(|mod_cpp| file_name.eq_ignore_ascii_case(MOD_CPP) || mod_cpp.published_id.is_some())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModInfoValue
+{
+    Text(String),
+    Number(String),
+
+    /// The contents between the braces, e.g. 1,0,0,1
+    Array(String)
+}
+
+impl ModInfoValue
+{
+    fn kind(&self) -> &'static str
+    {
+        match self
+        {
+            ModInfoValue::Text(_) => TEXT_KIND,
+            ModInfoValue::Number(_) => NUMBER_KIND,
+            ModInfoValue::Array(_) => ARRAY_KIND
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModInfoEntry
+{
+    pub line: usize,
+
+    /// As written in the file, arrays keep their [] suffix
+    pub key: String,
+    pub value: ModInfoValue
+}
+
+impl ModInfoEntry
+{
+    fn is_key(&self, key: &str) -> bool
+    {
+        self.key.trim_end_matches("[]").eq_ignore_ascii_case(key)
+    }
+}
+
+/// A mod.cpp or meta.cpp, a flat list of key = value; entries
+#[derive(Debug, Clone)]
+pub struct ModInfoFile
+{
+    pub path: PathBuf,
+    pub entries: Vec<ModInfoEntry>
+}
+
+impl ModInfoFile
+{
+    /// The mod.cpp and meta.cpp the packed mod ends up with: the ones generated from the manifest and the others from the mod folder.
+    /// Files that cannot be read or parsed are returned as errors.
+    pub fn read_all(mod_folder: &Path, manifest: &ArmapackToml, stage: PipelineStage) -> Vec<Result<Self, ModPackerError>>
+    {
+        let mut paths: Vec<PathBuf> = match mod_folder.read_dir()
+        {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && is_mod_info_file(mod_folder, path) && !is_generated(path, manifest))
+                .collect(),
+            Err(_) => vec![]
+        };
+
+        paths.sort();
+
+        let read_files = paths.into_iter().map(|path| {
+            let content = fs::read(&path).in_stage(stage, &path)?;
+
+            Self::parse(&path, &String::from_utf8_lossy(&content), stage)
+        });
+
+        let generated_files = generated_files(manifest).into_iter()
+            .map(|(file_name, content)| Self::parse(&mod_folder.join(file_name), &content, stage));
+
+        generated_files.chain(read_files).collect()
+    }
+
+    fn parse(path: &Path, content: &str, stage: PipelineStage) -> Result<Self, ModPackerError>
+    {
+        let entries = Parser::new(content).parse()
+            .map_err(|(line, problem)| ModPackerError::InvalidModInfo {
+                stage,
+                path: PathBuf::from(path),
+                line,
+                problem,
+            })?;
+
+        Ok(Self {
+            path: PathBuf::from(path),
+            entries,
+        })
+    }
+
+    fn is_meta_cpp(&self) -> bool
+    {
+        self.path.file_name().is_some_and(|file_name| file_name.eq_ignore_ascii_case(META_CPP))
+    }
+
+    /// Checks the keys and the type of their values. Unknown keys are only warned about, since Arma ignores them.
+    pub fn validate(&self, stage: PipelineStage) -> (Vec<ModPackerError>, Vec<String>)
+    {
+        let mut errors: Vec<ModPackerError> = vec![];
+        let mut warnings: Vec<String> = vec![];
+
+        let known_keys: &[&str] = if self.is_meta_cpp() { &META_CPP_KEYS } else { &MOD_CPP_KEYS };
+
+        let mut seen_keys = HashSet::<String>::new();
+
+        for entry in &self.entries
+        {
+            let invalid_entry = |problem: String| ModPackerError::InvalidModInfo {
+                stage,
+                path: self.path.clone(),
+                line: entry.line,
+                problem,
+            };
+
+            if !seen_keys.insert(entry.key.trim_end_matches("[]").to_ascii_lowercase())
+            {
+                errors.push(invalid_entry(format!("\"{}\" is set more than once", entry.key)));
+                continue;
+            }
+
+            if !known_keys.iter().any(|key| entry.is_key(key))
+            {
+                warnings.push(format!("Unknown key \"{}\" in {:?} line {}, Arma ignores it", entry.key, self.path, entry.line));
+                continue;
+            }
+
+            let expected_kind = if NUMBER_KEYS.iter().any(|key| entry.is_key(key)) {
+                NUMBER_KIND
+            }
+            else if ARRAY_KEYS.iter().any(|key| entry.is_key(key)) {
+                ARRAY_KIND
+            }
+            else {
+                TEXT_KIND
+            };
+
+            if entry.value.kind() != expected_kind
+            {
+                errors.push(invalid_entry(format!("\"{}\" must be {expected_kind}, not {}", entry.key, entry.value.kind())));
+                continue;
+            }
+
+            if let ModInfoValue::Number(number) = &entry.value
+            {
+                if entry.is_key("publishedid") && !number.chars().all(|character| character.is_ascii_digit())
+                {
+                    errors.push(invalid_entry(format!("\"{}\" must be the id of the Steam Workshop item, not {number}", entry.key)));
+                }
+            }
+        }
+
+        (errors, warnings)
+    }
+
+    /// The line and path of every picture entry
+    pub fn pictures(&self) -> Vec<(usize, &str)>
+    {
+        self.entries.iter()
+            .filter(|entry| PICTURE_KEYS.iter().any(|key| entry.is_key(key)))
+            .filter_map(|entry| match &entry.value
+            {
+                ModInfoValue::Text(path) if !path.is_empty() => Some((entry.line, path.as_str())),
+                _ => None
+            })
+            .collect()
+    }
+}
+
+/// Writes the mod.cpp, and the meta.cpp if a Steam Workshop id is set, from the [mod_cpp] section of the manifest into the mod folder.
+/// Returns the files written, none without a [mod_cpp] section.
+pub fn generate(mod_folder: &Path, manifest: &ArmapackToml, stage: PipelineStage) -> Result<Vec<PathBuf>, ModPackerError>
+{
+    let mut written_files: Vec<PathBuf> = vec![];
+
+    for (file_name, content) in generated_files(manifest)
+    {
+        // the files from the mod folder may differ in case, and would end up next to the generated ones
+        if let Ok(entries) = mod_folder.read_dir()
+        {
+            for entry in entries.filter_map(|entry| entry.ok())
+            {
+                if entry.file_name().to_string_lossy().eq_ignore_ascii_case(file_name)
+                {
+                    fs::remove_file(entry.path()).in_stage(stage, entry.path())?;
+                }
+            }
+        }
+
+        let path = mod_folder.join(file_name);
+
+        fs::write(&path, content).in_stage(stage, &path)?;
+
+        written_files.push(path);
+    }
+
+    Ok(written_files)
+}
+
+/// The name and content of the files generated from the [mod_cpp] section
+fn generated_files(manifest: &ArmapackToml) -> Vec<(&'static str, String)>
+{
+    let Some(mod_cpp) = &manifest.mod_cpp else {
+        return vec![];
+    };
+
+    let name = mod_cpp.name.as_ref().or(manifest.mod_info.name.as_ref());
+
+    let mut files = vec![(MOD_CPP, mod_cpp_content(mod_cpp, name))];
+
+    if let Some(published_id) = mod_cpp.published_id
+    {
+        let mut content = format!("protocol = 1;\npublishedid = {published_id};\n");
+
+        if let Some(name) = name
+        {
+            content.push_str(&text_entry("name", name));
+        }
+
+        files.push((META_CPP, content));
+    }
+
+    files
+}
+
+fn mod_cpp_content(mod_cpp: &ModCppSection, name: Option<&String>) -> String
+{
+    let entries = [
+        ("name", name),
+        ("picture", mod_cpp.picture.as_ref()),
+        ("logo", mod_cpp.logo.as_ref()),
+        ("logoOver", mod_cpp.logo_over.as_ref()),
+        ("tooltip", mod_cpp.tooltip.as_ref()),
+        ("overview", mod_cpp.overview.as_ref()),
+        ("action", mod_cpp.action.as_ref())
+    ];
+
+    let mut content: String = entries.iter()
+        .filter_map(|(key, value)| Some(text_entry(key, (*value)?)))
+        .collect();
+
+    if let Some(published_id) = mod_cpp.published_id
+    {
+        content.push_str(&format!("publishedid = {published_id};\n"));
+    }
+
+    content
+}
+
+/// Quotes are escaped by doubling them, like in every Arma config
+fn text_entry(key: &str, value: &str) -> String
+{
+    format!("{key} = \"{}\";\n", value.replace('"', "\"\""))
+}
+
+/// Parses the key = value; entries of a mod.cpp, reporting the line and problem of the first syntax error
+struct Parser
+{
+    characters: Vec<char>,
+    position: usize,
+    line: usize
+}
+
+impl Parser
+{
+    fn new(content: &str) -> Self
+    {
+        Self {
+            characters: content.chars().collect(),
+            position: 0,
+            line: 1,
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<ModInfoEntry>, (usize, String)>
+    {
+        let mut entries: Vec<ModInfoEntry> = vec![];
+
+        loop
+        {
+            self.skip_blanks();
+
+            let Some(first) = self.peek(0) else {
+                break;
+            };
+
+            if !first.is_ascii_alphabetic() && first != '_'
+            {
+                return Err((self.line, format!("expected a key, found '{first}'")));
+            }
+
+            let line = self.line;
+
+            let key = self.take_while(|character| character.is_ascii_alphanumeric() || character == '_' || character == '[' || character == ']');
+
+            self.skip_blanks();
+
+            if self.next() != Some('=')
+            {
+                return Err((self.line, format!("expected = after \"{key}\"")));
+            }
+
+            self.skip_blanks();
+
+            let value = match self.peek(0)
+            {
+                Some('"') => ModInfoValue::Text(self.read_text(&key)?),
+                Some('{') => {
+                    self.next();
+
+                    let array = self.take_while(|character| character != '}' && character != ';');
+
+                    if self.next() != Some('}')
+                    {
+                        return Err((self.line, format!("expected }} at the end of \"{key}\"")));
+                    }
+
+                    ModInfoValue::Array(String::from(array.trim()))
+                }
+                _ => {
+                    let number = self.take_while(|character| character != ';' && character != '\n');
+                    let number = number.trim();
+
+                    if number.parse::<f64>().is_err()
+                    {
+                        return Err((self.line, format!("the value of \"{key}\" is neither a quoted string nor a number")));
+                    }
+
+                    ModInfoValue::Number(String::from(number))
+                }
+            };
+
+            self.skip_blanks();
+
+            if self.next() != Some(';')
+            {
+                return Err((line, format!("expected ; after the value of \"{key}\"")));
+            }
+
+            entries.push(ModInfoEntry {
+                line,
+                key,
+                value,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn read_text(&mut self, key: &str) -> Result<String, (usize, String)>
+    {
+        let line = self.line;
+
+        self.next();
+
+        let mut text = String::new();
+
+        loop
+        {
+            match self.next()
+            {
+                Some('"') if self.peek(0) == Some('"') => {
+                    self.next();
+                    text.push('"');
+                }
+                Some('"') => return Ok(text),
+                Some(character) => text.push(character),
+                None => return Err((line, format!("the string of \"{key}\" is not closed")))
+            }
+        }
+    }
+
+    /// Skips whitespace, // line comments and /* block comments */
+    fn skip_blanks(&mut self)
+    {
+        loop
+        {
+            match (self.peek(0), self.peek(1))
+            {
+                (Some(character), _) if character.is_whitespace() => {
+                    self.next();
+                }
+                (Some('/'), Some('/')) => {
+                    self.take_while(|character| character != '\n');
+                }
+                (Some('/'), Some('*')) => {
+                    self.position += 2;
+
+                    while self.peek(0).is_some() && !(self.peek(0) == Some('*') && self.peek(1) == Some('/'))
+                    {
+                        self.next();
+                    }
+
+                    self.position = (self.position + 2).min(self.characters.len());
+                }
+                _ => break
+            }
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> String
+    {
+        let mut taken = String::new();
+
+        while let Some(character) = self.peek(0).filter(|character| predicate(*character))
+        {
+            self.next();
+            taken.push(character);
+        }
+
+        taken
+    }
+
+    fn peek(&self, offset: usize) -> Option<char>
+    {
+        self.characters.get(self.position + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char>
+    {
+        let character = self.peek(0)?;
+
+        self.position += 1;
+
+        if character == '\n'
+        {
+            self.line += 1;
+        }
+
+        Some(character)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::path::Path;
+    use crate::commands::scaffold;
+    use crate::packers::mod_packer_error::{ModPackerError, PipelineStage};
+    use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+    use super::{generated_files, has_mod_info_file_name, is_mod_info_file, ModInfoFile, ModInfoValue};
+
+    fn parse(file_name: &str, content: &str) -> Result<ModInfoFile, ModPackerError>
+    {
+        ModInfoFile::parse(&Path::new("mod").join(file_name), content, PipelineStage::CheckReferences)
+    }
+
+    /// The problems of the file, errors first
+    fn problems(file_name: &str, content: &str) -> Vec<String>
+    {
+        let (errors, warnings) = parse(file_name, content).unwrap().validate(PipelineStage::CheckReferences);
+
+        errors.iter().map(|error| error.to_string()).chain(warnings).collect()
+    }
+
+    #[test]
+    fn parser_reads_text_numbers_arrays_and_skips_comments()
+    {
+        let mod_cpp = parse("mod.cpp", concat!(
+            "// my mod\n",
+            "name = \"My \"\"quoted\"\" Mod\";\n",
+            "/* block\n",
+            "   comment */ hideName = 1;\n",
+            "dlcColor[] = { 1, 0.5, 0, 1 };\n",
+            "overview = \"two\n",
+            "lines\"; picture = \"logo.paa\";\n",
+        )).unwrap();
+
+        let entries: Vec<(usize, &str, &ModInfoValue)> = mod_cpp.entries.iter()
+            .map(|entry| (entry.line, entry.key.as_str(), &entry.value))
+            .collect();
+
+        assert_eq!(entries, vec![
+            (2, "name", &ModInfoValue::Text(String::from("My \"quoted\" Mod"))),
+            (4, "hideName", &ModInfoValue::Number(String::from("1"))),
+            (5, "dlcColor[]", &ModInfoValue::Array(String::from("1, 0.5, 0, 1"))),
+            (6, "overview", &ModInfoValue::Text(String::from("two\nlines"))),
+            (7, "picture", &ModInfoValue::Text(String::from("logo.paa"))),
+        ]);
+
+        assert_eq!(mod_cpp.pictures(), vec![(7, "logo.paa")]);
+    }
+
+    #[test]
+    fn parser_reports_the_line_of_syntax_errors()
+    {
+        let line_of = |content: &str| match parse("mod.cpp", content)
+        {
+            Err(ModPackerError::InvalidModInfo { line, .. }) => line,
+            other => panic!("expected a syntax error, got {other:?}")
+        };
+
+        assert_eq!(line_of("name = \"My Mod\";\nauthor = \"me\"\n"), 2);
+        assert_eq!(line_of("name = \"My Mod\";\n\nauthor = \"me;\n"), 3);
+        assert_eq!(line_of("name = \"My Mod\";\nhideName = yes;\n"), 2);
+        assert_eq!(line_of("name \"My Mod\";\n"), 1);
+    }
+
+    #[test]
+    fn validation_checks_keys_and_value_types()
+    {
+        let problems = problems("mod.cpp", concat!(
+            "name = \"My Mod\";\n",
+            "NAME = \"Other\";\n",
+            "hideName = \"yes\";\n",
+            "publishedid = 1.5;\n",
+            "colour = \"red\";\n",
+        ));
+
+        assert_eq!(problems.len(), 4, "{problems:?}");
+        assert!(problems[0].contains("\"NAME\" is set more than once"));
+        assert!(problems[1].contains("\"hideName\" must be a number, not a quoted string"));
+        assert!(problems[2].contains("must be the id of the Steam Workshop item"));
+        assert!(problems[3].contains("Unknown key \"colour\""));
+
+        // meta.cpp only knows the keys the launcher writes
+        let problems = self::problems("META.CPP", "protocol = 1;\npublishedid = 123;\nname = \"My Mod\";\npicture = \"logo.paa\";\n");
+
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("Unknown key \"picture\""));
+    }
+
+    #[test]
+    fn scaffold_and_manifest_mod_cpp_are_valid()
+    {
+        assert!(problems("mod.cpp", &scaffold::mod_cpp("My \"quoted\" Mod")).is_empty());
+
+        let manifest: ArmapackToml = toml::from_str(concat!(
+            "[mod]\nname = \"My Mod\"\n",
+            "[mod_cpp]\npicture = \"logo.paa\"\nlogo_over = \"logo.paa\"\naction = \"https://example.com\"\npublished_id = 123\n",
+        )).unwrap();
+
+        let files = generated_files(&manifest);
+
+        assert_eq!(files.iter().map(|(file_name, _)| *file_name).collect::<Vec<_>>(), vec!["mod.cpp", "meta.cpp"]);
+
+        for (file_name, content) in &files
+        {
+            assert!(problems(file_name, content).is_empty(), "{file_name}: {content}");
+        }
+    }
+
+    #[test]
+    fn mod_info_file_names_match_at_any_depth()
+    {
+        let mod_folder = Path::new("mod");
+
+        assert!(is_mod_info_file(mod_folder, &mod_folder.join("Mod.cpp")));
+        assert!(!is_mod_info_file(mod_folder, &mod_folder.join("addons").join("main").join("mod.cpp")));
+
+        assert!(has_mod_info_file_name(&mod_folder.join("addons").join("main").join("META.cpp")));
+        assert!(!has_mod_info_file_name(&mod_folder.join("addons").join("main").join("config.cpp")));
+    }
+}
\ No newline at end of file
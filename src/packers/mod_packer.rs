@@ -1,37 +1,362 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
-use std::io::{Error};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use ignore::{WalkBuilder};
+use rayon::prelude::*;
 use remove_empty_subdirs::remove_empty_subdirs;
 use crate::converters::config_converter::ArmaToolsConfigConverter;
-use crate::converters::FileConverter;
+use crate::cache::build_cache::{BuildCache, CACHE_FOLDER_NAME};
+use crate::console;
+use crate::console::Progress;
+use crate::converters::converter_registry::{BuiltinConverter, ConversionDirection, ConverterRegistry, ConverterRule};
+use crate::converters::RegisteredConverter;
 use crate::converters::paa_converter::ArmaToolsPAAConverter;
+use crate::ignore_rules::{IgnoreMatch, IgnoreRules};
 use crate::json_files::key_json::key_json_handler::KeyJsonHandler;
+use crate::json_files::key_json::model::key_json::KeyJson;
+use crate::json_files::pbo_json::model::pbo_header::PBOHeader;
+use crate::json_files::pbo_json::pbo_json_handler::PBOJsonHandler;
 use crate::json_files::path_json::model::path_json::PathJson;
+use crate::packers::build_hooks::{run_hook, BuildHook, HookContext};
+use crate::packers::build_plan::{FileAction, PlannedFile};
+use crate::packers::mod_info_files;
+use crate::packers::mod_packer_error::{ModPackerError, PipelineStage, StageContext};
 use crate::packers::Packer;
 use crate::packers::pbo_packer::{ArmaToolsPBOPacker};
+use crate::packers::pbo_prefixes::{self, PboPrefix};
+use crate::packers::pbo_references;
 use crate::signing::pbo_signer::{ArmaToolsPBOSigner, PBOSigner};
-use crate::utils::check_source_and_destination;
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+use crate::utils::{check_source_and_destination, has_extension, sha1_of_file};
 
-pub trait ModPacker : Packer {}
+#[derive(Debug, Default, Clone)]
+pub struct PackOptions
+{
+    /// Fail packing if any PBO would end up without a signature
+    pub require_signed: bool,
+
+    /// Fail packing on file references to files that do not exist instead of warning about them
+    pub strict_references: bool,
+
+    /// Reuse converted files, pbos and signatures from the build cache in the source folder
+    pub use_cache: bool,
+
+    /// Project manifest with defaults for prefixes, signing and converters, the per-folder json files take precedence
+    pub manifest: ArmapackToml,
+
+    /// Build report written after packing, never copied into the mod even if it is inside the source folder
+    pub report_file: Option<PathBuf>,
+
+    /// Timestamp of every pbo entry, from SOURCE_DATE_EPOCH or 0 so that builds are reproducible
+    pub entry_timestamp: u32
+}
+
+/// What a pack run actually rebuilt and what it reused from the build cache
+#[derive(Debug, Default, Clone)]
+pub struct PackSummary
+{
+    pub converted_files: usize,
+    pub reused_conversions: usize,
+    pub packed_pbos: Vec<String>,
+    pub reused_pbos: usize,
+    pub signatures: usize,
+    pub reused_signatures: usize,
+
+    /// Every converted file, relative to the mod folder
+    pub conversions: Vec<ConvertedFile>,
+
+    /// Pbo folders packed without a key.json, which are therefore not signed
+    pub unsigned_pbos: Vec<PathBuf>,
+
+    /// Broken file references without strict references, references with the wrong case and dependencies whose pbos could not be read
+    pub reference_warnings: Vec<String>,
+
+    /// Files left out of the mod by .modignore rules or as unused public keys, relative to the source folder
+    pub ignored_files: Vec<PlannedFile>,
+
+    pub stage_durations: Vec<(PipelineStage, Duration)>
+}
+
+impl PackSummary
+{
+    fn finish_stage(&mut self, stage: PipelineStage, started: Instant)
+    {
+        self.stage_durations.push((stage, started.elapsed()));
+    }
+}
+
+/// A file converted while packing
+#[derive(Debug, Clone)]
+pub struct ConvertedFile
+{
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub converter: String,
+    pub source_sha1: String,
+
+    /// Whether the converted file was reused from the build cache
+    pub reused: bool
+}
+
+/// Where the authorities a pbo is signed with come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource
+{
+    KeyFile(PathBuf),
+    Authorities(Vec<String>)
+}
+
+impl KeySource
+{
+    /// Resolves the signing of a pbo folder: its own key.json, then the manifest's addon override, then the key.json in the
+    /// mod root, then the manifest's default authorities. None if the pbo is not signed.
+    pub fn resolve<F: Fn(&Path) -> bool>(pbo_folder: &Path, default_key_file: &Path, manifest: &ArmapackToml, exists: F) -> Option<Self>
+    {
+        let folder_key_file = pbo_folder.join("key.json");
+
+        if exists(&folder_key_file)
+        {
+            return Some(KeySource::KeyFile(folder_key_file));
+        }
+
+        let addon_name = pbo_folder.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        if let Some(authorities) = manifest.addon_authorities(&addon_name)
+        {
+            return Some(KeySource::Authorities(authorities.clone()));
+        }
+
+        if exists(default_key_file)
+        {
+            return Some(KeySource::KeyFile(PathBuf::from(default_key_file)));
+        }
+
+        if !manifest.signing.authorities.is_empty()
+        {
+            return Some(KeySource::Authorities(manifest.signing.authorities.clone()));
+        }
+
+        None
+    }
+}
+
+/// The pbos of the current destination, reused for the pbo folders that did not change since it was built
+struct PreviousBuild<'a>
+{
+    addons_folder: PathBuf,
+
+    /// Pbo folders relative to the mod folder that have to be packed again
+    changed_pbo_folders: &'a BTreeSet<PathBuf>
+}
+
+impl PreviousBuild<'_>
+{
+    /// The pbo folders of the mod folder that did not change and whose pbo is part of the previous build
+    fn unchanged_pbo_folders(&self, mod_folder: &Path) -> Result<BTreeSet<PathBuf>, ModPackerError>
+    {
+        let pbo_folders = ArmaToolsModPacker::identify_pbo_folders(mod_folder)?;
+
+        Ok(pbo_folders.into_iter()
+            .filter(|pbo_folder| !self.changed_pbo_folders.contains(&ArmaToolsModPacker::relative_to(pbo_folder, mod_folder)))
+            .filter(|pbo_folder| self.pbo_file_of(pbo_folder).is_file())
+            .collect())
+    }
+
+    fn copy_pbos(&self, pbo_folders: &BTreeSet<PathBuf>, addons_folder: &Path) -> Result<(), ModPackerError>
+    {
+        let stage = PipelineStage::Pack;
+
+        fs::create_dir_all(addons_folder).in_stage(stage, addons_folder)?;
+
+        for pbo_folder in pbo_folders
+        {
+            let pbo_file = self.pbo_file_of(pbo_folder);
+
+            let pbo_file_name = pbo_file.file_name().unwrap_or_default();
+
+            console::verbose(&format!("Kept: {}", pbo_file_name.to_string_lossy()));
+
+            fs::copy(&pbo_file, addons_folder.join(pbo_file_name)).in_stage(stage, &pbo_file)?;
+        }
+
+        Ok(())
+    }
+
+    fn pbo_file_of(&self, pbo_folder: &Path) -> PathBuf
+    {
+        self.addons_folder.join(format!("{}.pbo", ArmaToolsModPacker::file_name_of(pbo_folder)))
+    }
+}
 
 pub struct ArmaToolsModPacker {
     pbo_packer: ArmaToolsPBOPacker<String>,
-    config_converter: ArmaToolsConfigConverter<String>,
-    paa_converter: ArmaToolsPAAConverter<String>,
-    rvmat_converter: ArmaToolsConfigConverter<String>,
-    pbo_signer: ArmaToolsPBOSigner<String>
+    converters: ConverterRegistry,
+    pbo_signer: ArmaToolsPBOSigner<String>,
+    options: PackOptions
 }
 
-impl ModPacker for ArmaToolsModPacker {}
-
 impl Packer for ArmaToolsModPacker
 {
-    type PackError = Error;
-    type UnpackError = Error;
+    type PackError = ModPackerError;
+    type UnpackError = ModPackerError;
 
     fn pack<SourcePath: AsRef<Path>, DestPath: AsRef<Path>>(&self, source_folder: SourcePath, destination_folder: DestPath) -> Result<(), Self::PackError>
+    {
+        self.pack_with_summary(source_folder, destination_folder)?;
+
+        Ok(())
+    }
+
+    fn unpack<SourcePath: AsRef<Path>, DestPath: AsRef<Path>>(&self, source_folder: SourcePath, destination_folder: DestPath) -> Result<(), Self::UnpackError>
+    {
+        // Input:
+        // @Mod
+        //  |- mod.cpp
+        //  |- meta.cpp
+        //  |- logo.paa
+        //  |...
+        //  |- [keys]
+        //      |- first_authority.bikey
+        //      |...
+        //      |- last_authority.bikey
+        //  |- [Addons]
+        //      |- first.pbo
+        //          |- config.bin
+        //          |- [Data]
+        //              |- tex.paa
+        //              |- mat.rvmat
+        //              |...
+        //      |...
+        //      |- last.pbo
+        //          |...
+        //      |- first.bisign
+        //      |...
+        //      |- last.bisign
+
+        // Output:
+        // @Mod
+        //  |- mod.cpp
+        //  |- logo.png
+        //  |...
+        //  |- addons
+        //      |- first
+        //          |- config.cpp
+        //          |- pbo.json
+        //          |- [Data]
+        //              |- tex.png
+        //              |- mat.rvmat
+        //              |...
+        //      |...
+        //      |- last
+        //          |...
+
+
+        if destination_folder.as_ref().exists()
+        {
+            fs::remove_dir_all(&destination_folder).in_stage(PipelineStage::Prepare, &destination_folder)?;
+        }
+
+        check_source_and_destination(source_folder.as_ref(), destination_folder.as_ref(), true, true).in_stage(PipelineStage::Prepare, &source_folder)?;
+
+        let addons_folder_path = destination_folder.as_ref().join("addons");
+        let keys_folder_path = destination_folder.as_ref().join("keys");
+
+        fs::create_dir_all(&addons_folder_path).in_stage(PipelineStage::Prepare, &addons_folder_path)?;
+        fs::create_dir_all(&keys_folder_path).in_stage(PipelineStage::Prepare, &keys_folder_path)?;
+
+        Self::copy_files_in_folder(source_folder.as_ref(), destination_folder.as_ref(), true)?;
+        self.unpack_pbos_in_folder(source_folder.as_ref(), destination_folder.as_ref(), true)?;
+
+        let mut conversion_errors: Vec<ModPackerError> = vec![];
+
+        self.convert_files(destination_folder.as_ref(), true, &mut conversion_errors)?;
+
+        ModPackerError::collect(PipelineStage::Convert, &destination_folder, conversion_errors)?;
+
+        Ok(())
+    }
+}
+
+impl ArmaToolsModPacker
+{
+    pub fn create(paths: PathJson, options: PackOptions) -> Self
+    {
+        let timeout = paths.timeout();
+
+        let paa_converter = ArmaToolsPAAConverter
+        {
+            tool_path: paths.paa_converter_path,
+            launcher: paths.launcher.clone(),
+            timeout,
+        };
+
+        let config_converter = ArmaToolsConfigConverter
+        {
+            tool_path: paths.config_converter_path,
+            launcher: paths.launcher.clone(),
+            timeout,
+        };
+
+        let rvmat_converter = ArmaToolsConfigConverter
+        {
+            tool_path: paths.rvmat_converter_path,
+            launcher: paths.launcher.clone(),
+            timeout,
+        };
+
+        let pbo_packer = ArmaToolsPBOPacker
+        {
+            tool_path: paths.pbo_packer_path,
+            launcher: paths.launcher.clone(),
+            timeout,
+            prefix: None,
+            timestamp: options.entry_timestamp,
+        };
+
+        let pbo_signer = ArmaToolsPBOSigner
+        {
+            tool_path: paths.pbo_signer_path,
+            launcher: paths.launcher,
+            timeout,
+        };
+
+        let mut converters = ConverterRegistry::for_manifest(&options.manifest.converters);
+
+        converters.set_command_timeout(timeout);
+
+        converters.register(BuiltinConverter::Paa, paa_converter);
+        converters.register(BuiltinConverter::Config, config_converter);
+        converters.register(BuiltinConverter::Rvmat, rvmat_converter);
+
+        Self {
+            pbo_packer,
+            converters,
+            pbo_signer,
+            options
+        }
+    }
+
+    pub fn options(&self) -> &PackOptions
+    {
+        &self.options
+    }
+
+
+    /// Packs the mod like [Packer::pack] and reports what had to be rebuilt and what was reused from the build cache
+    pub fn pack_with_summary<SourcePath: AsRef<Path>, DestPath: AsRef<Path>>(&self, source_folder: SourcePath, destination_folder: DestPath) -> Result<PackSummary, ModPackerError>
+    {
+        self.pack_into_destination(source_folder.as_ref(), destination_folder.as_ref(), None)
+    }
+
+    /// Packs the mod like [Self::pack_with_summary], but only converts and packs the given pbo folders (relative to the
+    /// source folder). Every other pbo is taken from the current destination, which must hold a complete build of the mod.
+    pub fn pack_changed_pbos<SourcePath: AsRef<Path>, DestPath: AsRef<Path>>(&self, source_folder: SourcePath, destination_folder: DestPath, changed_pbo_folders: &BTreeSet<PathBuf>) -> Result<PackSummary, ModPackerError>
+    {
+        self.pack_into_destination(source_folder.as_ref(), destination_folder.as_ref(), Some(changed_pbo_folders))
+    }
+
+    fn pack_into_destination(&self, source_folder: &Path, destination_folder: &Path, changed_pbo_folders: Option<&BTreeSet<PathBuf>>) -> Result<PackSummary, ModPackerError>
     {
         // Input:
         // @Mod
@@ -41,6 +366,8 @@ impl Packer for ArmaToolsModPacker
         //  |- README.md
         //  |- .modignore       // contains misc_development and IMPORTANT_SECRET.md
         //  |- .convertignore
+        //  |- [key.json]       // Default key for every pbo folder without its own key.json
+        //  |- [armapack.toml]  // Project manifest: prefix pattern, default authorities, converters, addon overrides
         //  |...
         //  |- [misc_development]
         //      |- README.md
@@ -62,7 +389,7 @@ impl Packer for ArmaToolsModPacker
         //      |- first
         //          |- config.cpp
         //          |- pbo.json
-        //          |- [key.json]           // Without key.json, resulting .pbo is not signed
+        //          |- [key.json]           // Without key.json (here, in the manifest or in the mod root), resulting .pbo is not signed
         //          |- [Data]
         //              |- tex.png
         //              |- mat.rvmat
@@ -71,19 +398,28 @@ impl Packer for ArmaToolsModPacker
         //      |- last
         //          |...
 
-        // - Copy all files not excluded by .modignore into output folder
-        // - Convert all files not excluded by .convertignore and delete originals (in output folder)
-        // - Copy all key.json files as [mod_name]_key.json into addons folder
+        // - Copy all files not excluded by .modignore into output folder, except private keys and the manifest
+        // - Write mod.cpp and meta.cpp from the manifest, if it describes them
+        // - Check that every pbo prefix is valid and unique and that the #include paths of the configs exist
+        // - Check the file paths in configs, rvmats and scripts against the converted files of the mod and its dependencies
+        // - Validate the keys of mod.cpp and meta.cpp and check their pictures
+        // - Convert all files not excluded by .convertignore and delete originals (in output folder), reusing cached conversions,
+        //   unless the converter is disabled in the manifest. mod.cpp and meta.cpp are never converted
+        // - Add the prefix and headers from the manifest to every pbo.json that does not set them itself
+        // - Copy all key.json files as [mod_name]_key.json into addons folder, falling back to the manifest's addon authorities,
+        //   the mod root key.json and the manifest's default authorities
+        // - Fail if unsigned pbos are not allowed and a pbo folder has no key.json
         // - Build all folders containing pbo.json into .pbo files in addons folder and delete originals (in output folder)
-        // - Sign each pbo with a corresponding key.json
+        // - Sign each pbo once for every authority listed in its corresponding key.json, using the private keys in the source
         // - Copy each used .bikey into keys folder
         // - Delete key.json files
         // - Prune empty folders
+        // - All of the above happens in a staging folder next to the destination, which replaces the destination only on success
 
         // Output:
         // @Mod
-        //  |- mod.cpp
-        //  |- meta.cpp
+        //  |- mod.cpp          // Kept as text, generated from the manifest's [mod_cpp] section if it has one
+        //  |- meta.cpp         // Generated from the manifest if it sets a published_id, else copied if it exists
         //  |- logo.paa
         //  |- README.md
         //  |- [mod_guide]
@@ -104,251 +440,421 @@ impl Packer for ArmaToolsModPacker
         //      |...
         //      |- last.pbo
         //          |...
-        //      |- first.bisign
+        //      |- first.pbo.first_authority.bisign  // one signature per authority in key.json
         //      |...
-        //      |- last.bisign
+        //      |- last.pbo.last_authority.bisign
 
-        if destination_folder.as_ref().exists()
+        // When only some pbo folders changed, the other pbos are copied from the destination instead of being converted
+        // and packed again. They are still signed, which reuses their signatures from the build cache.
+
+        // the tools run in folders of the staging folder, so paths into the source (e.g. the private keys) must not be relative
+        let source_folder = &std::path::absolute(source_folder).in_stage(PipelineStage::Prepare, source_folder)?;
+
+        let staging_folder = Self::staging_folder_for(destination_folder).in_stage(PipelineStage::Prepare, destination_folder)?;
+
+        if staging_folder.exists()
         {
-            fs::remove_dir_all(&destination_folder).unwrap();
+            fs::remove_dir_all(&staging_folder).in_stage(PipelineStage::Prepare, &staging_folder)?;
         }
 
-        check_source_and_destination(&source_folder, &destination_folder, true, true).unwrap();
+        check_source_and_destination(source_folder, &staging_folder, true, true).in_stage(PipelineStage::Prepare, source_folder)?;
 
-        let addons_folder_path = destination_folder.as_ref().join("addons");
-        let keys_folder_path = destination_folder.as_ref().join("keys");
+        let hook_context = HookContext {
+            mod_folder: source_folder.clone(),
+            staging_folder: Some(staging_folder.clone()),
+            destination_folder: std::path::absolute(destination_folder).in_stage(PipelineStage::Prepare, destination_folder)?,
+            ..HookContext::default()
+        };
 
-        Self::copy_raw_files_for_packing(&source_folder, &destination_folder).unwrap();
+        let previous_build = changed_pbo_folders.map(|changed_pbo_folders| PreviousBuild {
+            addons_folder: destination_folder.join("addons"),
+            changed_pbo_folders,
+        });
 
-        Self::delete_mod_ignore_files(&destination_folder).unwrap();
+        let result = self.build_mod_folder(source_folder, &staging_folder, &hook_context, previous_build.as_ref())
+            .and_then(|summary| {
+                Self::check_for_private_keys(&staging_folder)?;
 
-        self.convert_raw_files(&destination_folder).unwrap();
+                Ok(summary)
+            });
 
-        Self::delete_convert_ignore_files(&destination_folder).unwrap();
+        let mut summary = match result
+        {
+            Ok(summary) => summary,
+            Err(error) => {
+                // the error is more useful than a failure to clean up, a stale staging folder is removed on the next run
+                let _ = fs::remove_dir_all(&staging_folder);
 
-        let pbo_paths: HashSet<PathBuf> = Self::identify_pbo_folders(&destination_folder).unwrap();
+                return Err(error);
+            }
+        };
 
-        Self::copy_key_files_from_pbo_folders(&addons_folder_path, &pbo_paths).unwrap();
+        let started = Instant::now();
 
-        Self::delete_non_addon_key_files(&destination_folder, &addons_folder_path).unwrap();
+        Self::swap_into_destination(&staging_folder, destination_folder)?;
 
-        self.pack_pbo_folders(&addons_folder_path, &pbo_paths).unwrap();
+        self.run_hook(BuildHook::AfterPack, &hook_context.without_staging_folder())?;
 
-        Self::delete_original_pbo_folders(&pbo_paths).unwrap();
+        summary.finish_stage(PipelineStage::Swap, started);
 
-        let used_bikeys: HashSet<String> = self.sign_packed_pbos(&addons_folder_path, &keys_folder_path).unwrap();
+        Ok(summary)
+    }
 
-        Self::delete_private_keys(&keys_folder_path).unwrap();
+    /// Folder next to the destination the mod is built in, so the destination only ever holds a complete build
+    pub fn staging_folder_for<A: AsRef<Path>>(destination_folder: A) -> std::io::Result<PathBuf>
+    {
+        Self::sibling_folder_of(destination_folder, "staging")
+    }
 
-        Self::delete_unused_bikeys(&keys_folder_path, &used_bikeys).unwrap();
+    /// Folder next to the destination the previous build is moved to while swapping in the new one
+    pub fn previous_folder_for<A: AsRef<Path>>(destination_folder: A) -> std::io::Result<PathBuf>
+    {
+        Self::sibling_folder_of(destination_folder, "previous")
+    }
 
-        Self::delete_addon_key_json_files(&addons_folder_path).unwrap();
+    fn sibling_folder_of<A: AsRef<Path>>(destination_folder: A, suffix: &str) -> std::io::Result<PathBuf>
+    {
+        let destination_folder = std::path::absolute(destination_folder.as_ref())?;
 
-        Self::prune_empty_directories(&destination_folder).unwrap();
+        let folder_name = destination_folder.file_name()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Destination Folder has no name"))?
+            .to_string_lossy()
+            .to_string();
 
-        Ok(())
+        Ok(destination_folder.with_file_name(format!(".{folder_name}.{suffix}")))
     }
 
-    fn unpack<SourcePath: AsRef<Path>, DestPath: AsRef<Path>>(&self, source_folder: SourcePath, destination_folder: DestPath) -> Result<(), Self::UnpackError>
+    /// Runs the packing pipeline, building the finished mod in the given (empty) mod folder
+    fn build_mod_folder(&self, source_folder: &Path, destination_folder: &Path, hook_context: &HookContext, previous_build: Option<&PreviousBuild>) -> Result<PackSummary, ModPackerError>
     {
-        // Input:
-        // @Mod
-        //  |- mod.cpp
-        //  |- meta.cpp
-        //  |- logo.paa
-        //  |...
-        //  |- [keys]
-        //      |- first_authority.bikey
-        //      |...
-        //      |- last_authority.bikey
-        //  |- [Addons]
-        //      |- first.pbo
-        //          |- config.bin
-        //          |- [Data]
-        //              |- tex.paa
-        //              |- mat.rvmat
-        //              |...
-        //      |...
-        //      |- last.pbo
-        //          |...
-        //      |- first.bisign
-        //      |...
-        //      |- last.bisign
+        let addons_folder_path = destination_folder.join("addons");
+        let keys_folder_path = destination_folder.join("keys");
 
-        // Output:
-        // @Mod
-        //  |- mod.cpp
-        //  |- logo.png
-        //  |...
-        //  |- addons
-        //      |- first
-        //          |- config.cpp
-        //          |- pbo.json
-        //          |- [Data]
-        //              |- tex.png
-        //              |- mat.rvmat
-        //              |...
-        //      |...
-        //      |- last
-        //          |...
+        // private keys are never copied, signing reads them from the source
+        let private_keys_folder_path = source_folder.join("keys");
+
+        let cache = self.options.use_cache.then(|| BuildCache::for_mod_folder(source_folder));
+
+        let mut summary = PackSummary::default();
+
+        let started = Instant::now();
+
+        self.run_hook(BuildHook::BeforeCopy, hook_context)?;
+
+        summary.ignored_files = Self::copy_raw_files_for_packing(source_folder, destination_folder, self.options.report_file.as_deref())?;
+
+        Self::delete_mod_ignore_files(destination_folder)?;
+
+        self.generate_mod_info_files(destination_folder)?;
+
+        summary.finish_stage(PipelineStage::Copy, started);
+
+        let started = Instant::now();
+
+        let prefixes = self.check_pbo_prefixes(destination_folder)?;
+
+        summary.finish_stage(PipelineStage::CheckPrefixes, started);
+
+        let started = Instant::now();
+
+        self.check_file_references(destination_folder, &prefixes, &mut summary)?;
+
+        summary.finish_stage(PipelineStage::CheckReferences, started);
+
+        let started = Instant::now();
+
+        let kept_pbo_folders = match previous_build
+        {
+            Some(previous_build) => previous_build.unchanged_pbo_folders(destination_folder)?,
+            None => BTreeSet::new()
+        };
+
+        self.convert_raw_files(destination_folder, &kept_pbo_folders, cache.as_ref(), &mut summary)?;
+
+        Self::delete_convert_ignore_files(destination_folder)?;
+
+        self.run_hook(BuildHook::AfterConvert, hook_context)?;
+
+        summary.finish_stage(PipelineStage::Convert, started);
+
+        let started = Instant::now();
+
+        let pbo_paths: BTreeSet<PathBuf> = Self::identify_pbo_folders(destination_folder)?;
+
+        self.apply_manifest_headers(&pbo_paths)?;
+
+        summary.finish_stage(PipelineStage::IdentifyPbos, started);
+
+        let started = Instant::now();
+
+        let default_key_file_path = destination_folder.join("key.json");
+
+        let unsigned_pbo_folders = self.copy_key_files_from_pbo_folders(&addons_folder_path, &pbo_paths, &default_key_file_path)?;
+
+        if self.options.require_signed
+        {
+            let errors = unsigned_pbo_folders.into_iter()
+                .map(|path| ModPackerError::Unsigned {
+                    stage: PipelineStage::CopyKeys,
+                    path,
+                })
+                .collect();
+
+            ModPackerError::collect(PipelineStage::CopyKeys, &addons_folder_path, errors)?;
+        }
+        else {
+            summary.unsigned_pbos = unsigned_pbo_folders.iter()
+                .map(|path| Self::relative_to(path, destination_folder))
+                .collect();
+        }
+
+        Self::delete_non_addon_key_files(destination_folder, &addons_folder_path)?;
+
+        summary.finish_stage(PipelineStage::CopyKeys, started);
+
+        let started = Instant::now();
+
+        if let Some(previous_build) = previous_build
+        {
+            previous_build.copy_pbos(&kept_pbo_folders, &addons_folder_path)?;
+
+            summary.reused_pbos += kept_pbo_folders.len();
+        }
+
+        let changed_pbo_paths: BTreeSet<PathBuf> = pbo_paths.difference(&kept_pbo_folders).cloned().collect();
+
+        self.pack_pbo_folders(&addons_folder_path, &changed_pbo_paths, cache.as_ref(), hook_context, &mut summary)?;
+
+        Self::delete_original_pbo_folders(&pbo_paths)?;
+
+        summary.finish_stage(PipelineStage::Pack, started);
+
+        let started = Instant::now();
+
+        let used_bikeys: HashSet<String> = self.sign_packed_pbos(&addons_folder_path, &private_keys_folder_path, cache.as_ref(), &mut summary)?;
 
+        self.run_hook(BuildHook::AfterSign, hook_context)?;
 
-        if destination_folder.as_ref().exists()
+        summary.finish_stage(PipelineStage::Sign, started);
+
+        let started = Instant::now();
+
+        for unused_bikey in Self::delete_unused_bikeys(&keys_folder_path, &used_bikeys)?
         {
-            fs::remove_dir_all(&destination_folder).unwrap();
+            summary.ignored_files.push(PlannedFile {
+                path: Path::new("keys").join(unused_bikey),
+                pbo: None,
+                action: FileAction::Skip { reason: String::from("public key not used by any pbo") },
+            });
         }
 
-        check_source_and_destination(source_folder.as_ref(), destination_folder.as_ref(), true, true).unwrap();
+        Self::delete_addon_key_json_files(&addons_folder_path, &pbo_paths)?;
 
-        let addons_folder_path = destination_folder.as_ref().join("addons");
-        let keys_folder_path = destination_folder.as_ref().join("keys");
+        Self::prune_empty_directories(destination_folder)?;
+
+        summary.finish_stage(PipelineStage::Cleanup, started);
+
+        Ok(summary)
+    }
 
-        fs::create_dir_all(&addons_folder_path).unwrap();
-        fs::create_dir_all(&keys_folder_path).unwrap();
+    /// Runs the hook from the manifest, if any, failing the stage it belongs to if the hook fails
+    fn run_hook(&self, hook: BuildHook, context: &HookContext) -> Result<(), ModPackerError>
+    {
+        run_hook(hook, &self.options.manifest, context)
+            .map_err(|source| ModPackerError::Hook {
+                stage: hook.stage(),
+                path: context.pbo.clone().unwrap_or_else(|| context.mod_folder.clone()),
+                hook: hook.to_string(),
+                source,
+            })
+    }
+
+    /// Fails if a private key ended up in the built mod, it must never reach the destination
+    fn check_for_private_keys(mod_folder: &Path) -> Result<(), ModPackerError>
+    {
+        let stage = PipelineStage::Cleanup;
 
-        Self::copy_files_in_folder(source_folder.as_ref(), destination_folder.as_ref(), true).unwrap();
-        self.unpack_pbos_in_folder(source_folder.as_ref(), destination_folder.as_ref(), true).unwrap();
+        let mut private_keys: Vec<PathBuf> = Self::find_files(mod_folder, stage, |path| {
+            has_extension(path, "biprivatekey")
+        })?.into_iter().collect();
 
-        self.convert_files(destination_folder.as_ref(), true).unwrap();
+        private_keys.sort();
 
-        Ok(())
+        let errors = private_keys.into_iter()
+            .map(|path| ModPackerError::PrivateKeyInOutput {
+                stage,
+                path,
+            })
+            .collect();
+
+        ModPackerError::collect(stage, mod_folder, errors)
     }
-}
 
-impl ArmaToolsModPacker
-{
-    pub fn create(paths: PathJson) -> Self
+    /// Replaces the destination with the staging folder. The previous destination is moved aside and only deleted once the
+    /// new build is in place, so it is restored if the swap fails.
+    fn swap_into_destination(staging_folder: &Path, destination_folder: &Path) -> Result<(), ModPackerError>
     {
-        let paa_converter = ArmaToolsPAAConverter
-        {
-            tool_path: paths.paa_converter_path,
-        };
+        let stage = PipelineStage::Swap;
 
-        let config_converter = ArmaToolsConfigConverter
-        {
-            tool_path: paths.config_converter_path,
-        };
+        let previous_folder = Self::previous_folder_for(destination_folder).in_stage(stage, destination_folder)?;
 
-        let rvmat_converter = ArmaToolsConfigConverter
+        if previous_folder.exists()
         {
-            tool_path: paths.rvmat_converter_path
-        };
+            fs::remove_dir_all(&previous_folder).in_stage(stage, &previous_folder)?;
+        }
 
-        let pbo_packer = ArmaToolsPBOPacker
+        let has_previous_build = destination_folder.exists();
+
+        if has_previous_build
         {
-            tool_path: paths.pbo_packer_path,
-            prefix: None,
-        };
+            fs::rename(destination_folder, &previous_folder).in_stage(stage, destination_folder)?;
+        }
 
-        let pbo_signer = ArmaToolsPBOSigner
+        if let Err(error) = fs::rename(staging_folder, destination_folder)
         {
-            tool_path: paths.pbo_signer_path
-        };
+            if has_previous_build
+            {
+                let _ = fs::rename(&previous_folder, destination_folder);
+            }
 
-        Self {
-            pbo_packer,
-            config_converter,
-            paa_converter,
-            rvmat_converter,
-            pbo_signer
+            return Err(error).in_stage(stage, staging_folder);
+        }
+
+        if has_previous_build
+        {
+            fs::remove_dir_all(&previous_folder).in_stage(stage, &previous_folder)?;
         }
+
+        Ok(())
     }
 
-    fn copy_raw_files_for_packing<A: AsRef<Path>, B: AsRef<Path>>(source_folder: A, destination_folder: B) -> Result<(), Error>
+    /// Copies the files not excluded by .modignore, returning the excluded ones with the rule responsible like the build plan lists them
+    fn copy_raw_files_for_packing<A: AsRef<Path>, B: AsRef<Path>>(source_folder: A, destination_folder: B, report_file: Option<&Path>) -> Result<Vec<PlannedFile>, ModPackerError>
     {
+        let mut walked_files = HashSet::<PathBuf>::new();
+
         let walk = WalkBuilder::new(&source_folder)
             .standard_filters(false)
             .add_custom_ignore_filename(".modignore")
+            .filter_entry(|entry| entry.file_name() != CACHE_FOLDER_NAME)
             .build();
 
         for entry in walk
         {
-            let entry = entry.unwrap();
+            let entry = entry.in_stage(PipelineStage::Copy, &source_folder)?;
 
             if !entry.path().is_file()
             {
                 continue;
             }
 
-            if entry.file_name().to_str().unwrap() == ".modignore"
+            walked_files.insert(PathBuf::from(entry.path()));
+
+            if entry.file_name() == ".modignore"
             {
                 continue;
             }
 
-            Self::copy_file(entry.path(), &source_folder, &destination_folder).unwrap();
-        }
+            if has_extension(entry.path(), "biprivatekey")
+            {
+                continue;
+            }
 
-        Ok(())
-    }
+            if entry.path() == source_folder.as_ref().join(ArmapackToml::FILE_NAME)
+            {
+                continue;
+            }
 
-    fn copy_file<A: AsRef<Path>, B: AsRef<Path>, C: AsRef<Path>>(file_path: A, source_root_path: B, destination_root_path: C) -> Result<(), Error>
-    {
-        if !file_path.as_ref().starts_with(&source_root_path)
-        {
-            let file_path = file_path.as_ref();
-            let source_root_path = source_root_path.as_ref();
+            if report_file.is_some_and(|report_file| std::path::absolute(entry.path()).is_ok_and(|path| path == report_file))
+            {
+                continue;
+            }
 
-            panic!("File path: {0:?} is not inside Source Root Path: {1:?}", file_path, source_root_path);
+            Self::copy_file(entry.path(), &source_folder, &destination_folder)?;
         }
 
-        let relative_file_path = file_path.as_ref().strip_prefix(&source_root_path).unwrap();
-
-        let destination_path = destination_root_path.as_ref().join(relative_file_path);
-
-        let destination_parent_path = destination_path.parent().unwrap();
-
-        fs::create_dir_all(destination_parent_path).unwrap();
-
-        fs::copy(file_path, destination_path).unwrap();
-
-        Ok(())
+        Self::mod_ignored_files(source_folder.as_ref(), &walked_files)
     }
 
-    fn delete_mod_ignore_files<A: AsRef<Path>>(mod_folder: A) -> Result<(), Error>
+    /// The files of the source folder missing from the walked ones, which .modignore excluded
+    fn mod_ignored_files(source_folder: &Path, walked_files: &HashSet<PathBuf>) -> Result<Vec<PlannedFile>, ModPackerError>
     {
-        let mut ignore_files = HashSet::<PathBuf>::new();
+        let stage = PipelineStage::Copy;
 
-        let walk = WalkBuilder::new(&mod_folder)
-            .standard_filters(false)
-            .filter_entry(|entry| {
-                if entry.path().is_dir()
-                {
-                    return true;
-                }
+        let mod_ignore_rules = IgnoreRules::load(source_folder, ".modignore").in_stage(stage, source_folder)?;
 
-                let file_name = entry.file_name().to_str();
+        let relative = |path: &Path| PathBuf::from(path.strip_prefix(source_folder).unwrap_or(path));
 
-                return if let Some(file_name) = file_name
-                {
-                    file_name == ".modignore"
-                }
-                else {
-                    false
-                };
-            })
+        let walk = WalkBuilder::new(source_folder)
+            .standard_filters(false)
+            .filter_entry(|entry| entry.file_name() != CACHE_FOLDER_NAME)
             .build();
 
+        let mut ignored_files: Vec<PlannedFile> = vec![];
+
         for entry in walk
         {
-            let entry = entry.unwrap();
+            let entry = entry.in_stage(stage, source_folder)?;
 
-            if entry.path().is_dir()
+            if !entry.path().is_file() || walked_files.contains(entry.path())
             {
                 continue;
             }
 
-            ignore_files.insert(PathBuf::from(entry.path()));
+            let action = match mod_ignore_rules.matched(entry.path(), false)
+            {
+                Some(ignore_match) => FileAction::Ignore {
+                    ignored_by: IgnoreMatch {
+                        ignore_file: relative(&ignore_match.ignore_file),
+                        ..ignore_match
+                    },
+                },
+                None => FileAction::Skip { reason: String::from("inside a folder excluded by .modignore") }
+            };
+
+            ignored_files.push(PlannedFile {
+                path: relative(entry.path()),
+                pbo: None,
+                action,
+            });
         }
 
-        for ignore_file in ignore_files
+        ignored_files.sort_by(|first, second| first.path.cmp(&second.path));
+
+        Ok(ignored_files)
+    }
+
+    fn copy_file<A: AsRef<Path>, B: AsRef<Path>, C: AsRef<Path>>(file_path: A, source_root_path: B, destination_root_path: C) -> Result<(), ModPackerError>
+    {
+        let file_path = file_path.as_ref();
+
+        let relative_file_path = file_path.strip_prefix(&source_root_path)
+            .map_err(|_| {
+                let message = format!("File is not inside Source Root Path: {:?}", source_root_path.as_ref());
+
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+            })
+            .in_stage(PipelineStage::Copy, file_path)?;
+
+        let destination_path = destination_root_path.as_ref().join(relative_file_path);
+
+        if let Some(destination_parent_path) = destination_path.parent()
         {
-            fs::remove_file(&ignore_file).unwrap();
+            fs::create_dir_all(destination_parent_path).in_stage(PipelineStage::Copy, destination_parent_path)?;
         }
 
+        fs::copy(file_path, &destination_path).in_stage(PipelineStage::Copy, file_path)?;
+
         Ok(())
     }
 
-    fn convert_raw_files<A: AsRef<Path>>(&self, mod_folder: A) -> Result<(), Error>
+    fn delete_mod_ignore_files<A: AsRef<Path>>(mod_folder: A) -> Result<(), ModPackerError>
+    {
+        Self::delete_files_named(mod_folder, ".modignore", PipelineStage::Copy)
+    }
+
+    /// Converts the files of the mod folder in place, except those in the skipped pbo folders
+    fn convert_raw_files<A: AsRef<Path>>(&self, mod_folder: A, skipped_pbo_folders: &BTreeSet<PathBuf>, cache: Option<&BuildCache>, summary: &mut PackSummary) -> Result<(), ModPackerError>
     {
         let mut files_to_convert: Vec<PathBuf> = vec![];
 
@@ -359,14 +865,31 @@ impl ArmaToolsModPacker
 
         for entry in walk
         {
-            let entry = entry.unwrap();
+            let entry = entry.in_stage(PipelineStage::Convert, &mod_folder)?;
 
             if !entry.path().is_file()
             {
                 continue;
             }
 
-            if entry.file_name().to_str().unwrap() == ".convertignore"
+            if entry.file_name() == ".convertignore"
+            {
+                continue;
+            }
+
+            if mod_info_files::has_mod_info_file_name(entry.path())
+            {
+                continue;
+            }
+
+            if skipped_pbo_folders.iter().any(|pbo_folder| entry.path().starts_with(pbo_folder))
+            {
+                continue;
+            }
+
+            let rule = self.converters.find(entry.path(), ConversionDirection::Pack);
+
+            if rule.is_some_and(|rule| !rule.converter.is_enabled(&self.options.manifest.converters))
             {
                 continue;
             }
@@ -374,59 +897,248 @@ impl ArmaToolsModPacker
             files_to_convert.push(PathBuf::from(entry.path()));
         }
 
-        for entry_path in &files_to_convert
-        {
-            println!("Converting: {:?}", entry_path);
+        files_to_convert.sort();
 
-            let entry_extension = entry_path.extension().unwrap().to_str().unwrap();
+        // the conversions run in parallel, two of them writing the same output (e.g. tex_co.png and tex_co.tga) would race
+        self.check_conversion_outputs(&files_to_convert, mod_folder.as_ref())?;
 
-            match entry_extension
+        let progress = Progress::start("convert", files_to_convert.len());
+
+        let results: Vec<Result<ConvertedFile, ModPackerError>> = files_to_convert.par_iter()
+            .map(|entry_path| {
+                let result = self.convert_raw_file(entry_path, cache);
+
+                progress.advance();
+
+                result
+            })
+            .collect();
+
+        progress.finish();
+
+        let mut errors: Vec<ModPackerError> = vec![];
+
+        for result in results
+        {
+            match result
             {
-                "pac" |
-                "tga" |
-                "jpg" |
-                "png" => {
-                    let converted_path = entry_path.with_extension("paa");
+                Ok(converted_file) => {
+                    let converted_file = ConvertedFile {
+                        source: Self::relative_to(&converted_file.source, mod_folder.as_ref()),
+                        output: Self::relative_to(&converted_file.output, mod_folder.as_ref()),
+                        ..converted_file
+                    };
+
+                    if converted_file.reused
+                    {
+                        console::verbose(&format!("Reused: {}", converted_file.source.display()));
+
+                        summary.reused_conversions += 1;
+                    }
+                    else {
+                        console::verbose(&format!("Converted: {}", converted_file.source.display()));
 
-                    self.paa_converter.binarize(entry_path, &converted_path).unwrap();
+                        summary.converted_files += 1;
+                    }
 
-                    fs::remove_file(entry_path).unwrap();
+                    summary.conversions.push(converted_file);
                 }
-                "cpp" => {
-                    let converted_path = entry_path.with_extension("bin");
+                Err(error) => {
+                    errors.push(error);
+                }
+            }
+        }
 
-                    self.config_converter.binarize(entry_path, &converted_path).unwrap();
+        ModPackerError::collect(PipelineStage::Convert, &mod_folder, errors)
+    }
 
-                    fs::remove_file(entry_path).unwrap();
-                }
-                "rvmat" => {
-                    // files with the .rvmat extension are being converted from .rvmat (plaintext) to .rvmat (binarized)
-                    // so we have to first use a different extension .brvmat, and convert into that
-                    // after that, we can delete the original file and rename the converted extension into .rvmat
+    /// Fails if the converted outputs of two files would have the same path
+    fn check_conversion_outputs(&self, files_to_convert: &[PathBuf], mod_folder: &Path) -> Result<(), ModPackerError>
+    {
+        let stage = PipelineStage::Convert;
 
-                    let converted_path = entry_path.with_extension("brvmat");
+        let mut sources_by_output = BTreeMap::<PathBuf, &PathBuf>::new();
 
-                    self.rvmat_converter.binarize(entry_path, &converted_path).unwrap();
+        let mut errors: Vec<ModPackerError> = vec![];
 
-                    fs::remove_file(entry_path).unwrap();
+        for file in files_to_convert
+        {
+            let Some(rule) = self.converters.find(file, ConversionDirection::Pack) else {
+                continue;
+            };
 
-                    fs::copy(&converted_path, entry_path).unwrap();
+            let output = rule.output_path(file);
 
-                    fs::remove_file(converted_path).unwrap();
-                }
-                _ => {
-                    panic!("Cannot convert file {entry_path:?} with extension {entry_extension}, consider adding that file");
+            match sources_by_output.get(&output)
+            {
+                Some(other) => errors.push(ModPackerError::DuplicateOutput {
+                    stage,
+                    path: Self::relative_to(file, mod_folder),
+                    other: Self::relative_to(other, mod_folder),
+                    output: Self::relative_to(&output, mod_folder),
+                }),
+                None => {
+                    sources_by_output.insert(output, file);
                 }
             }
         }
 
-        Ok(())
+        ModPackerError::collect(stage, mod_folder, errors)
+    }
+
+    /// Converts a single file in place, reporting whether the converted output was reused from the build cache
+    fn convert_raw_file(&self, entry_path: &Path, cache: Option<&BuildCache>) -> Result<ConvertedFile, ModPackerError>
+    {
+        let stage = PipelineStage::Convert;
+
+        let Some(rule) = self.converters.find(entry_path, ConversionDirection::Pack) else {
+            return Err(ModPackerError::UnsupportedFile {
+                stage,
+                path: PathBuf::from(entry_path),
+                extension: entry_path.extension().unwrap_or_default().to_string_lossy().to_string(),
+            });
+        };
+
+        let converter = self.converter_for(rule, entry_path)?;
+
+        let converted_path = rule.output_path(entry_path);
+
+        let mut converted_file = ConvertedFile {
+            source: PathBuf::from(entry_path),
+            output: converted_path.clone(),
+            converter: rule.converter.name(),
+            source_sha1: sha1_of_file(entry_path).in_stage(stage, entry_path)?,
+            reused: false,
+        };
+
+        if converted_path != entry_path
+        {
+            converted_file.reused = Self::convert_cached(cache, entry_path, &converted_path, &converter.identity(), |source, destination| {
+                converter.convert(source, destination, rule.direction)
+            })?;
+
+            fs::remove_file(entry_path).in_stage(stage, entry_path)?;
+
+            return Ok(converted_file);
+        }
+
+        // files converted in place (e.g. .rvmat from plaintext to binarized) are first converted into a temporary file (.brvmat)
+        // after that, we can delete the original file and rename the temporary file to the original name
+
+        let temporary_path = rule.temporary_path(entry_path);
+
+        converted_file.reused = Self::convert_cached(cache, entry_path, &temporary_path, &converter.identity(), |source, destination| {
+            converter.convert(source, destination, rule.direction)
+        })?;
+
+        fs::remove_file(entry_path).in_stage(stage, entry_path)?;
+
+        fs::copy(&temporary_path, entry_path).in_stage(stage, &temporary_path)?;
+
+        fs::remove_file(&temporary_path).in_stage(stage, &temporary_path)?;
+
+        Ok(converted_file)
+    }
+
+    /// The converter implementation a rule refers to
+    fn converter_for<'a>(&'a self, rule: &'a ConverterRule, path: &Path) -> Result<&'a dyn RegisteredConverter, ModPackerError>
+    {
+        self.converters.converter(&rule.converter)
+            .ok_or_else(|| ModPackerError::Conversion {
+                stage: PipelineStage::Convert,
+                path: PathBuf::from(path),
+                source: std::io::Error::other(format!("{} is not available", rule.converter.name())),
+            })
+    }
+
+    /// Converts a single file into Arma ready format, or back into an editable format when debinarizing.
+    /// The input file is kept, returns the path of the converted file.
+    pub fn convert_single_file(&self, input: &Path, output: Option<&Path>, debinarize: bool) -> Result<PathBuf, ModPackerError>
+    {
+        let stage = PipelineStage::Convert;
+
+        let direction = if debinarize { ConversionDirection::Unpack } else { ConversionDirection::Pack };
+
+        let Some(rule) = self.converters.find(input, direction) else {
+            return Err(ModPackerError::UnsupportedFile {
+                stage,
+                path: PathBuf::from(input),
+                extension: input.extension().unwrap_or_default().to_string_lossy().to_string(),
+            });
+        };
+
+        let converter = self.converter_for(rule, input)?;
+
+        let output = output.map(PathBuf::from).unwrap_or_else(|| rule.output_path(input));
+
+        // files converting into their own format (e.g. .rvmat) go through a temporary file like when packing
+        let converted_path = if output == input { rule.temporary_path(input) } else { output.clone() };
+
+        converter.convert(input, &converted_path, direction)
+            .map_err(|source| ModPackerError::Conversion {
+                stage,
+                path: PathBuf::from(input),
+                source,
+            })?;
+
+        if converted_path != output
+        {
+            fs::rename(&converted_path, &output).in_stage(stage, &converted_path)?;
+        }
+
+        Ok(output)
+    }
+
+    /// Runs the conversion unless the build cache already holds its output for the same input, converter and settings.
+    /// Returns whether the output was reused from the cache.
+    fn convert_cached<F>(cache: Option<&BuildCache>, source: &Path, destination: &Path, converter_identity: &str, convert: F) -> Result<bool, ModPackerError>
+    where F: FnOnce(&Path, &Path) -> Result<(), std::io::Error>
+    {
+        let stage = PipelineStage::Convert;
+
+        let settings = destination.extension().unwrap_or_default().to_string_lossy().to_string();
+
+        let cache_entry = match cache
+        {
+            Some(cache) => {
+                let key = BuildCache::conversion_key(source, converter_identity, &settings).in_stage(stage, source)?;
+
+                Some((cache, key))
+            }
+            None => None
+        };
+
+        if let Some((cache, key)) = &cache_entry
+        {
+            if cache.fetch("convert", key, destination).in_stage(stage, destination)?
+            {
+                return Ok(true);
+            }
+        }
+
+        convert(source, destination)
+            .map_err(|error| ModPackerError::Conversion {
+                stage,
+                path: PathBuf::from(source),
+                source: error,
+            })?;
+
+        if let Some((cache, key)) = &cache_entry
+        {
+            cache.store("convert", key, destination).in_stage(stage, destination)?;
+        }
+
+        Ok(false)
     }
 
-    fn delete_convert_ignore_files<A: AsRef<Path>>(mod_folder: A) -> Result<(), Error>
+    fn delete_convert_ignore_files<A: AsRef<Path>>(mod_folder: A) -> Result<(), ModPackerError>
     {
-        let mut ignore_files = HashSet::<PathBuf>::new();
+        Self::delete_files_named(mod_folder, ".convertignore", PipelineStage::Convert)
+    }
 
+    /// Folders with a pbo.json that is not inside another pbo folder
+    pub fn identify_pbo_folders<A: AsRef<Path>>(mod_folder: A) -> Result<BTreeSet<PathBuf>, ModPackerError>
+    {
         let walk = WalkBuilder::new(&mod_folder)
             .standard_filters(false)
             .filter_entry(|entry| {
@@ -435,235 +1147,357 @@ impl ArmaToolsModPacker
                     return true;
                 }
 
-                let file_name = entry.file_name().to_str();
-
-                return if let Some(file_name) = file_name
-                {
-                    file_name == ".convertignore"
-                }
-                else {
-                    false
-                };
+                entry.file_name() == "pbo.json"
             })
             .build();
 
+        let mut pbo_json_files: Vec<PathBuf> = vec![];
+
         for entry in walk
         {
-            let entry = entry.unwrap();
+            let entry = entry.in_stage(PipelineStage::IdentifyPbos, &mod_folder)?;
 
             if entry.path().is_dir()
             {
                 continue;
             }
 
-            ignore_files.insert(PathBuf::from(entry.path()));
+            pbo_json_files.push(entry.into_path());
+        }
+
+        Ok(Self::pbo_folders_of(&pbo_json_files))
+    }
+
+    /// The pbo folders of the pbo.json files among the files, a pbo.json inside another pbo folder belongs to that pbo
+    pub fn pbo_folders_of<'a, I: IntoIterator<Item = &'a PathBuf>>(files: I) -> BTreeSet<PathBuf>
+    {
+        let mut pbo_json_files: Vec<&PathBuf> = files.into_iter()
+            .filter(|file| file.file_name().is_some_and(|file_name| file_name == "pbo.json"))
+            .collect();
+
+        // parents before their sub-folders, the walk order does not guarantee that
+        pbo_json_files.sort_by_key(|file| file.components().count());
+
+        let mut pbo_folders = BTreeSet::<PathBuf>::new();
+
+        for pbo_json_file in pbo_json_files
+        {
+            if pbo_folders.iter().any(|pbo_folder| pbo_json_file.starts_with(pbo_folder))
+            {
+                continue;
+            }
+
+            if let Some(pbo_folder) = pbo_json_file.parent()
+            {
+                pbo_folders.insert(PathBuf::from(pbo_folder));
+            }
         }
 
-        for ignore_file in ignore_files
+        pbo_folders
+    }
+
+    /// Writes the mod.cpp and meta.cpp from the manifest, replacing the ones copied from the source
+    fn generate_mod_info_files(&self, mod_folder: &Path) -> Result<(), ModPackerError>
+    {
+        for generated_file in mod_info_files::generate(mod_folder, &self.options.manifest, PipelineStage::Copy)?
         {
-            fs::remove_file(&ignore_file).unwrap();
+            console::verbose(&format!("Generated: {}", Self::relative_to(&generated_file, mod_folder).display()));
         }
 
         Ok(())
     }
 
-    fn identify_pbo_folders<A: AsRef<Path>>(mod_folder: A) -> Result<HashSet<PathBuf>, Error>
+    /// Checks the prefixes of the copied pbo folders and the #include paths of their configs before they are binarized
+    fn check_pbo_prefixes(&self, mod_folder: &Path) -> Result<Vec<PboPrefix>, ModPackerError>
     {
-        let mut pbo_json_files = HashSet::<PathBuf>::new();
+        let stage = PipelineStage::CheckPrefixes;
 
-        let walk = WalkBuilder::new(&mod_folder)
-            .standard_filters(false)
-            .filter_entry(|entry| {
-                if entry.path().is_dir()
-                {
-                    return true;
-                }
+        let pbo_folders = Self::identify_pbo_folders(mod_folder)?;
 
-                let file_name = entry.file_name().to_str();
+        let (prefixes, mut errors) = pbo_prefixes::check_prefixes(&pbo_folders, &self.options.manifest, stage);
 
-                return if let Some(file_name) = file_name
-                {
-                    file_name == "pbo.json"
-                }
-                else {
-                    false
-                };
-            })
-            .build();
+        errors.extend(pbo_prefixes::check_includes(&pbo_folders, &prefixes, &self.options.manifest, None, stage));
 
-        let mut pbo_directories = HashSet::<PathBuf>::new();
+        ModPackerError::collect(stage, mod_folder, errors)?;
 
-        for entry in walk
+        Ok(prefixes)
+    }
+
+    /// Checks the file paths in the configs, rvmats and scripts of the copied pbo folders before they are converted
+    fn check_file_references(&self, mod_folder: &Path, prefixes: &[PboPrefix], summary: &mut PackSummary) -> Result<(), ModPackerError>
+    {
+        let stage = PipelineStage::CheckReferences;
+
+        let check = pbo_references::check_references(mod_folder, prefixes, &self.options.manifest, &self.converters, None, self.options.strict_references, stage);
+
+        for warning in &check.warnings
         {
-            let entry = entry.unwrap();
+            console::warning(warning);
+        }
 
-            if entry.path().is_dir()
+        summary.reference_warnings = check.warnings;
+
+        ModPackerError::collect(stage, mod_folder, check.errors)
+    }
+
+    /// Adds the prefix and headers from the manifest to the pbo.json of every pbo folder, headers already set in the pbo.json
+    /// are kept as they are
+    fn apply_manifest_headers(&self, pbo_folders: &BTreeSet<PathBuf>) -> Result<(), ModPackerError>
+    {
+        let stage = PipelineStage::IdentifyPbos;
+
+        let manifest = &self.options.manifest;
+
+        let handler = PBOJsonHandler::default();
+
+        for pbo_folder in pbo_folders
+        {
+            let addon_name = Self::file_name_of(pbo_folder);
+
+            let mut headers: Vec<(String, String)> = vec![];
+
+            if let Some(prefix) = manifest.addon_prefix(&addon_name)
+            {
+                headers.push((String::from("prefix"), prefix));
+            }
+
+            if let Some(addon_headers) = manifest.addon_headers(&addon_name)
+            {
+                headers.extend(addon_headers.iter().map(|(name, value)| (name.clone(), value.clone())));
+            }
+
+            if headers.is_empty()
             {
                 continue;
             }
 
-            let mut skip: bool = false;
+            let pbo_json_path = pbo_folder.join("pbo.json");
 
-            for pbo_directory in &pbo_directories
+            let mut pbo_json = handler.read_json(&pbo_json_path)
+                .map_err(|error| ModPackerError::IO {
+                    stage,
+                    path: pbo_json_path.clone(),
+                    source: std::io::Error::other(error),
+                })?;
+
+            for (name, value) in headers
             {
-                if entry.path().starts_with(pbo_directory)
+                if pbo_json.headers.iter().any(|header| header.name.eq_ignore_ascii_case(&name))
                 {
-                    skip = true;
-                    break;
+                    continue;
                 }
-            }
 
-            if skip
-            {
-                continue;
+                pbo_json.headers.push(PBOHeader {
+                    name,
+                    value,
+                });
             }
 
-            pbo_json_files.insert(PathBuf::from(entry.path()));
-            pbo_directories.insert(PathBuf::from(entry.path().parent().unwrap()));
+            handler.write_json(&pbo_json, &pbo_json_path)
+                .map_err(|error| ModPackerError::IO {
+                    stage,
+                    path: pbo_json_path.clone(),
+                    source: std::io::Error::other(error),
+                })?;
         }
 
-        Ok(pbo_directories)
+        Ok(())
     }
 
-    fn copy_key_files_from_pbo_folders<A: AsRef<Path>>(addons_folder: A, pbo_folders: &HashSet<PathBuf>) -> Result<(), Error>
+    /// Writes the key file of every pbo folder as [pbo_name]_key.json into the addons folder, resolved by [KeySource::resolve].
+    /// Returns the pbo folders that have no key file at all and will therefore not be signed.
+    fn copy_key_files_from_pbo_folders<A: AsRef<Path>>(&self, addons_folder: A, pbo_folders: &BTreeSet<PathBuf>, default_key_file: &Path) -> Result<Vec<PathBuf>, ModPackerError>
     {
+        let stage = PipelineStage::CopyKeys;
+
+        let mut unsigned_pbo_folders: Vec<PathBuf> = vec![];
+
         for pbo_folder in pbo_folders
         {
-            println!("Copying Key file from: {:?}", pbo_folder);
+            let Some(key_source) = KeySource::resolve(pbo_folder, default_key_file, &self.options.manifest, Path::is_file) else {
+                unsigned_pbo_folders.push(PathBuf::from(pbo_folder));
+                continue;
+            };
 
-            for child_entry in pbo_folder.read_dir().unwrap()
-            {
-                let child_entry = child_entry.unwrap();
+            let parent_folder_name = Self::file_name_of(pbo_folder);
 
-                let child_path = child_entry.path();
+            let new_key_file_path = addons_folder.as_ref().join(format!("{parent_folder_name}_key.json"));
 
-                if child_path.is_dir()
-                {
-                    continue;
+            fs::create_dir_all(&addons_folder).in_stage(stage, &addons_folder)?;
+
+            match key_source
+            {
+                KeySource::KeyFile(key_file) => {
+                    console::verbose(&format!("Copying key file for {parent_folder_name} from: {}", key_file.display()));
+
+                    fs::copy(&key_file, &new_key_file_path).in_stage(stage, &key_file)?;
+                }
+                KeySource::Authorities(authorities) => {
+                    let key_json = KeyJson {
+                        authority_name: None,
+                        authority_names: authorities,
+                    };
+
+                    KeyJsonHandler::default().write_json(&key_json, &new_key_file_path)
+                        .map_err(|error| ModPackerError::IO {
+                            stage,
+                            path: new_key_file_path.clone(),
+                            source: std::io::Error::other(error),
+                        })?;
                 }
+            }
+        }
 
-                let file_name = child_path.file_name().unwrap();
+        Ok(unsigned_pbo_folders)
+    }
 
-                let file_name = file_name.to_str();
+    fn delete_non_addon_key_files<A: AsRef<Path>, B: AsRef<Path>>(mod_folder: A, addons_folder: B) -> Result<(), ModPackerError>
+    {
+        let key_json_files = Self::find_files(&mod_folder, PipelineStage::CopyKeys, |path| {
+            path.file_name().is_some_and(|file_name| file_name == "key.json") &&
+                path.parent() != Some(addons_folder.as_ref())
+        })?;
 
-                if file_name.is_none()
-                {
-                    continue;
-                }
+        Self::delete_files(key_json_files, PipelineStage::CopyKeys)
+    }
 
-                let file_name = file_name.unwrap();
+    fn pack_pbo_folders<A: AsRef<Path>>(&self, addons_folder: A, pbo_folders: &BTreeSet<PathBuf>, cache: Option<&BuildCache>, hook_context: &HookContext, summary: &mut PackSummary) -> Result<(), ModPackerError>
+    {
+        let addons_folder = addons_folder.as_ref();
 
-                if file_name != "key.json"
-                {
-                    continue;
-                }
+        let pbo_folders: Vec<&PathBuf> = pbo_folders.iter().collect();
 
-                let parent_folder_name = pbo_folder.file_name().unwrap().to_str().unwrap();
+        // hooks may change the folders (e.g. stamp a version), so they all run before packing and computing cache keys
+        for pbo_folder in &pbo_folders
+        {
+            let pbo_folder_name = Self::file_name_of(pbo_folder);
 
-                let new_key_file_name = format!("{parent_folder_name}_key.json");
+            let pbo_file_path = addons_folder.join(format!("{pbo_folder_name}.pbo"));
 
-                fs::copy(child_entry.path(), &addons_folder.as_ref().with_file_name(new_key_file_name)).unwrap();
-            }
+            self.run_hook(BuildHook::BeforePbo, &hook_context.for_addon(&pbo_folder_name, pbo_folder, &pbo_file_path))?;
         }
 
-        Ok(())
-    }
+        let progress = Progress::start("pack", pbo_folders.len());
 
-    fn delete_non_addon_key_files<A: AsRef<Path>, B: AsRef<Path>>(mod_folder: A, addons_folder: B) -> Result<(), Error>
-    {
-        let mut key_json_files = HashSet::<PathBuf>::new();
+        let results: Vec<Result<bool, ModPackerError>> = pbo_folders.par_iter()
+            .map(|pbo_folder| {
+                let pbo_folder_name = Self::file_name_of(pbo_folder);
 
-        let walk = WalkBuilder::new(&mod_folder)
-            .standard_filters(false)
-            .filter_entry(|entry| {
-                if entry.path().is_dir()
-                {
-                    return true;
-                }
+                let pbo_file_name = format!("{pbo_folder_name}.pbo");
 
-                let file_name = entry.file_name().to_str();
+                let pbo_file_path = addons_folder.join(pbo_file_name);
 
-                return if let Some(file_name) = file_name
-                {
-                    file_name == "key.json"
-                }
-                else {
-                    false
-                };
+                let result = self.pack_pbo_folder_cached(pbo_folder, &pbo_file_path, cache);
+
+                progress.advance();
+
+                result
             })
-            .build();
+            .collect();
 
-        for entry in walk
-        {
-            let entry = entry.unwrap();
+        progress.finish();
 
-            if entry.path().is_dir()
-            {
-                continue;
-            }
+        let mut errors: Vec<ModPackerError> = vec![];
+
+        for (pbo_folder, result) in pbo_folders.iter().zip(results)
+        {
+            let pbo_folder_name = Self::file_name_of(pbo_folder);
 
-            if entry.path().parent().is_some() &&
-                entry.path().parent().unwrap() == addons_folder.as_ref()
+            match result
             {
-                continue;
-            }
+                Ok(true) => {
+                    console::verbose(&format!("Reused: {pbo_folder_name}.pbo"));
 
-            key_json_files.insert(PathBuf::from(entry.path()));
-        }
+                    summary.reused_pbos += 1;
+                }
+                Ok(false) => {
+                    console::verbose(&format!("Packed: {pbo_folder_name}.pbo"));
 
-        for key_json_file in key_json_files
-        {
-            fs::remove_file(&key_json_file).unwrap();
+                    summary.packed_pbos.push(pbo_folder_name);
+                }
+                Err(error) => errors.push(error)
+            }
         }
 
-        Ok(())
+        ModPackerError::collect(PipelineStage::Pack, addons_folder, errors)
     }
 
-    fn pack_pbo_folders<A: AsRef<Path>>(&self, addons_folder: A, pbo_folders: &HashSet<PathBuf>) -> Result<(), Error>
+    /// Packs the folder unless the build cache already holds a pbo for the exact same folder contents.
+    /// Returns whether the pbo was reused from the cache.
+    fn pack_pbo_folder_cached(&self, pbo_folder: &Path, pbo_file_path: &Path, cache: Option<&BuildCache>) -> Result<bool, ModPackerError>
     {
-        for pbo_folder in pbo_folders
+        let stage = PipelineStage::Pack;
+
+        let cache_entry = match cache
         {
-            let pbo_folder_name = pbo_folder.file_name().unwrap().to_str().unwrap();
+            Some(cache) => {
+                let identity = format!("pboc|{}", self.pbo_packer.tool_path);
+                let settings = format!("{}|{}", self.pbo_packer.prefix.clone().unwrap_or_default(), self.pbo_packer.timestamp);
 
-            let pbo_file_name = format!("{pbo_folder_name}.pbo");
+                let key = BuildCache::folder_key(pbo_folder, &identity, &settings).in_stage(stage, pbo_folder)?;
 
-            let pbo_file_path = addons_folder.as_ref().join(pbo_file_name);
+                Some((cache, key))
+            }
+            None => None
+        };
 
-            self.pbo_packer.pack(pbo_folder, &pbo_file_path).unwrap()
+        if let Some((cache, key)) = &cache_entry
+        {
+            if cache.fetch("pbo", key, pbo_file_path).in_stage(stage, pbo_file_path)?
+            {
+                return Ok(true);
+            }
         }
 
-        Ok(())
+        self.pbo_packer.pack(pbo_folder, pbo_file_path)
+            .map_err(|source| ModPackerError::Packing {
+                stage,
+                path: PathBuf::from(pbo_folder),
+                source,
+            })?;
+
+        if let Some((cache, key)) = &cache_entry
+        {
+            cache.store("pbo", key, pbo_file_path).in_stage(stage, pbo_file_path)?;
+        }
+
+        Ok(false)
     }
 
-    fn delete_original_pbo_folders(pbo_folders: &HashSet<PathBuf>) -> Result<(), Error>
+    fn delete_original_pbo_folders(pbo_folders: &BTreeSet<PathBuf>) -> Result<(), ModPackerError>
     {
         for pbo_folder in pbo_folders
         {
-            fs::remove_dir_all(pbo_folder).unwrap();
+            fs::remove_dir_all(pbo_folder).in_stage(PipelineStage::Pack, pbo_folder)?;
         }
 
         Ok(())
     }
 
-    fn sign_packed_pbos<A: AsRef<Path>, B: AsRef<Path>>(&self, addons_folder: A, keys_folder: B) -> Result<HashSet<String>, Error>
+    fn sign_packed_pbos<A: AsRef<Path>, B: AsRef<Path>>(&self, addons_folder: A, keys_folder: B, cache: Option<&BuildCache>, summary: &mut PackSummary) -> Result<HashSet<String>, ModPackerError>
     {
+        let stage = PipelineStage::Sign;
+
         let mut pbo_files = HashSet::<PathBuf>::new();
 
-        for entry in addons_folder.as_ref().read_dir().unwrap()
+        if addons_folder.as_ref().exists()
         {
-            let entry = entry.unwrap();
-
-            if entry.path().is_dir()
+            for entry in addons_folder.as_ref().read_dir().in_stage(stage, &addons_folder)?
             {
-                continue;
-            }
+                let entry = entry.in_stage(stage, &addons_folder)?;
 
-            if entry.path().extension().unwrap().to_str().unwrap() != "pbo"
-            {
-                continue;
-            }
+                if entry.path().is_dir()
+                {
+                    continue;
+                }
+
+                if entry.path().extension().is_none_or(|extension| extension != "pbo")
+                {
+                    continue;
+                }
 
-            pbo_files.insert(PathBuf::from(entry.path()));
+                pbo_files.insert(entry.path());
+            }
         }
 
         let mut pbo_authority_name_pairs = HashSet::<(PathBuf, String)>::new();
@@ -672,7 +1506,7 @@ impl ArmaToolsModPacker
 
         for pbo_file in pbo_files
         {
-            let pbo_file_name = pbo_file.file_stem().unwrap().to_str().unwrap();
+            let pbo_file_name = Self::file_stem_of(&pbo_file);
 
             let key_file_name = format!("{pbo_file_name}_key.json");
 
@@ -683,201 +1517,256 @@ impl ArmaToolsModPacker
                 continue;
             }
 
-            let key_json = key_json_handler.read_json(key_file_path).unwrap();
-
-            pbo_authority_name_pairs.insert((pbo_file, key_json.authority_name));
-        }
-
-        let mut needed_public_keys = HashSet::<String>::new();
-
-        for (pbo, authority) in pbo_authority_name_pairs
-        {
-            let private_key_name = format!("{authority}.biprivatekey");
-            let public_key_name = format!("{authority}.bikey");
+            let key_json = key_json_handler.read_json(&key_file_path)
+                .map_err(|source| ModPackerError::KeyFile {
+                    stage,
+                    path: key_file_path.clone(),
+                    source,
+                })?;
 
-            let private_key_path = keys_folder.as_ref().join(&private_key_name);
+            let authorities = key_json.authorities();
 
-            self.pbo_signer.sign(pbo, private_key_path, &addons_folder).unwrap();
+            if authorities.is_empty()
+            {
+                return Err(ModPackerError::MissingAuthority {
+                    stage,
+                    path: key_file_path,
+                });
+            }
 
-            if !needed_public_keys.contains(&public_key_name)
+            for authority in authorities
             {
-                needed_public_keys.insert(public_key_name);
+                pbo_authority_name_pairs.insert((pbo_file.clone(), authority));
             }
         }
 
-        Ok(needed_public_keys)
-    }
+        let mut pbo_authority_name_pairs: Vec<(PathBuf, String)> = pbo_authority_name_pairs.into_iter().collect();
 
-    fn delete_private_keys<A: AsRef<Path>>(keys_folder: A) -> Result<(), Error>
-    {
-        let mut private_keys = HashSet::<PathBuf>::new();
+        pbo_authority_name_pairs.sort();
 
-        let walk = WalkBuilder::new(&keys_folder)
-            .standard_filters(false)
-            .filter_entry(|entry| {
-                if entry.path().is_dir()
-                {
-                    return true;
-                }
+        let addons_folder = addons_folder.as_ref();
+        let keys_folder = keys_folder.as_ref();
 
-                let file_extension = entry.path().extension();
+        let progress = Progress::start("sign", pbo_authority_name_pairs.len());
 
-                if file_extension.is_none()
-                {
-                    return false;
-                }
+        let results: Vec<Result<bool, ModPackerError>> = pbo_authority_name_pairs.par_iter()
+            .map(|(pbo, authority)| {
+                let private_key_name = format!("{authority}.biprivatekey");
 
-                let file_extension = file_extension.unwrap().to_str();
+                let private_key_path = keys_folder.join(&private_key_name);
 
-                return if let Some(file_extension) = file_extension
-                {
-                    file_extension == "biprivatekey"
-                }
-                else {
-                    false
-                };
+                let result = self.sign_pbo_cached(pbo, authority, &private_key_path, addons_folder, cache);
+
+                progress.advance();
+
+                result
             })
-            .build();
+            .collect();
 
-        for entry in walk
+        progress.finish();
+
+        let mut errors: Vec<ModPackerError> = vec![];
+
+        for ((pbo, authority), result) in pbo_authority_name_pairs.iter().zip(results)
         {
-            let entry = entry.unwrap();
+            let pbo_file_name = Self::file_name_of(pbo);
 
-            if entry.path().is_dir()
+            match result
             {
-                continue;
-            }
+                Ok(true) => {
+                    console::verbose(&format!("Reused signature: {pbo_file_name} with \"{authority}\""));
 
-            private_keys.insert(PathBuf::from(entry.path()));
-        }
+                    summary.reused_signatures += 1;
+                }
+                Ok(false) => {
+                    console::verbose(&format!("Signed: {pbo_file_name} with \"{authority}\""));
 
-        for private_key in private_keys
-        {
-            fs::remove_file(&private_key).unwrap();
+                    summary.signatures += 1;
+                }
+                Err(error) => errors.push(error)
+            }
         }
 
-        Ok(())
+        ModPackerError::collect(stage, addons_folder, errors)?;
+
+        let needed_public_keys: HashSet<String> = pbo_authority_name_pairs.into_iter()
+            .map(|(_, authority)| format!("{authority}.bikey"))
+            .collect();
+
+        Ok(needed_public_keys)
     }
 
-    fn delete_unused_bikeys<A: AsRef<Path>>(keys_folder: A, used_keys: &HashSet<String>) -> Result<(), Error>
+    /// Signs the pbo unless the build cache already holds a signature for the same pbo and private key.
+    /// Returns whether the signature was reused from the cache.
+    fn sign_pbo_cached(&self, pbo: &Path, authority: &str, private_key_path: &Path, addons_folder: &Path, cache: Option<&BuildCache>) -> Result<bool, ModPackerError>
     {
-        let mut ignore_files = HashSet::<PathBuf>::new();
+        let stage = PipelineStage::Sign;
 
-        let walk = WalkBuilder::new(&keys_folder)
-            .standard_filters(false)
-            .filter_entry(|entry| {
-                if entry.path().is_dir()
-                {
-                    return true;
-                }
+        let signature_file_name = format!("{}.{authority}.bisign", Self::file_name_of(pbo));
 
-                let file_extension = entry.path().extension();
+        let signature_path = addons_folder.join(signature_file_name);
 
-                if file_extension.is_none()
-                {
-                    return false;
-                }
+        let cache_entry = match cache
+        {
+            Some(cache) if private_key_path.is_file() => {
+                let identity = format!("DSSignFile|{}|{authority}", self.pbo_signer.tool_path);
 
-                let file_extension = file_extension.unwrap().to_str();
+                let key = BuildCache::signature_key(pbo, private_key_path, &identity).in_stage(stage, pbo)?;
 
-                return if let Some(file_extension) = file_extension
-                {
-                    file_extension == "bikey"
-                }
-                else {
-                    false
-                };
-            })
-            .build();
+                Some((cache, key))
+            }
+            _ => None
+        };
 
-        for entry in walk
+        if let Some((cache, key)) = &cache_entry
         {
-            let entry = entry.unwrap();
+            if cache.fetch("sign", key, &signature_path).in_stage(stage, &signature_path)?
+            {
+                return Ok(true);
+            }
+        }
 
-            if entry.path().is_dir()
+        self.pbo_signer.sign(pbo, private_key_path, addons_folder)
+            .map_err(|source| ModPackerError::Signing {
+                stage,
+                path: PathBuf::from(pbo),
+                authority: String::from(authority),
+                source,
+            })?;
+
+        if let Some((cache, key)) = &cache_entry
+        {
+            if signature_path.is_file()
             {
-                continue;
+                cache.store("sign", key, &signature_path).in_stage(stage, &signature_path)?;
             }
+        }
 
-            let entry_file_name = String::from(entry.file_name().to_str().unwrap());
+        Ok(false)
+    }
 
-            if used_keys.contains(&entry_file_name)
+    /// Deletes the public keys no pbo is signed with, returning their file names
+    fn delete_unused_bikeys<A: AsRef<Path>>(keys_folder: A, used_keys: &HashSet<String>) -> Result<Vec<String>, ModPackerError>
+    {
+        let unused_keys = Self::find_files(&keys_folder, PipelineStage::Cleanup, |path| {
+            if path.extension().is_none_or(|extension| extension != "bikey")
             {
-                continue;
+                return false;
             }
 
-            ignore_files.insert(PathBuf::from(entry.path()));
-        }
+            let file_name = path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
 
-        for ignore_file in ignore_files
-        {
-            fs::remove_file(&ignore_file).unwrap();
-        }
+            !used_keys.contains(&file_name)
+        })?;
+
+        let mut unused_key_names: Vec<String> = unused_keys.iter()
+            .map(|path| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+            .collect();
+
+        unused_key_names.sort();
+
+        Self::delete_files(unused_keys, PipelineStage::Cleanup)?;
+
+        Ok(unused_key_names)
+    }
+
+    /// Deletes the [pbo_name]_key.json files written by [Self::copy_key_files_from_pbo_folders], other json files in the addons
+    /// folder are part of the mod
+    fn delete_addon_key_json_files<A: AsRef<Path>>(addons_folder: A, pbo_folders: &BTreeSet<PathBuf>) -> Result<(), ModPackerError>
+    {
+        let key_json_files: HashSet<PathBuf> = pbo_folders.iter()
+            .map(|pbo_folder| addons_folder.as_ref().join(format!("{}_key.json", Self::file_name_of(pbo_folder))))
+            .filter(|key_json_file| key_json_file.is_file())
+            .collect();
+
+        Self::delete_files(key_json_files, PipelineStage::Cleanup)
+    }
+
+    fn prune_empty_directories<A: AsRef<Path>>(mod_folder: A) -> Result<(), ModPackerError>
+    {
+        remove_empty_subdirs(mod_folder.as_ref()).in_stage(PipelineStage::Cleanup, &mod_folder)?;
 
         Ok(())
     }
 
-    fn delete_addon_key_json_files<A: AsRef<Path>>(addons_folder: A) -> Result<(), Error>
+    fn delete_files_named<A: AsRef<Path>>(mod_folder: A, file_name: &str, stage: PipelineStage) -> Result<(), ModPackerError>
     {
-        let mut key_json_files = HashSet::<PathBuf>::new();
+        let files = Self::find_files(&mod_folder, stage, |path| {
+            path.file_name().is_some_and(|name| name == file_name)
+        })?;
 
-        let walk = WalkBuilder::new(&addons_folder)
-            .standard_filters(false)
-            .filter_entry(|entry| {
-                if entry.path().is_dir()
-                {
-                    return true;
-                }
+        Self::delete_files(files, stage)
+    }
 
-                let file_name = entry.file_name().to_str();
+    /// Walks the given folder (without any ignore rules) and collects every file accepted by the filter
+    fn find_files<A: AsRef<Path>, F: Fn(&Path) -> bool>(folder: A, stage: PipelineStage, filter: F) -> Result<HashSet<PathBuf>, ModPackerError>
+    {
+        let mut files = HashSet::<PathBuf>::new();
 
-                return if let Some(file_name) = file_name
-                {
-                    file_name == "key.json"
-                }
-                else {
-                    false
-                };
-            })
+        if !folder.as_ref().exists()
+        {
+            return Ok(files);
+        }
+
+        let walk = WalkBuilder::new(&folder)
+            .standard_filters(false)
             .build();
 
         for entry in walk
         {
-            let entry = entry.unwrap();
+            let entry = entry.in_stage(stage, &folder)?;
 
             if entry.path().is_dir()
             {
                 continue;
             }
 
-            key_json_files.insert(PathBuf::from(entry.path()));
+            if filter(entry.path())
+            {
+                files.insert(PathBuf::from(entry.path()));
+            }
         }
 
-        for key_json_file in key_json_files
+        Ok(files)
+    }
+
+    fn delete_files(files: HashSet<PathBuf>, stage: PipelineStage) -> Result<(), ModPackerError>
+    {
+        for file in files
         {
-            fs::remove_file(&key_json_file).unwrap();
+            fs::remove_file(&file).in_stage(stage, &file)?;
         }
 
         Ok(())
     }
 
-    fn prune_empty_directories<A: AsRef<Path>>(mod_folder: A) -> Result<(), Error>
+    fn file_name_of(path: &Path) -> String
+    {
+        path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default()
+    }
+
+    fn relative_to(path: &Path, folder: &Path) -> PathBuf
     {
-        remove_empty_subdirs(mod_folder.as_ref()).unwrap();
+        path.strip_prefix(folder).map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(path))
+    }
 
-        Ok(())
+    fn file_stem_of(path: &Path) -> String
+    {
+        path.file_stem().map(|file_stem| file_stem.to_string_lossy().to_string()).unwrap_or_default()
     }
 
-    fn copy_files_in_folder<A: AsRef<Path>, B: AsRef<Path>>(source_folder: A, destination_folder: B, recurse: bool) -> Result<(), Error> {
+    fn copy_files_in_folder<A: AsRef<Path>, B: AsRef<Path>>(source_folder: A, destination_folder: B, recurse: bool) -> Result<(), ModPackerError> {
+        let stage = PipelineStage::Copy;
+
         let source_folder_path: &Path = source_folder.as_ref();
         let destination_folder_path: &Path = destination_folder.as_ref();
 
-        let source_directory = source_folder_path.read_dir()?;
+        let source_directory = source_folder_path.read_dir().in_stage(stage, source_folder_path)?;
 
         for item in source_directory {
-            let item = item?;
+            let item = item.in_stage(stage, source_folder_path)?;
 
-            let metadata = item.metadata()?;
+            let metadata = item.metadata().in_stage(stage, item.path())?;
 
             let next_destination_folder_path = destination_folder_path.join(item.file_name());
 
@@ -897,9 +1786,9 @@ impl ArmaToolsModPacker
                         continue;
                     }
 
-                    fs::create_dir_all(destination_folder_path)?;
+                    fs::create_dir_all(destination_folder_path).in_stage(stage, destination_folder_path)?;
 
-                    fs::copy(item.path(), next_destination_folder_path)?;
+                    fs::copy(&item_path, next_destination_folder_path).in_stage(stage, &item_path)?;
                 }
             }
         }
@@ -907,16 +1796,18 @@ impl ArmaToolsModPacker
         Ok(())
     }
 
-    fn unpack_pbos_in_folder<A: AsRef<Path>, B: AsRef<Path>>(&self, source_folder: A, destination_folder: B, recurse: bool) -> Result<(), Error> {
+    fn unpack_pbos_in_folder<A: AsRef<Path>, B: AsRef<Path>>(&self, source_folder: A, destination_folder: B, recurse: bool) -> Result<(), ModPackerError> {
+        let stage = PipelineStage::Unpack;
+
         let source_folder_path: &Path = source_folder.as_ref();
         let destination_folder_path: &Path = destination_folder.as_ref();
 
-        let source_directory = source_folder_path.read_dir().unwrap();
+        let source_directory = source_folder_path.read_dir().in_stage(stage, source_folder_path)?;
 
         for item in source_directory {
-            let item = item.unwrap();
+            let item = item.in_stage(stage, source_folder_path)?;
 
-            let metadata = item.metadata()?;
+            let metadata = item.metadata().in_stage(stage, item.path())?;
 
             let next_destination_folder_path = destination_folder_path.join(item.file_name());
 
@@ -924,7 +1815,7 @@ impl ArmaToolsModPacker
                 if recurse {
                     let next_source_folder_path = source_folder_path.join(item.file_name());
 
-                    self.unpack_pbos_in_folder(next_source_folder_path, next_destination_folder_path, true).unwrap();
+                    self.unpack_pbos_in_folder(next_source_folder_path, next_destination_folder_path, true)?;
                 }
             } else {
                 let item_path = item.path();
@@ -932,7 +1823,12 @@ impl ArmaToolsModPacker
 
                 if let Some(extension) = extension {
                     if extension == "pbo" {
-                        self.pbo_packer.unpack(item.path(), next_destination_folder_path).unwrap();
+                        self.pbo_packer.unpack(&item_path, next_destination_folder_path)
+                            .map_err(|source| ModPackerError::Packing {
+                                stage,
+                                path: item_path.clone(),
+                                source,
+                            })?;
                     }
                 }
             }
@@ -941,85 +1837,163 @@ impl ArmaToolsModPacker
         Ok(())
     }
 
-    fn convert_files<P: AsRef<Path>>(&self, directory: P, recurse: bool) -> Result<(), Error> {
+    /// Converts all files in the directory back into editable formats.
+    /// Failed conversions are collected into the errors list so the remaining files are still converted.
+    fn convert_files<P: AsRef<Path>>(&self, directory: P, recurse: bool, errors: &mut Vec<ModPackerError>) -> Result<(), ModPackerError> {
+        let mut files_to_convert: Vec<PathBuf> = vec![];
+
+        Self::collect_files_in_folder(directory, recurse, &mut files_to_convert)?;
+
+        files_to_convert.sort();
+
+        let progress = Progress::start("convert", files_to_convert.len());
+
+        let results: Vec<Result<(), ModPackerError>> = files_to_convert.par_iter()
+            .map(|item_path| {
+                let result = self.convert_packed_file(item_path);
+
+                progress.advance();
+
+                result
+            })
+            .collect();
+
+        progress.finish();
+
+        for (item_path, result) in files_to_convert.iter().zip(results) {
+            match result {
+                Ok(()) => console::verbose(&format!("Converted: {}", item_path.display())),
+                Err(error) => errors.push(error)
+            }
+        }
+
+        Ok(())
+    }
+
+    fn collect_files_in_folder<P: AsRef<Path>>(directory: P, recurse: bool, files: &mut Vec<PathBuf>) -> Result<(), ModPackerError> {
+        let stage = PipelineStage::Convert;
+
         let directory_path: &Path = directory.as_ref();
 
-        let directory_items = directory_path.read_dir()?;
+        let directory_items = directory_path.read_dir().in_stage(stage, directory_path)?;
 
-        directory_items.for_each(|item| {
-            let item = item.unwrap();
+        for item in directory_items {
+            let item = item.in_stage(stage, directory_path)?;
 
-            let metadata = item.metadata().unwrap();
+            let metadata = item.metadata().in_stage(stage, item.path())?;
 
             if metadata.is_dir() {
                 if recurse {
-                    self.convert_files(item.path(), true).unwrap();
+                    Self::collect_files_in_folder(item.path(), true, files)?;
                 }
             }
             else {
-                let item_path = item.path();
-                let extension = item_path.extension();
-                let file_name = item_path.file_stem().unwrap();
+                files.push(item.path());
+            }
+        }
 
-                println!("Converting: {:?}", &item_path);
+        Ok(())
+    }
 
-                if let Some(extension) = extension {
-                    if extension == "paa" {
-                        let output_file_path = item_path.with_extension("png");
+    fn convert_packed_file(&self, item_path: &Path) -> Result<(), ModPackerError> {
+        let stage = PipelineStage::Convert;
 
-                        let result = self.paa_converter.debinarize(&item_path, output_file_path);
+        let Some(rule) = self.converters.find(item_path, ConversionDirection::Unpack) else {
+            return Ok(());
+        };
 
-                        if let Err(e) = result {
-                            eprintln!("Error converting paa: {e}");
-                        }
-                        else {
-                            let remove_result = fs::remove_file(item.path());
+        let converter = self.converter_for(rule, item_path)?;
 
-                            if let Err(e) = remove_result {
-                                eprintln!("Error removing converted paa: {e}");
-                            }
-                        }
-                    }
-                    if extension == "bin" && file_name.to_ascii_lowercase() != "texheaders"{
-                        let output_file_path = item.path().with_extension("cpp");
+        let output_file_path = rule.output_path(item_path);
 
-                        let result = self.config_converter.debinarize(item.path(), &output_file_path);
+        let converted_path = if output_file_path == item_path { rule.temporary_path(item_path) } else { output_file_path.clone() };
 
-                        if let Err(e) = result {
-                            eprintln!("Error converting bin: {e}");
-                        }
-                        else {
-                            let remove_result = fs::remove_file(item.path());
+        converter.convert(item_path, &converted_path, rule.direction)
+            .map_err(|source| ModPackerError::Conversion {
+                stage,
+                path: PathBuf::from(item_path),
+                source,
+            })?;
 
-                            if let Err(e) = remove_result {
-                                eprintln!("Error removing converted bin: {e}");
-                            }
-                        }
-                    }
-                    if extension == "rvmat" {
-                        let output_file_path = item.path().with_extension("dbrvmat");
+        fs::remove_file(item_path).in_stage(stage, item_path)?;
 
-                        let result = self.rvmat_converter.debinarize(item.path(), &output_file_path);
+        if converted_path != output_file_path {
+            fs::copy(&converted_path, &output_file_path).in_stage(stage, &converted_path)?;
 
-                        if let Err(e) = result {
-                            eprintln!("Error converting rvmat: {e}");
-                        }
-                        else {
-                            let remove_result = fs::remove_file(item.path());
+            fs::remove_file(&converted_path).in_stage(stage, &converted_path)?;
+        }
 
-                            if let Err(e) = remove_result {
-                                eprintln!("Error removing converted rvmat: {e}");
-                            }
-                        }
+        Ok(())
+    }
+}
 
-                        fs::copy(&output_file_path, item.path()).unwrap();
+#[cfg(test)]
+mod tests
+{
+    use std::path::{Path, PathBuf};
+    use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+    use super::KeySource;
 
-                        fs::remove_file(&output_file_path).unwrap();
-                    }
-                }
-            }
-        });
+    const MANIFEST: &str = r#"
+        [signing]
+        authorities = ["mod_default"]
 
-        Ok(())
+        [addons.main]
+        authorities = ["main_override"]
+    "#;
+
+    fn resolve(pbo_folder: &str, existing_files: &[&str], manifest: &ArmapackToml) -> Option<KeySource>
+    {
+        let exists = |path: &Path| existing_files.iter().any(|file| path == Path::new(file));
+
+        KeySource::resolve(Path::new(pbo_folder), Path::new("mod/key.json"), manifest, exists)
+    }
+
+    #[test]
+    fn folder_key_json_wins_over_everything()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        let key_source = resolve("mod/addons/main", &["mod/addons/main/key.json", "mod/key.json"], &manifest);
+
+        assert_eq!(key_source, Some(KeySource::KeyFile(PathBuf::from("mod/addons/main/key.json"))));
+    }
+
+    #[test]
+    fn addon_authorities_win_over_root_key_json()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        let key_source = resolve("mod/addons/main", &["mod/key.json"], &manifest);
+
+        assert_eq!(key_source, Some(KeySource::Authorities(vec![String::from("main_override")])));
+    }
+
+    #[test]
+    fn root_key_json_wins_over_default_authorities()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        let key_source = resolve("mod/addons/other", &["mod/key.json"], &manifest);
+
+        assert_eq!(key_source, Some(KeySource::KeyFile(PathBuf::from("mod/key.json"))));
+    }
+
+    #[test]
+    fn default_authorities_are_the_last_fallback()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        let key_source = resolve("mod/addons/other", &[], &manifest);
+
+        assert_eq!(key_source, Some(KeySource::Authorities(vec![String::from("mod_default")])));
+    }
+
+    #[test]
+    fn unsigned_without_any_key_source()
+    {
+        let key_source = resolve("mod/addons/other", &[], &ArmapackToml::default());
+
+        assert_eq!(key_source, None);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,319 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::json_files::json_handler::JsonReadError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PipelineStage
+{
+    Prepare,
+    Copy,
+    CheckPrefixes,
+    CheckReferences,
+    Convert,
+    IdentifyPbos,
+    CopyKeys,
+    Pack,
+    Sign,
+    Cleanup,
+    Swap,
+    Unpack
+}
+
+impl Display for PipelineStage
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let name = match self
+        {
+            PipelineStage::Prepare => "prepare",
+            PipelineStage::Copy => "copy",
+            PipelineStage::CheckPrefixes => "check prefixes",
+            PipelineStage::CheckReferences => "check references",
+            PipelineStage::Convert => "convert",
+            PipelineStage::IdentifyPbos => "identify pbos",
+            PipelineStage::CopyKeys => "copy keys",
+            PipelineStage::Pack => "pack",
+            PipelineStage::Sign => "sign",
+            PipelineStage::Cleanup => "cleanup",
+            PipelineStage::Swap => "swap",
+            PipelineStage::Unpack => "unpack"
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ModPackerError
+{
+    #[error("IO Error in stage {stage} at {path:?}")]
+    IO {
+        stage: PipelineStage,
+        path: PathBuf,
+        #[source] source: io::Error
+    },
+
+    #[error("Cannot walk folder {path:?} in stage {stage}")]
+    Walk {
+        stage: PipelineStage,
+        path: PathBuf,
+        #[source] source: ignore::Error
+    },
+
+    #[error("Conversion of {path:?} failed in stage {stage}")]
+    Conversion {
+        stage: PipelineStage,
+        path: PathBuf,
+        #[source] source: io::Error
+    },
+
+    #[error("No converter for {path:?} with extension \"{extension}\", consider adding it to .convertignore")]
+    UnsupportedFile {
+        stage: PipelineStage,
+        path: PathBuf,
+        extension: String
+    },
+
+    #[error("{path:?} and {other:?} would both be converted to {output:?}")]
+    DuplicateOutput {
+        stage: PipelineStage,
+        path: PathBuf,
+        other: PathBuf,
+        output: PathBuf
+    },
+
+    #[error("Packing {path:?} failed in stage {stage}")]
+    Packing {
+        stage: PipelineStage,
+        path: PathBuf,
+        #[source] source: io::Error
+    },
+
+    #[error("Signing {path:?} with authority \"{authority}\" failed in stage {stage}")]
+    Signing {
+        stage: PipelineStage,
+        path: PathBuf,
+        authority: String,
+        #[source] source: io::Error
+    },
+
+    #[error("Cannot read key file {path:?} in stage {stage}")]
+    KeyFile {
+        stage: PipelineStage,
+        path: PathBuf,
+        #[source] source: JsonReadError
+    },
+
+    #[error("Key file {path:?} does not specify any authority")]
+    MissingAuthority {
+        stage: PipelineStage,
+        path: PathBuf
+    },
+
+    #[error("Signing is required but no key.json applies to the pbo folder {path:?}")]
+    Unsigned {
+        stage: PipelineStage,
+        path: PathBuf
+    },
+
+    #[error("Hook {hook} failed in stage {stage}")]
+    Hook {
+        stage: PipelineStage,
+        path: PathBuf,
+        hook: String,
+        #[source] source: io::Error
+    },
+
+    #[error("Prefix \"{prefix}\" of the pbo folder {path:?} {problem}")]
+    InvalidPrefix {
+        stage: PipelineStage,
+        path: PathBuf,
+        prefix: String,
+        problem: String
+    },
+
+    #[error("Prefix \"{prefix}\" of the pbo folder {path:?} is also used by {other:?}")]
+    DuplicatePrefix {
+        stage: PipelineStage,
+        path: PathBuf,
+        prefix: String,
+        other: PathBuf
+    },
+
+    #[error("#include \"{include}\" in {path:?} line {line} {problem}")]
+    BrokenInclude {
+        stage: PipelineStage,
+        path: PathBuf,
+        line: usize,
+        include: String,
+        problem: String
+    },
+
+    #[error("File reference \"{reference}\" in {path:?} line {line} {problem}")]
+    BrokenReference {
+        stage: PipelineStage,
+        path: PathBuf,
+        line: usize,
+        reference: String,
+        problem: String
+    },
+
+    #[error("Invalid {path:?} line {line}: {problem}")]
+    InvalidModInfo {
+        stage: PipelineStage,
+        path: PathBuf,
+        line: usize,
+        problem: String
+    },
+
+    #[error("Private key {path:?} ended up in the packed mod")]
+    PrivateKeyInOutput {
+        stage: PipelineStage,
+        path: PathBuf
+    },
+
+    #[error("{} errors in stage {stage} below {path:?}", errors.len())]
+    Aggregate {
+        stage: PipelineStage,
+        path: PathBuf,
+        errors: Vec<ModPackerError>
+    }
+}
+
+impl ModPackerError
+{
+    pub fn stage(&self) -> PipelineStage
+    {
+        match self
+        {
+            ModPackerError::IO { stage, .. } |
+            ModPackerError::Walk { stage, .. } |
+            ModPackerError::Conversion { stage, .. } |
+            ModPackerError::UnsupportedFile { stage, .. } |
+            ModPackerError::DuplicateOutput { stage, .. } |
+            ModPackerError::Packing { stage, .. } |
+            ModPackerError::Signing { stage, .. } |
+            ModPackerError::KeyFile { stage, .. } |
+            ModPackerError::MissingAuthority { stage, .. } |
+            ModPackerError::Unsigned { stage, .. } |
+            ModPackerError::Hook { stage, .. } |
+            ModPackerError::InvalidPrefix { stage, .. } |
+            ModPackerError::DuplicatePrefix { stage, .. } |
+            ModPackerError::BrokenInclude { stage, .. } |
+            ModPackerError::BrokenReference { stage, .. } |
+            ModPackerError::InvalidModInfo { stage, .. } |
+            ModPackerError::PrivateKeyInOutput { stage, .. } |
+            ModPackerError::Aggregate { stage, .. } => *stage
+        }
+    }
+
+    pub fn path(&self) -> &Path
+    {
+        match self
+        {
+            ModPackerError::IO { path, .. } |
+            ModPackerError::Walk { path, .. } |
+            ModPackerError::Conversion { path, .. } |
+            ModPackerError::UnsupportedFile { path, .. } |
+            ModPackerError::DuplicateOutput { path, .. } |
+            ModPackerError::Packing { path, .. } |
+            ModPackerError::Signing { path, .. } |
+            ModPackerError::KeyFile { path, .. } |
+            ModPackerError::MissingAuthority { path, .. } |
+            ModPackerError::Unsigned { path, .. } |
+            ModPackerError::Hook { path, .. } |
+            ModPackerError::InvalidPrefix { path, .. } |
+            ModPackerError::DuplicatePrefix { path, .. } |
+            ModPackerError::BrokenInclude { path, .. } |
+            ModPackerError::BrokenReference { path, .. } |
+            ModPackerError::InvalidModInfo { path, .. } |
+            ModPackerError::PrivateKeyInOutput { path, .. } |
+            ModPackerError::Aggregate { path, .. } => path
+        }
+    }
+
+    /// Process exit code used when this error aborts the program
+    pub fn exit_code(&self) -> u8
+    {
+        match self
+        {
+            ModPackerError::IO { .. } |
+            ModPackerError::Walk { .. } => 3,
+            ModPackerError::Conversion { .. } |
+            ModPackerError::UnsupportedFile { .. } |
+            ModPackerError::DuplicateOutput { .. } |
+            ModPackerError::BrokenInclude { .. } |
+            ModPackerError::BrokenReference { .. } => 4,
+            ModPackerError::Packing { .. } => 5,
+            ModPackerError::Signing { .. } |
+            ModPackerError::PrivateKeyInOutput { .. } => 6,
+            ModPackerError::KeyFile { .. } |
+            ModPackerError::MissingAuthority { .. } |
+            ModPackerError::Unsigned { .. } |
+            ModPackerError::InvalidPrefix { .. } |
+            ModPackerError::DuplicatePrefix { .. } |
+            ModPackerError::InvalidModInfo { .. } => 2,
+            ModPackerError::Hook { .. } => 7,
+            ModPackerError::Aggregate { errors, .. } => {
+                errors.iter().map(|error| error.exit_code()).max().unwrap_or(1)
+            }
+        }
+    }
+
+    /// Combines all errors collected in a stage into a single result, so a stage can report every failure at once
+    pub fn collect<P: AsRef<Path>>(stage: PipelineStage, path: P, mut errors: Vec<ModPackerError>) -> Result<(), ModPackerError>
+    {
+        match errors.len()
+        {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(ModPackerError::Aggregate {
+                stage,
+                path: PathBuf::from(path.as_ref()),
+                errors,
+            })
+        }
+    }
+
+    /// All individual errors, with aggregates flattened
+    pub fn flatten(&self) -> Vec<&ModPackerError>
+    {
+        match self
+        {
+            ModPackerError::Aggregate { errors, .. } => errors.iter().flat_map(|error| error.flatten()).collect(),
+            _ => vec![self]
+        }
+    }
+}
+
+pub trait StageContext<T>
+{
+    /// Attaches the pipeline stage and offending path to an IO error
+    fn in_stage<P: AsRef<Path>>(self, stage: PipelineStage, path: P) -> Result<T, ModPackerError>;
+}
+
+impl<T> StageContext<T> for Result<T, io::Error>
+{
+    fn in_stage<P: AsRef<Path>>(self, stage: PipelineStage, path: P) -> Result<T, ModPackerError>
+    {
+        self.map_err(|source| ModPackerError::IO {
+            stage,
+            path: PathBuf::from(path.as_ref()),
+            source,
+        })
+    }
+}
+
+impl<T> StageContext<T> for Result<T, ignore::Error>
+{
+    fn in_stage<P: AsRef<Path>>(self, stage: PipelineStage, path: P) -> Result<T, ModPackerError>
+    {
+        self.map_err(|source| ModPackerError::Walk {
+            stage,
+            path: PathBuf::from(path.as_ref()),
+            source,
+        })
+    }
+}
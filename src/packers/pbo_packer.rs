@@ -1,21 +1,28 @@
+use std::fs;
 use std::path::Path;
-use std::io::{Error, ErrorKind};
-use std::process::Command;
+use std::time::Duration;
+use std::io::Error;
+use crate::console;
+use crate::json_files::path_json::model::launcher::Launcher;
 use crate::json_files::pbo_json::model::pbo_header::PBOHeader;
 use crate::json_files::pbo_json::model::pbo_json::PBOJson;
 use crate::json_files::pbo_json::pbo_json_handler::PBOJsonHandler;
 use crate::packers::Packer;
-
-pub trait PBOPacker : Packer {}
+use crate::pbo::pbo_file::PboFile;
+use crate::tools::tool_invocation::ToolInvocation;
+use crate::tools::tool_runner::{ToolKind, ToolRunner};
 
 #[derive(Debug)]
 pub struct ArmaToolsPBOPacker<P: AsRef<Path>>
 {
     pub tool_path: P,
-    pub prefix: Option<String>
-}
+    pub launcher: Option<Launcher>,
+    pub timeout: Option<Duration>,
+    pub prefix: Option<String>,
 
-impl<P: AsRef<Path>> PBOPacker for ArmaToolsPBOPacker<P> {}
+    /// Written for every entry instead of the file times, so the same folder always packs into the same bytes
+    pub timestamp: u32
+}
 
 impl<P: AsRef<Path>> Packer for ArmaToolsPBOPacker<P>
 {
@@ -23,103 +30,83 @@ impl<P: AsRef<Path>> Packer for ArmaToolsPBOPacker<P>
     type UnpackError = Error;
 
     fn pack<SourcePath: AsRef<Path>, DestPath: AsRef<Path>>(&self, source_folder: SourcePath, destination_file: DestPath) -> Result<(), Self::PackError> {
-        let source_path = source_folder.as_ref().canonicalize().unwrap();
+        let source_path = source_folder.as_ref().canonicalize()?;
         let destination_path = destination_file.as_ref();
 
         //check_source_and_destination(&source_path, destination_path, true, false)?;
 
-        self.set_pbo_json_prefix(&source_path).unwrap();
-
-        let output = Command::new(self.tool_path.as_ref())
-            .current_dir(destination_path.parent().unwrap())
-            .arg("pack")
-            .arg(source_path.as_os_str())
-            .output()?;
-
-        if !output.status.success() {
-            let code = output.status.code().unwrap_or(0);
-
-            let stdout = String::from_utf8(output.stdout);
-            let stderr = String::from_utf8(output.stderr);
-
-            if let Ok(stdout) = stdout {
-                println!("{}", stdout);
-            }
+        self.set_pbo_json_prefix(&source_path)?;
 
-            if let Ok(stderr) = stderr {
-                eprintln!("{}", stderr);
-            }
+        let working_directory = destination_path.parent().ok_or(Error::other("PBO destination has no parent directory"))?;
 
-            println!("Folder path: {:?}", source_path.as_os_str());
+        let invocation = ToolInvocation::new(self.tool_path.as_ref(), self.launcher.as_ref())
+            .current_dir(working_directory)
+            .arg("pack")
+            .path_arg(&source_path);
 
-            let output = format!("PBO packing failed, error code: {code}");
+        ToolRunner::new(ToolKind::PboPacker, self.timeout)
+            .run(&invocation)
+            .map_err(Error::other)?;
 
-            return Err(Error::new(ErrorKind::Other, output));
-        }
+        self.normalize_pbo(&source_path, destination_path)?;
 
         Ok(())
     }
 
     fn unpack<SourcePath: AsRef<Path>, DestPath: AsRef<Path>>(&self, source_folder: SourcePath, destination_folder: DestPath) -> Result<(), Self::UnpackError> {
-        let source_path = source_folder.as_ref().canonicalize().unwrap();
+        let source_path = source_folder.as_ref().canonicalize()?;
         let destination_path = destination_folder.as_ref();
 
-        println!("Source Path: {:?}", &source_path);
-        println!("Destination Path: {:?}", &destination_path);
-
-        let working_directory = destination_path.parent().unwrap();
+        let working_directory = destination_path.parent().ok_or(Error::other("PBO destination has no parent directory"))?;
 
-        println!("Working Directory: {:?}", working_directory);
+        console::trace(&format!("Source Path: {:?}", &source_path));
+        console::trace(&format!("Destination Path: {:?}", &destination_path));
+        console::trace(&format!("Working Directory: {:?}", working_directory));
 
         //check_source_and_destination(&source_path, destination_path, false, true)?;
 
-        let output = Command::new(self.tool_path.as_ref())
+        let invocation = ToolInvocation::new(self.tool_path.as_ref(), self.launcher.as_ref())
             .current_dir(working_directory)
             .arg("unpack")
-            .arg(&source_path.as_os_str())
-            .output().unwrap();
-
-        if !output.status.success() {
-            let code = output.status.code().unwrap_or(0);
-
-            let stdout = String::from_utf8(output.stdout.clone());
-            let stderr = String::from_utf8(output.stderr.clone());
-
-            if let Ok(stdout) = stdout {
-                println!("{}", stdout);
-            }
-            else {
-                let lossy_stdout = String::from_utf8_lossy(&output.stdout);
+            .path_arg(&source_path);
 
-                eprintln!("Unable to read stdout, cannot convert to utf-8");
-                eprintln!("Lossy stdout: {lossy_stdout}")
-            }
+        ToolRunner::new(ToolKind::PboPacker, self.timeout)
+            .run(&invocation)
+            .map_err(Error::other)?;
 
-            if let Ok(stderr) = stderr {
-                eprintln!("{}", stderr);
-            }
-            else {
-                let lossy_stderr = String::from_utf8_lossy(&output.stderr);
+        self.set_pbo_json_prefix(destination_folder)?;
 
-                eprintln!("Unable to read stderr, cannot convert to utf-8");
-                eprintln!("Lossy stderr: {lossy_stderr}")
-            }
-
-            println!("PBO path: {:?}", source_path.as_os_str());
+        Ok(())
+    }
+}
 
-            let output = format!("PBO unpacking failed, error code: {code}");
+impl<P: AsRef<Path>> ArmaToolsPBOPacker<P>
+{
+    /// Rewrites the packed pbo with sorted entries, fixed timestamps and the header order of the pbo.json,
+    /// as the entry order and timestamps written by the packer depend on the file system
+    fn normalize_pbo(&self, source_folder: &Path, pbo_file: &Path) -> Result<(), Error>
+    {
+        let pbo_json_path = source_folder.join("pbo.json");
 
-            return Err(Error::new(ErrorKind::Other, output));
+        let header_order: Vec<String> = if pbo_json_path.exists()
+        {
+            PBOJsonHandler::default().read_json(&pbo_json_path)
+                .map_err(Error::other)?
+                .headers.into_iter()
+                .map(|header| header.name)
+                .collect()
         }
+        else {
+            vec![]
+        };
 
-        self.set_pbo_json_prefix(destination_folder).unwrap();
+        let bytes = fs::read(pbo_file)?;
 
-        Ok(())
+        let normalized = PboFile::normalize(&bytes, &header_order, self.timestamp).map_err(Error::other)?;
+
+        fs::write(pbo_file, normalized)
     }
-}
 
-impl<P: AsRef<Path>> ArmaToolsPBOPacker<P>
-{
     fn set_pbo_json_prefix<DestPath: AsRef<Path>>(&self, destination_folder: DestPath) -> Result<(), Error>
     {
         if let Some(prefix) = &self.prefix
@@ -130,7 +117,7 @@ impl<P: AsRef<Path>> ArmaToolsPBOPacker<P>
 
             let mut pbo_json = if pbo_json_path.exists()
             {
-                handler.read_json(&pbo_json_path).unwrap()
+                handler.read_json(&pbo_json_path).map_err(Error::other)?
             }
             else {
                 PBOJson{
@@ -160,7 +147,7 @@ impl<P: AsRef<Path>> ArmaToolsPBOPacker<P>
                 pbo_json.headers.push(prefix_header);
             }
 
-            handler.write_json(&pbo_json, &pbo_json_path).unwrap();
+            handler.write_json(&pbo_json, &pbo_json_path).map_err(Error::other)?;
         }
 
         Ok(())
@@ -0,0 +1,451 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use ignore::WalkBuilder;
+use crate::json_files::pbo_json::pbo_json_handler::PBOJsonHandler;
+use crate::packers::mod_packer_error::{ModPackerError, PipelineStage};
+use crate::packers::pbo_references::strip_comments;
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+
+/// A pbo folder and the prefix it is packed with
+#[derive(Debug, Clone)]
+pub struct PboPrefix
+{
+    pub folder: PathBuf,
+    pub prefix: String
+}
+
+/// The prefix header of the pbo.json in a pbo folder, which wins over the prefix from the manifest
+pub fn pbo_json_prefix(pbo_folder: &Path) -> Option<String>
+{
+    let pbo_json = PBOJsonHandler::default().read_json(pbo_folder.join("pbo.json")).ok()?;
+
+    pbo_json.headers.into_iter()
+        .find(|header| header.name.eq_ignore_ascii_case("prefix"))
+        .map(|header| header.value)
+}
+
+/// The prefix a pbo folder is packed with: its pbo.json, else the addon override or prefix pattern of the manifest
+pub fn pbo_prefix(pbo_folder: &Path, manifest: &ArmapackToml) -> Option<String>
+{
+    let addon_name = pbo_folder.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+    pbo_json_prefix(pbo_folder).or_else(|| manifest.addon_prefix(&addon_name))
+}
+
+/// Why Arma cannot use the prefix, None if it is fine
+pub fn prefix_problem(prefix: &str) -> Option<&'static str>
+{
+    if prefix.is_empty()
+    {
+        return Some("is empty");
+    }
+
+    if prefix.contains('/')
+    {
+        return Some("uses / instead of \\ as separator");
+    }
+
+    if prefix.starts_with('\\')
+    {
+        return Some("starts with a \\");
+    }
+
+    if prefix.ends_with('\\')
+    {
+        return Some("ends with a \\");
+    }
+
+    if prefix.contains("\\\\")
+    {
+        return Some("contains an empty folder name");
+    }
+
+    None
+}
+
+/// Resolves the prefix of every pbo folder and checks that it is valid and not used by any other pbo of the mod.
+/// Pbo folders without a prefix are left out.
+pub fn check_prefixes<'a, I: IntoIterator<Item = &'a PathBuf>>(pbo_folders: I, manifest: &ArmapackToml, stage: PipelineStage) -> (Vec<PboPrefix>, Vec<ModPackerError>)
+{
+    let mut prefixes: Vec<PboPrefix> = vec![];
+    let mut errors: Vec<ModPackerError> = vec![];
+
+    // prefixes are case-insensitive in Arma
+    let mut used_prefixes = BTreeMap::<String, PathBuf>::new();
+
+    for pbo_folder in pbo_folders
+    {
+        let Some(prefix) = pbo_prefix(pbo_folder, manifest) else {
+            continue;
+        };
+
+        if let Some(problem) = prefix_problem(&prefix)
+        {
+            errors.push(ModPackerError::InvalidPrefix {
+                stage,
+                path: pbo_folder.clone(),
+                prefix,
+                problem: String::from(problem),
+            });
+            continue;
+        }
+
+        if let Some(other) = used_prefixes.get(&prefix.to_ascii_lowercase())
+        {
+            errors.push(ModPackerError::DuplicatePrefix {
+                stage,
+                path: pbo_folder.clone(),
+                prefix,
+                other: other.clone(),
+            });
+            continue;
+        }
+
+        used_prefixes.insert(prefix.to_ascii_lowercase(), pbo_folder.clone());
+
+        prefixes.push(PboPrefix {
+            folder: pbo_folder.clone(),
+            prefix,
+        });
+    }
+
+    (prefixes, errors)
+}
+
+/// Whether the checks see the file: every file when checking a copied mod folder, only the copied ones when checking the source
+/// (e.g. in a dry run), so files excluded by .modignore count as missing in both
+pub fn is_checked(copied_files: Option<&BTreeSet<PathBuf>>, path: &Path) -> bool
+{
+    copied_files.is_none_or(|copied_files| copied_files.contains(path))
+}
+
+/// Checks the #include paths in the configs (.cpp and .hpp files) of the pbo folders.
+/// Relative includes must exist next to the including file. Absolute includes below the prefix of a pbo of the mod must exist
+/// in that pbo, and ones below the prefix root of the manifest must match the prefix of a pbo. Includes from other mods are not checked.
+pub fn check_includes<'a, I: IntoIterator<Item = &'a PathBuf>>(pbo_folders: I, prefixes: &[PboPrefix], manifest: &ArmapackToml, copied_files: Option<&BTreeSet<PathBuf>>, stage: PipelineStage) -> Vec<ModPackerError>
+{
+    let prefix_root = manifest.prefix_root().map(|prefix_root| prefix_root.to_ascii_lowercase());
+
+    let mut errors: Vec<ModPackerError> = vec![];
+
+    for pbo_folder in pbo_folders
+    {
+        for config_file in config_files(pbo_folder).into_iter().filter(|config_file| is_checked(copied_files, config_file))
+        {
+            let Ok(content) = fs::read(&config_file) else {
+                continue;
+            };
+
+            // commented out includes are not preprocessed, the stripped content keeps the line numbers
+            let content = strip_comments(&String::from_utf8_lossy(&content), false);
+
+            for (index, include) in content.lines().enumerate().filter_map(|(index, line)| Some((index, include_path(line)?)))
+            {
+                let problem = include_problem(&include, &config_file, prefixes, prefix_root.as_deref(), copied_files);
+
+                if let Some(problem) = problem
+                {
+                    errors.push(ModPackerError::BrokenInclude {
+                        stage,
+                        path: config_file.clone(),
+                        line: index + 1,
+                        include,
+                        problem,
+                    });
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+/// The path of an #include line, None for every other line
+fn include_path(line: &str) -> Option<String>
+{
+    let directive = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix("include")?.trim();
+
+    let (open, close) = match directive.chars().next()?
+    {
+        '"' => ('"', '"'),
+        '<' => ('<', '>'),
+        _ => return None
+    };
+
+    let path = directive.strip_prefix(open)?.split(close).next()?;
+
+    Some(String::from(path))
+}
+
+fn include_problem(include: &str, config_file: &Path, prefixes: &[PboPrefix], prefix_root: Option<&str>, copied_files: Option<&BTreeSet<PathBuf>>) -> Option<String>
+{
+    let mut include_path = include.replace('/', "\\");
+
+    // doubled separators, as if the include was an escaped string, still resolve in Arma
+    while include_path.contains("\\\\")
+    {
+        include_path = include_path.replace("\\\\", "\\");
+    }
+
+    let Some(absolute_path) = include_path.strip_prefix('\\') else {
+        let folder = config_file.parent().unwrap_or(Path::new("."));
+
+        return match find_case_insensitive(folder, &include_path).filter(|path| is_checked(copied_files, path))
+        {
+            Some(_) => None,
+            None => Some(format!("does not exist relative to {folder:?}"))
+        };
+    };
+
+    let lowercase_path = absolute_path.to_ascii_lowercase();
+
+    let pbo_prefix = prefixes.iter()
+        .filter(|pbo_prefix| is_below(&lowercase_path, &pbo_prefix.prefix.to_ascii_lowercase()))
+        .max_by_key(|pbo_prefix| pbo_prefix.prefix.len());
+
+    match pbo_prefix
+    {
+        Some(pbo_prefix) => {
+            let relative_path = absolute_path[pbo_prefix.prefix.len()..].trim_start_matches('\\');
+
+            match find_case_insensitive(&pbo_prefix.folder, relative_path).filter(|path| is_checked(copied_files, path))
+            {
+                Some(_) => None,
+                None => Some(format!("does not exist in the pbo folder {:?} with the prefix \"{}\"", pbo_prefix.folder, pbo_prefix.prefix))
+            }
+        }
+        None if prefix_root.is_some_and(|prefix_root| is_below(&lowercase_path, prefix_root)) => {
+            Some(String::from("is below the prefix root of the mod but no pbo has a matching prefix"))
+        }
+        None => None
+    }
+}
+
+/// Whether the path is the prefix itself or inside it, both lowercase and \ separated
+pub fn is_below(path: &str, prefix: &str) -> bool
+{
+    path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('\\'))
+}
+
+/// Finds the file below the folder like Arma does, ignoring the case of every component of the \ separated path
+pub fn find_case_insensitive(folder: &Path, relative_path: &str) -> Option<PathBuf>
+{
+    let mut current = PathBuf::from(folder);
+
+    for component in relative_path.split('\\').filter(|component| !component.is_empty() && *component != ".")
+    {
+        if component == ".."
+        {
+            current.pop();
+            continue;
+        }
+
+        let exact = current.join(component);
+
+        if exact.exists()
+        {
+            current = exact;
+            continue;
+        }
+
+        let entry = current.read_dir().ok()?
+            .filter_map(|entry| entry.ok())
+            .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(component))?;
+
+        current = entry.path();
+    }
+
+    Some(current).filter(|path| path.is_file())
+}
+
+fn config_files(pbo_folder: &Path) -> Vec<PathBuf>
+{
+    let mut config_files: Vec<PathBuf> = WalkBuilder::new(pbo_folder)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("cpp") || extension.eq_ignore_ascii_case("hpp")))
+        .collect();
+
+    config_files.sort();
+
+    config_files
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use crate::packers::mod_packer_error::{ModPackerError, PipelineStage};
+    use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+    use super::{check_includes, check_prefixes, prefix_problem};
+
+    const MANIFEST: &str = r#"
+        [mod]
+        name = "test"
+        prefix = "z\\{project}\\addons\\{addon}"
+    "#;
+
+    /// A fresh mod folder in the temp folder with the given files below it
+    fn mod_folder(test_name: &str, files: &[(&str, &str)]) -> PathBuf
+    {
+        let folder = std::env::temp_dir().join(format!("armapack-prefixes-test-{test_name}-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&folder);
+
+        for (file, content) in files
+        {
+            let path = folder.join(file);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        folder
+    }
+
+    fn pbo_json(prefix: &str) -> String
+    {
+        format!(r#"{{ "headers": [{{ "name": "prefix", "value": "{}" }}] }}"#, prefix.replace('\\', "\\\\"))
+    }
+
+    fn addon_folders(folder: &Path, addons: &[&str]) -> Vec<PathBuf>
+    {
+        addons.iter().map(|addon| folder.join("addons").join(addon)).collect()
+    }
+
+    #[test]
+    fn valid_prefixes_come_from_the_pattern_or_pbo_json()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        let folder = mod_folder("valid", &[
+            ("addons/main/config.cpp", ""),
+            ("addons/extra/pbo.json", &pbo_json("z\\test\\addons\\custom")),
+        ]);
+
+        let (prefixes, errors) = check_prefixes(&addon_folders(&folder, &["main", "extra"]), &manifest, PipelineStage::CheckPrefixes);
+
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let prefixes: Vec<&str> = prefixes.iter().map(|pbo_prefix| pbo_prefix.prefix.as_str()).collect();
+
+        assert_eq!(prefixes, vec!["z\\test\\addons\\main", "z\\test\\addons\\custom"]);
+        assert_eq!(prefix_problem("z\\test\\addons\\main"), None);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn mismatched_prefixes_are_reported()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        // the pbo.json of extra claims the prefix the pattern gives main, with another case
+        let folder = mod_folder("mismatched", &[
+            ("addons/main/config.cpp", ""),
+            ("addons/extra/pbo.json", &pbo_json("Z\\Test\\addons\\main")),
+            ("addons/broken/pbo.json", &pbo_json("z/test/addons/broken")),
+        ]);
+
+        let (prefixes, errors) = check_prefixes(&addon_folders(&folder, &["main", "extra", "broken"]), &manifest, PipelineStage::CheckPrefixes);
+
+        assert_eq!(prefixes.len(), 1);
+        assert!(matches!(&errors[..], [
+            ModPackerError::DuplicatePrefix { prefix: duplicate, .. },
+            ModPackerError::InvalidPrefix { prefix: invalid, .. }
+        ] if duplicate == "Z\\Test\\addons\\main" && invalid == "z/test/addons/broken"), "{errors:?}");
+
+        assert_eq!(prefix_problem(""), Some("is empty"));
+        assert_eq!(prefix_problem("\\z\\test"), Some("starts with a \\"));
+        assert_eq!(prefix_problem("z\\test\\"), Some("ends with a \\"));
+        assert_eq!(prefix_problem("z\\\\test"), Some("contains an empty folder name"));
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn bad_prefix_patterns_are_rejected()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        assert_eq!(manifest.check_prefix_pattern(), Ok(()));
+        assert_eq!(manifest.addon_prefix("main").as_deref(), Some("z\\test\\addons\\main"));
+        assert_eq!(manifest.prefix_root().as_deref(), Some("z\\test\\addons"));
+
+        let unknown_placeholder: ArmapackToml = toml::from_str("[mod]\nprefix = 'z\\{mod}\\addons\\{addon}'").unwrap();
+
+        assert!(unknown_placeholder.check_prefix_pattern().unwrap_err().contains("unknown placeholder {mod}"));
+
+        let missing_name: ArmapackToml = toml::from_str("[mod]\nprefix = 'z\\{project}\\addons'").unwrap();
+
+        assert!(missing_name.check_prefix_pattern().unwrap_err().contains("needs mod.name"));
+    }
+
+    #[test]
+    fn bad_includes_are_reported()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        let folder = mod_folder("includes", &[
+            ("addons/main/script_macros.hpp", ""),
+            ("addons/main/config.cpp", concat!(
+                "#include \"script_macros.hpp\"\n",
+                "#include \"\\z\\test\\addons\\main\\Script_Macros.hpp\"\n",
+                "#include \"\\x\\cba\\addons\\main\\script_macros_common.hpp\"\n",
+                "#include \"missing.hpp\"\n",
+                "#include \"\\z\\test\\addons\\main\\missing.hpp\"\n",
+                "#include \"\\z\\test\\addons\\other\\script_macros.hpp\"\n",
+            )),
+        ]);
+
+        let pbo_folders = addon_folders(&folder, &["main"]);
+
+        let (prefixes, _) = check_prefixes(&pbo_folders, &manifest, PipelineStage::CheckPrefixes);
+
+        let errors = check_includes(&pbo_folders, &prefixes, &manifest, None, PipelineStage::CheckPrefixes);
+
+        let lines: Vec<usize> = errors.iter()
+            .map(|error| match error
+            {
+                ModPackerError::BrokenInclude { line, .. } => *line,
+                other => panic!("unexpected error {other:?}")
+            })
+            .collect();
+
+        assert_eq!(lines, vec![4, 5, 6]);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn commented_out_includes_are_not_checked()
+    {
+        let manifest: ArmapackToml = toml::from_str(MANIFEST).unwrap();
+
+        let folder = mod_folder("commented-includes", &[
+            ("addons/main/config.cpp", concat!(
+                "// #include \"line_comment.hpp\"\n",
+                "/*\n",
+                "#include \"block_comment.hpp\"\n",
+                "*/\n",
+                "/* old */ #include \"missing.hpp\"\n",
+            )),
+        ]);
+
+        let pbo_folders = addon_folders(&folder, &["main"]);
+
+        let (prefixes, _) = check_prefixes(&pbo_folders, &manifest, PipelineStage::CheckPrefixes);
+
+        let errors = check_includes(&pbo_folders, &prefixes, &manifest, None, PipelineStage::CheckPrefixes);
+
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(matches!(&errors[0], ModPackerError::BrokenInclude { line: 5, include, .. } if include == "missing.hpp"));
+    }
+}
\ No newline at end of file
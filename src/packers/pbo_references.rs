@@ -0,0 +1,635 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use ignore::WalkBuilder;
+use crate::converters::converter_registry::{ConversionDirection, ConverterRegistry};
+use crate::diagnostics::error_chain;
+use crate::packers::mod_info_files::{self, ModInfoFile};
+use crate::packers::mod_packer_error::{ModPackerError, PipelineStage};
+use crate::packers::pbo_prefixes::{is_below, is_checked, PboPrefix};
+use crate::pbo::pbo_file::PboFile;
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+use crate::utils::files_with_extension;
+
+/// Files scanned for references: configs, rvmats and scripts
+const REFERENCING_EXTENSIONS: [&str; 4] = ["cpp", "hpp", "rvmat", "sqf"];
+
+/// Quoted strings ending in one of these extensions are taken as the in-game path of a file
+const REFERENCED_EXTENSIONS: [&str; 19] = [
+    "paa", "pac", "png", "jpg", "tga", "p3d", "rtm", "rvmat", "bisurf", "wss", "ogg", "wav", "lip", "sqf", "sqs", "fsm", "bikb", "hpp", "html"
+];
+
+/// What the checks found: invalid mod.cpp files fail the build, as do broken references with strict references.
+/// References with the wrong case and unreadable dependencies only warn.
+#[derive(Debug, Default)]
+pub struct ReferenceCheck
+{
+    pub errors: Vec<ModPackerError>,
+    pub warnings: Vec<String>
+}
+
+/// Where the files of a [PboContents] come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentsSource
+{
+    PboFolder,
+    Dependency,
+
+    /// The loose files of the mod, which the mod.cpp refers to relative to the mod folder
+    ModFolder
+}
+
+/// The files of a pbo as Arma sees them, by their lowercase \ separated path inside the pbo
+struct PboContents
+{
+    /// The pbo folder of the mod, the .pbo file of a dependency or the mod folder
+    location: PathBuf,
+
+    /// Empty for the mod folder
+    prefix: String,
+    source: ContentsSource,
+
+    /// Lowercase path to the path in its actual case
+    files: HashMap<String, String>,
+
+    /// Lowercase path of a file converted while packing to the path of its converted file, e.g. data\tex_co.png to data\tex_co.paa
+    converted_files: HashMap<String, String>
+}
+
+impl PboContents
+{
+    /// The path the file inside is referred to with
+    fn reference_path(&self, relative_path: &str) -> String
+    {
+        match self.source
+        {
+            ContentsSource::ModFolder => String::from(relative_path),
+            _ => format!("{}\\{relative_path}", self.prefix)
+        }
+    }
+}
+
+/// Checks the file paths in the configs, rvmats and scripts of the pbo folders against the files that end up in the pbos of the mod,
+/// after conversion, and in the pbos of its dependencies. Paths outside the mod and its dependencies, e.g. into the base game, are not checked.
+/// The mod.cpp and meta.cpp are validated as well, their pictures may also be relative to the mod folder.
+/// Broken references are only warned about unless strict_references is set. Only the copied files are seen if they are given, see [is_checked].
+pub fn check_references(mod_folder: &Path, prefixes: &[PboPrefix], manifest: &ArmapackToml, converters: &ConverterRegistry, copied_files: Option<&BTreeSet<PathBuf>>, strict_references: bool, stage: PipelineStage) -> ReferenceCheck
+{
+    let mut check = ReferenceCheck::default();
+
+    let converted_paths = converted_paths(mod_folder, manifest, converters, copied_files);
+
+    let mut pbos: Vec<PboContents> = prefixes.iter()
+        .map(|pbo_prefix| folder_contents(&pbo_prefix.folder, &pbo_prefix.prefix, ContentsSource::PboFolder, &converted_paths, copied_files))
+        .collect();
+
+    pbos.extend(dependency_pbo_contents(manifest, &mut check.warnings));
+
+    let prefix_root = manifest.prefix_root().map(|prefix_root| prefix_root.to_ascii_lowercase());
+
+    for pbo_prefix in prefixes
+    {
+        for referencing_file in referencing_files(&pbo_prefix.folder).into_iter().filter(|referencing_file| is_checked(copied_files, referencing_file))
+        {
+            let Ok(content) = fs::read(&referencing_file) else {
+                continue;
+            };
+
+            let is_script = referencing_file.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("sqf"));
+
+            // commented out paths are not part of the packed config
+            let content = strip_comments(&String::from_utf8_lossy(&content), is_script);
+
+            for (index, line) in content.lines().enumerate()
+            {
+                // includes are checked with the prefixes
+                if line.trim_start().starts_with('#')
+                {
+                    continue;
+                }
+
+                for reference in file_references(line, is_script)
+                {
+                    check_reference(&mut check, &reference, &referencing_file, index + 1, &pbos, prefix_root.as_deref(), stage);
+                }
+            }
+        }
+    }
+
+    check_mod_info_files(&mut check, mod_folder, manifest, &pbos, &converted_paths, copied_files, stage);
+
+    // the files may come from a mod that is not listed as dependency or be created at runtime
+    if !strict_references
+    {
+        let (broken_references, errors): (Vec<_>, Vec<_>) = check.errors.into_iter()
+            .partition(|error| matches!(error, ModPackerError::BrokenReference { .. }));
+
+        check.errors = errors;
+        check.warnings.extend(broken_references.iter().map(|error| error.to_string()));
+    }
+
+    check
+}
+
+fn check_mod_info_files(check: &mut ReferenceCheck, mod_folder: &Path, manifest: &ArmapackToml, pbos: &[PboContents], converted_paths: &BTreeMap<PathBuf, PathBuf>, copied_files: Option<&BTreeSet<PathBuf>>, stage: PipelineStage)
+{
+    let prefix_root = manifest.prefix_root().map(|prefix_root| prefix_root.to_ascii_lowercase());
+
+    let mod_folder_contents = folder_contents(mod_folder, "", ContentsSource::ModFolder, converted_paths, copied_files);
+
+    for mod_info_file in ModInfoFile::read_all(mod_folder, manifest, stage)
+    {
+        let mod_info_file = match mod_info_file
+        {
+            Ok(mod_info_file) => mod_info_file,
+            Err(error) => {
+                check.errors.push(error);
+                continue;
+            }
+        };
+
+        let (errors, warnings) = mod_info_file.validate(stage);
+
+        check.errors.extend(errors);
+        check.warnings.extend(warnings);
+
+        for (line, picture) in mod_info_file.pictures()
+        {
+            if !check_reference(check, picture, &mod_info_file.path, line, pbos, prefix_root.as_deref(), stage)
+            {
+                let relative_path = normalize_path(picture);
+
+                check_contents(check, picture, &mod_info_file.path, line, &mod_folder_contents, &relative_path, stage);
+            }
+        }
+    }
+}
+
+/// Checks the reference against the pbos it is an in-game path of. False if it is not below the prefix of a pbo or the prefix root,
+/// e.g. a path into the base game.
+fn check_reference(check: &mut ReferenceCheck, reference: &str, referencing_file: &Path, line: usize, pbos: &[PboContents], prefix_root: Option<&str>, stage: PipelineStage) -> bool
+{
+    let path = normalize_path(reference);
+
+    let lowercase_path = path.to_ascii_lowercase();
+
+    let pbo = pbos.iter()
+        .filter(|pbo| is_below(&lowercase_path, &pbo.prefix.to_ascii_lowercase()))
+        .max_by_key(|pbo| pbo.prefix.len());
+
+    let Some(pbo) = pbo else {
+        if !prefix_root.is_some_and(|prefix_root| is_below(&lowercase_path, prefix_root))
+        {
+            return false;
+        }
+
+        check.errors.push(ModPackerError::BrokenReference {
+            stage,
+            path: PathBuf::from(referencing_file),
+            line,
+            reference: String::from(reference),
+            problem: String::from("is below the prefix root of the mod but no pbo has a matching prefix"),
+        });
+
+        return true;
+    };
+
+    let relative_path = path[pbo.prefix.len()..].trim_start_matches('\\');
+
+    check_contents(check, reference, referencing_file, line, pbo, relative_path, stage);
+
+    true
+}
+
+/// Checks that the file exists in the contents, with the same case and under the name it has after conversion
+fn check_contents(check: &mut ReferenceCheck, reference: &str, referencing_file: &Path, line: usize, contents: &PboContents, relative_path: &str, stage: PipelineStage)
+{
+    let lowercase_relative_path = relative_path.to_ascii_lowercase();
+
+    if let Some(actual_path) = contents.files.get(&lowercase_relative_path)
+    {
+        if actual_path != relative_path
+        {
+            check.warnings.push(format!(
+                "File reference \"{reference}\" in {referencing_file:?} line {line} has the wrong case, the file is \"{}\"",
+                contents.reference_path(actual_path)
+            ));
+        }
+
+        return;
+    }
+
+    let problem = match (contents.converted_files.get(&lowercase_relative_path), contents.source)
+    {
+        (Some(converted_path), _) => format!("is converted while packing, refer to \"{}\" instead", contents.reference_path(converted_path)),
+        (None, ContentsSource::PboFolder) => format!("does not exist in the pbo folder {:?} with the prefix \"{}\"", contents.location, contents.prefix),
+        (None, ContentsSource::Dependency) => format!("does not exist in the dependency {:?} with the prefix \"{}\"", contents.location, contents.prefix),
+        (None, ContentsSource::ModFolder) => format!("does not exist in the mod folder {:?}", contents.location)
+    };
+
+    check.errors.push(ModPackerError::BrokenReference {
+        stage,
+        path: PathBuf::from(referencing_file),
+        line,
+        reference: String::from(reference),
+        problem,
+    });
+}
+
+/// The path without leading \ and with single separators, / is accepted as separator as well
+fn normalize_path(reference: &str) -> String
+{
+    let mut path = reference.replace('/', "\\");
+
+    // doubled separators, as if the path was an escaped string, still resolve in Arma
+    while path.contains("\\\\")
+    {
+        path = path.replace("\\\\", "\\");
+    }
+
+    String::from(path.trim_start_matches('\\'))
+}
+
+/// The content without // and /* */ comments, line breaks inside block comments are kept so the line numbers stay the same.
+/// Comment markers inside quoted strings, e.g. in "http://example.com", are part of the string.
+pub fn strip_comments(content: &str, is_script: bool) -> String
+{
+    let mut stripped = String::with_capacity(content.len());
+
+    let mut characters = content.chars().peekable();
+
+    let mut quote: Option<char> = None;
+
+    while let Some(character) = characters.next()
+    {
+        if let Some(open_quote) = quote
+        {
+            // config strings end with the line, script strings may span lines
+            if character == open_quote || (character == '\n' && !is_script)
+            {
+                quote = None;
+            }
+
+            stripped.push(character);
+            continue;
+        }
+
+        match (character, characters.peek())
+        {
+            ('/', Some('/')) => {
+                while characters.next_if(|next| *next != '\n').is_some() {}
+            }
+            ('/', Some('*')) => {
+                characters.next();
+
+                while let Some(comment_character) = characters.next()
+                {
+                    if comment_character == '*' && characters.next_if_eq(&'/').is_some()
+                    {
+                        break;
+                    }
+
+                    if comment_character == '\n'
+                    {
+                        stripped.push('\n');
+                    }
+                }
+
+                // keeps the code before and after the comment apart
+                stripped.push(' ');
+            }
+            _ => {
+                if character == '"' || (is_script && character == '\'')
+                {
+                    quote = Some(character);
+                }
+
+                stripped.push(character);
+            }
+        }
+    }
+
+    stripped
+}
+
+/// The quoted strings of the line that look like the path of a file, scripts also quote with '
+fn file_references(line: &str, is_script: bool) -> Vec<String>
+{
+    let mut references: Vec<String> = vec![];
+
+    let mut characters = line.char_indices();
+
+    while let Some((start, quote)) = characters.next()
+    {
+        if quote != '"' && !(is_script && quote == '\'')
+        {
+            continue;
+        }
+
+        let Some((end, _)) = characters.by_ref().find(|(_, character)| *character == quote) else {
+            break;
+        };
+
+        let string = &line[start + 1..end];
+
+        if is_file_path(string)
+        {
+            references.push(String::from(string));
+        }
+    }
+
+    references
+}
+
+fn is_file_path(string: &str) -> bool
+{
+    // format strings like "\x\data\%1.paa" are only completed at runtime
+    if !string.contains('\\') || string.contains('%')
+    {
+        return false;
+    }
+
+    let file_name = string.rsplit('\\').next().unwrap_or_default();
+
+    file_name.rsplit_once('.').is_some_and(|(stem, extension)| {
+        !stem.is_empty() && REFERENCED_EXTENSIONS.iter().any(|referenced_extension| referenced_extension.eq_ignore_ascii_case(extension))
+    })
+}
+
+/// Every file the converters change the path of while packing, with the path it is converted to.
+/// Uses the same .convertignore rules and converter settings as the conversion itself.
+fn converted_paths(mod_folder: &Path, manifest: &ArmapackToml, converters: &ConverterRegistry, copied_files: Option<&BTreeSet<PathBuf>>) -> BTreeMap<PathBuf, PathBuf>
+{
+    let walk = WalkBuilder::new(mod_folder)
+        .standard_filters(false)
+        .add_custom_ignore_filename(".convertignore")
+        .build();
+
+    walk.filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && is_checked(copied_files, path) && !mod_info_files::has_mod_info_file_name(path))
+        .filter_map(|path| {
+            let rule = converters.find(&path, ConversionDirection::Pack)
+                .filter(|rule| rule.converter.is_enabled(&manifest.converters))?;
+
+            let output_path = rule.output_path(&path);
+
+            (output_path != path).then_some((path, output_path))
+        })
+        .collect()
+}
+
+fn folder_contents(folder: &Path, prefix: &str, source: ContentsSource, converted_paths: &BTreeMap<PathBuf, PathBuf>, copied_files: Option<&BTreeSet<PathBuf>>) -> PboContents
+{
+    let mut contents = PboContents {
+        location: PathBuf::from(folder),
+        prefix: String::from(prefix),
+        source,
+        files: HashMap::new(),
+        converted_files: HashMap::new(),
+    };
+
+    let walk = WalkBuilder::new(folder)
+        .standard_filters(false)
+        .build();
+
+    for path in walk.filter_map(|entry| entry.ok()).map(|entry| entry.into_path()).filter(|path| path.is_file() && is_checked(copied_files, path))
+    {
+        let source_path = pbo_path(folder, &path);
+
+        match converted_paths.get(&path)
+        {
+            Some(converted_path) => {
+                let converted_path = pbo_path(folder, converted_path);
+
+                contents.files.insert(converted_path.to_ascii_lowercase(), converted_path.clone());
+                contents.converted_files.insert(source_path.to_ascii_lowercase(), converted_path);
+            }
+            None => {
+                contents.files.insert(source_path.to_ascii_lowercase(), source_path);
+            }
+        }
+    }
+
+    contents
+}
+
+/// The pbos in the addons folders of the dependencies, dependencies that cannot be read are skipped with a warning
+fn dependency_pbo_contents(manifest: &ArmapackToml, warnings: &mut Vec<String>) -> Vec<PboContents>
+{
+    let mut pbos: Vec<PboContents> = vec![];
+
+    for dependency in &manifest.mod_info.dependencies
+    {
+        let addons_folder = Path::new(dependency).join("addons");
+
+        if !addons_folder.is_dir()
+        {
+            warnings.push(format!("Dependency {dependency:?} has no addons folder, file references into it are not checked"));
+            continue;
+        }
+
+        for pbo_file in files_with_extension(&addons_folder, "pbo")
+        {
+            let pbo = match PboFile::read(&pbo_file)
+            {
+                Ok(pbo) => pbo,
+                Err(error) => {
+                    warnings.push(format!("Cannot read dependency {pbo_file:?}, file references into it are not checked: {}", error_chain(&error)));
+                    continue;
+                }
+            };
+
+            // without a prefix Arma mounts the pbo under its file name
+            let prefix = pbo.properties.iter()
+                .find(|property| property.name.eq_ignore_ascii_case("prefix"))
+                .map(|property| property.value.to_string().trim_matches('\\').to_string())
+                .unwrap_or_else(|| pbo_file.file_stem().unwrap_or_default().to_string_lossy().to_string());
+
+            let files = pbo.entries.into_iter()
+                .map(|entry| entry.name.to_string().replace('/', "\\"))
+                .map(|name| (name.to_ascii_lowercase(), name))
+                .collect();
+
+            pbos.push(PboContents {
+                location: pbo_file,
+                prefix,
+                source: ContentsSource::Dependency,
+                files,
+                converted_files: HashMap::new(),
+            });
+        }
+    }
+
+    pbos
+}
+
+/// The \ separated path of the file inside the pbo packed from the folder
+fn pbo_path(pbo_folder: &Path, path: &Path) -> String
+{
+    let relative_path = path.strip_prefix(pbo_folder).unwrap_or(path);
+
+    relative_path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+fn referencing_files(pbo_folder: &Path) -> Vec<PathBuf>
+{
+    let mut referencing_files: Vec<PathBuf> = WalkBuilder::new(pbo_folder)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension().is_some_and(|extension| {
+            REFERENCING_EXTENSIONS.iter().any(|referencing_extension| extension.eq_ignore_ascii_case(referencing_extension))
+        }))
+        .collect();
+
+    referencing_files.sort();
+
+    referencing_files
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+    use std::path::Path;
+    use crate::converters::converter_registry::ConverterRegistry;
+    use crate::packers::mod_packer_error::{ModPackerError, PipelineStage};
+    use crate::packers::pbo_prefixes::PboPrefix;
+    use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+    use super::{check_references, strip_comments, ReferenceCheck};
+
+    const CONFIG: &str = r#"class CfgVehicles
+{
+    picture = "\z\test\addons\main\data\icon.paa";
+    texture = "\z\test\addons\main\data\missing.paa";
+    model = "\x\dep\addons\core\data\dep.p3d";
+    other = "\x\dep\addons\core\data\missing.p3d";
+    // old = "\z\test\addons\main\data\line_comment.paa";
+    /* old = "\z\test\addons\main\data\block_comment.paa";
+       old = "\z\test\addons\main\data\block_comment.paa"; */ url = "http://example.com"; // "\z\test\addons\main\data\trailing.paa"
+};"#;
+
+    /// Writes an uncompressed pbo with the prefix whose entries are empty files
+    fn write_pbo(path: &Path, prefix: &str, entry_names: &[&str])
+    {
+        // header extension entry ("Vers") with the properties
+        let mut bytes: Vec<u8> = vec![0];
+
+        bytes.extend_from_slice(&0x5665_7273u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+
+        for string in ["prefix", prefix, ""]
+        {
+            bytes.extend_from_slice(string.as_bytes());
+            bytes.push(0);
+        }
+
+        for name in entry_names.iter().chain([""].iter())
+        {
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.push(0);
+            bytes.extend_from_slice(&[0; 20]);
+        }
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, bytes).unwrap();
+    }
+
+    /// Checks the config in the pbo folder main of a mod with the prefix root z\test\addons and the dependency @dep
+    fn check(test_name: &str, strict_references: bool) -> ReferenceCheck
+    {
+        let folder = std::env::temp_dir().join(format!("armapack-references-test-{test_name}-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&folder);
+
+        let pbo_folder = folder.join("mod").join("addons").join("main");
+
+        fs::create_dir_all(pbo_folder.join("data")).unwrap();
+        fs::write(pbo_folder.join("data").join("icon.paa"), "").unwrap();
+        fs::write(pbo_folder.join("config.cpp"), CONFIG).unwrap();
+
+        write_pbo(&folder.join("@dep").join("addons").join("core.pbo"), "x\\dep\\addons\\core", &["data\\dep.p3d"]);
+
+        let mut manifest: ArmapackToml = toml::from_str("[mod]\nprefix = 'z\\test\\addons'").unwrap();
+
+        manifest.mod_info.dependencies = vec![folder.join("@dep").to_string_lossy().to_string()];
+
+        let prefixes = vec![
+            PboPrefix { folder: pbo_folder, prefix: String::from("z\\test\\addons\\main") },
+        ];
+
+        let converters = ConverterRegistry::for_manifest(&manifest.converters);
+
+        let check = check_references(&folder.join("mod"), &prefixes, &manifest, &converters, None, strict_references, PipelineStage::CheckReferences);
+
+        fs::remove_dir_all(&folder).unwrap();
+
+        check
+    }
+
+    fn broken_references(errors: &[ModPackerError]) -> Vec<(usize, &str)>
+    {
+        errors.iter()
+            .map(|error| match error
+            {
+                ModPackerError::BrokenReference { line, reference, .. } => (*line, reference.as_str()),
+                other => panic!("unexpected error {other:?}")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resolved_references_pass()
+    {
+        let check = check("resolved", true);
+
+        let references: Vec<&str> = broken_references(&check.errors).into_iter().map(|(_, reference)| reference).collect();
+
+        assert!(!references.iter().any(|reference| reference.ends_with("icon.paa") || reference.ends_with("dep.p3d")), "{references:?}");
+        assert!(check.warnings.is_empty(), "{:?}", check.warnings);
+    }
+
+    #[test]
+    fn missing_references_only_warn_by_default()
+    {
+        let check = check("missing", false);
+
+        assert!(check.errors.is_empty(), "{:?}", check.errors);
+        assert_eq!(check.warnings.len(), 2, "{:?}", check.warnings);
+        assert!(check.warnings[0].contains("\\z\\test\\addons\\main\\data\\missing.paa"));
+        assert!(check.warnings[1].contains("\\x\\dep\\addons\\core\\data\\missing.p3d"));
+    }
+
+    #[test]
+    fn missing_references_fail_with_strict_references()
+    {
+        let check = check("strict", true);
+
+        assert_eq!(broken_references(&check.errors), vec![
+            (4, "\\z\\test\\addons\\main\\data\\missing.paa"),
+            (6, "\\x\\dep\\addons\\core\\data\\missing.p3d"),
+        ]);
+
+        assert!(check.errors[1].to_string().contains("does not exist in the dependency"));
+    }
+
+    #[test]
+    fn commented_out_references_are_ignored()
+    {
+        let stripped = strip_comments(CONFIG, false);
+
+        assert_eq!(stripped.lines().count(), CONFIG.lines().count());
+        assert!(!stripped.contains("comment.paa") && !stripped.contains("trailing.paa"));
+        assert!(stripped.lines().nth(8).unwrap().contains("url = \"http://example.com\";"));
+
+        let script = strip_comments("hint 'a // b'; /* \"\\z\\test\\addons\\main\\data\\gone.paa\" */ hint \"c\";", true);
+
+        assert_eq!(script, "hint 'a // b';   hint \"c\";");
+    }
+}
\ No newline at end of file
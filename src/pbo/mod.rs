@@ -0,0 +1 @@
+pub mod pbo_file;
\ No newline at end of file
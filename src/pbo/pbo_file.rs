@@ -0,0 +1,433 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Serialize, Serializer};
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+use crate::utils::to_hex;
+
+/// Packing method of the header extension entry holding the pbo properties ("Vers")
+const VERSION_PACKING_METHOD: u32 = 0x5665_7273;
+
+/// Packing method of compressed entries ("Cprs")
+const COMPRESSED_PACKING_METHOD: u32 = 0x4370_7273;
+
+/// Packing method of encrypted entries ("Enco")
+const ENCRYPTED_PACKING_METHOD: u32 = 0x456e_636f;
+
+const CHECKSUM_LENGTH: usize = 20;
+
+#[derive(Error, Debug)]
+pub enum PboReadError
+{
+    #[error("IO Error")]
+    IO(#[from] io::Error),
+
+    #[error("Invalid PBO: {0}")]
+    Invalid(String)
+}
+
+/// A string from the pbo header, kept as the raw bytes so names in other encodings than UTF-8 (e.g. Windows-1252)
+/// are written back unchanged. Only decoded for display.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PboString(Vec<u8>);
+
+impl PboString
+{
+    pub fn as_bytes(&self) -> &[u8]
+    {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    pub fn eq_ignore_ascii_case(&self, other: &str) -> bool
+    {
+        self.0.eq_ignore_ascii_case(other.as_bytes())
+    }
+}
+
+impl From<&[u8]> for PboString
+{
+    fn from(bytes: &[u8]) -> Self
+    {
+        Self(bytes.to_vec())
+    }
+}
+
+impl fmt::Display for PboString
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        formatter.pad(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl Serialize for PboString
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PboProperty
+{
+    pub name: PboString,
+    pub value: PboString
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PboEntry
+{
+    pub name: PboString,
+    pub packing_method: String,
+    pub original_size: u32,
+    pub timestamp: u32,
+    pub data_size: u32,
+    pub data_offset: u64,
+
+    /// The packing method as stored in the header
+    #[serde(skip)]
+    pub packing_method_id: u32
+}
+
+/// The header of a pbo file, read natively without any external tool
+#[derive(Serialize, Debug, Clone)]
+pub struct PboFile
+{
+    pub properties: Vec<PboProperty>,
+    pub entries: Vec<PboEntry>,
+
+    /// SHA1 stored at the end of the pbo, missing in pbos written by some older tools
+    pub stored_checksum: Option<String>,
+
+    /// SHA1 of everything before the stored checksum
+    pub computed_checksum: String
+}
+
+impl PboFile
+{
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, PboReadError>
+    {
+        let bytes = fs::read(path)?;
+
+        Self::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, PboReadError>
+    {
+        let mut reader = ByteReader {
+            bytes,
+            position: 0
+        };
+
+        let mut properties: Vec<PboProperty> = vec![];
+        let mut entries: Vec<PboEntry> = vec![];
+
+        let mut is_first_entry = true;
+
+        loop
+        {
+            let name = reader.read_string()?;
+            let packing_method = reader.read_u32()?;
+            let original_size = reader.read_u32()?;
+            let _reserved = reader.read_u32()?;
+            let timestamp = reader.read_u32()?;
+            let data_size = reader.read_u32()?;
+
+            if name.is_empty() && is_first_entry && packing_method == VERSION_PACKING_METHOD
+            {
+                loop
+                {
+                    let property_name = reader.read_string()?;
+
+                    if property_name.is_empty()
+                    {
+                        break;
+                    }
+
+                    let value = reader.read_string()?;
+
+                    properties.push(PboProperty {
+                        name: property_name,
+                        value,
+                    });
+                }
+
+                is_first_entry = false;
+                continue;
+            }
+
+            if name.is_empty()
+            {
+                break;
+            }
+
+            is_first_entry = false;
+
+            entries.push(PboEntry {
+                name,
+                packing_method: Self::packing_method_name(packing_method),
+                original_size,
+                timestamp,
+                data_size,
+                data_offset: 0,
+                packing_method_id: packing_method,
+            });
+        }
+
+        let mut data_offset = reader.position as u64;
+
+        for entry in &mut entries
+        {
+            entry.data_offset = data_offset;
+            data_offset += entry.data_size as u64;
+        }
+
+        let data_end = usize::try_from(data_offset)
+            .ok()
+            .filter(|data_end| *data_end <= bytes.len())
+            .ok_or_else(|| PboReadError::Invalid(format!("entries need {data_offset} bytes but the file only has {}", bytes.len())))?;
+
+        // the checksum is stored after a single zero byte
+        let stored_checksum = match bytes.get(data_end..)
+        {
+            Some([0, checksum @ ..]) if checksum.len() >= CHECKSUM_LENGTH => Some(to_hex(&checksum[..CHECKSUM_LENGTH])),
+            _ => None
+        };
+
+        let computed_checksum = to_hex(&Sha1::digest(&bytes[..data_end]));
+
+        Ok(Self {
+            properties,
+            entries,
+            stored_checksum,
+            computed_checksum,
+        })
+    }
+
+    /// Rewrites the pbo so the same contents always give the same bytes: entries sorted case-insensitively with backslash separators,
+    /// every entry stamped with the timestamp, properties in the given order (others after them) and the checksum recomputed.
+    /// The entry data, compressed or not, is copied unchanged.
+    pub fn normalize(bytes: &[u8], property_order: &[String], timestamp: u32) -> Result<Vec<u8>, PboReadError>
+    {
+        let pbo = Self::parse(bytes)?;
+
+        let mut properties = pbo.properties;
+
+        properties.sort_by_key(|property| property_order.iter()
+            .position(|name| property.name.eq_ignore_ascii_case(name))
+            .unwrap_or(property_order.len()));
+
+        let mut entries: Vec<(Vec<u8>, PboEntry)> = pbo.entries.into_iter()
+            .map(|entry| {
+                let name = entry.name.as_bytes().iter()
+                    .map(|byte| if *byte == b'/' { b'\\' } else { *byte })
+                    .collect();
+
+                (name, entry)
+            })
+            .collect();
+
+        entries.sort_by_cached_key(|(name, _)| (name.to_ascii_lowercase(), name.clone()));
+
+        let mut output: Vec<u8> = Vec::with_capacity(bytes.len());
+
+        Self::write_entry_header(&mut output, b"", VERSION_PACKING_METHOD, 0, 0, 0);
+
+        for property in &properties
+        {
+            Self::write_string(&mut output, property.name.as_bytes());
+            Self::write_string(&mut output, property.value.as_bytes());
+        }
+
+        output.push(0);
+
+        for (name, entry) in &entries
+        {
+            Self::write_entry_header(&mut output, name, entry.packing_method_id, entry.original_size, timestamp, entry.data_size);
+        }
+
+        Self::write_entry_header(&mut output, b"", 0, 0, 0, 0);
+
+        for (_, entry) in &entries
+        {
+            let data_start = entry.data_offset as usize;
+
+            output.extend_from_slice(&bytes[data_start..data_start + entry.data_size as usize]);
+        }
+
+        let checksum = Sha1::digest(&output);
+
+        output.push(0);
+        output.extend_from_slice(&checksum);
+
+        Ok(output)
+    }
+
+    /// Whether the stored checksum matches the content, None if the pbo has no checksum
+    pub fn checksum_matches(&self) -> Option<bool>
+    {
+        self.stored_checksum.as_ref().map(|stored_checksum| *stored_checksum == self.computed_checksum)
+    }
+
+    fn packing_method_name(packing_method: u32) -> String
+    {
+        match packing_method
+        {
+            0 => String::from("uncompressed"),
+            COMPRESSED_PACKING_METHOD => String::from("compressed"),
+            ENCRYPTED_PACKING_METHOD => String::from("encrypted"),
+            VERSION_PACKING_METHOD => String::from("version"),
+            other => format!("{other:#010x}")
+        }
+    }
+
+    fn write_entry_header(output: &mut Vec<u8>, name: &[u8], packing_method: u32, original_size: u32, timestamp: u32, data_size: u32)
+    {
+        Self::write_string(output, name);
+
+        // the reserved field between the sizes is always 0
+        for value in [packing_method, original_size, 0, timestamp, data_size]
+        {
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn write_string(output: &mut Vec<u8>, value: &[u8])
+    {
+        output.extend_from_slice(value);
+        output.push(0);
+    }
+}
+
+struct ByteReader<'a>
+{
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl ByteReader<'_>
+{
+    fn read_string(&mut self) -> Result<PboString, PboReadError>
+    {
+        let remaining = &self.bytes[self.position..];
+
+        let length = remaining.iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| PboReadError::Invalid(format!("unterminated string in header at byte {}", self.position)))?;
+
+        let value = PboString::from(&remaining[..length]);
+
+        self.position += length + 1;
+
+        Ok(value)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, PboReadError>
+    {
+        let value = self.bytes.get(self.position..self.position + 4)
+            .ok_or_else(|| PboReadError::Invalid(format!("header ends unexpectedly at byte {}", self.position)))?;
+
+        self.position += 4;
+
+        Ok(u32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Builds an uncompressed pbo with the given properties and (name, timestamp, data) entries in that order
+    fn pbo_bytes(properties: &[(&str, &str)], entries: &[(&[u8], u32, &[u8])]) -> Vec<u8>
+    {
+        let mut output = vec![];
+
+        PboFile::write_entry_header(&mut output, b"", VERSION_PACKING_METHOD, 0, 0, 0);
+
+        for (name, value) in properties
+        {
+            PboFile::write_string(&mut output, name.as_bytes());
+            PboFile::write_string(&mut output, value.as_bytes());
+        }
+
+        output.push(0);
+
+        for (name, timestamp, data) in entries
+        {
+            PboFile::write_entry_header(&mut output, name, 0, data.len() as u32, *timestamp, data.len() as u32);
+        }
+
+        PboFile::write_entry_header(&mut output, b"", 0, 0, 0, 0);
+
+        for (_, _, data) in entries
+        {
+            output.extend_from_slice(data);
+        }
+
+        let checksum = Sha1::digest(&output);
+
+        output.push(0);
+        output.extend_from_slice(&checksum);
+
+        output
+    }
+
+    fn property_order() -> Vec<String>
+    {
+        vec![String::from("prefix"), String::from("version")]
+    }
+
+    #[test]
+    fn normalize_gives_identical_bytes_for_the_same_input()
+    {
+        let first = pbo_bytes(
+            &[("version", "1"), ("prefix", "z\\test")],
+            &[(b"config.cpp", 100, b"class CfgPatches {};"), (b"data/caf\xe9.paa", 200, b"image")]
+        );
+
+        let second = pbo_bytes(
+            &[("prefix", "z\\test"), ("version", "1")],
+            &[(b"data\\caf\xe9.paa", 300, b"image"), (b"config.cpp", 400, b"class CfgPatches {};")]
+        );
+
+        let normalized = PboFile::normalize(&first, &property_order(), 0).unwrap();
+
+        assert_eq!(normalized, PboFile::normalize(&first, &property_order(), 0).unwrap());
+        assert_eq!(normalized, PboFile::normalize(&second, &property_order(), 0).unwrap());
+    }
+
+    #[test]
+    fn normalize_is_idempotent()
+    {
+        let bytes = pbo_bytes(
+            &[("prefix", "z\\test")],
+            &[(b"B.sqf", 5, b"hint 'b';"), (b"a.sqf", 7, b"hint 'a';"), (b"data/caf\xe9.paa", 9, b"image")]
+        );
+
+        let normalized = PboFile::normalize(&bytes, &property_order(), 42).unwrap();
+
+        assert_eq!(normalized, PboFile::normalize(&normalized, &property_order(), 42).unwrap());
+        assert_eq!(PboFile::parse(&normalized).unwrap().checksum_matches(), Some(true));
+    }
+
+    #[test]
+    fn normalize_keeps_names_that_are_not_utf8()
+    {
+        let bytes = pbo_bytes(&[("prefix", "z\\caf\u{e9}")], &[(b"data/caf\xe9.paa", 0, b"image")]);
+
+        let pbo = PboFile::parse(&PboFile::normalize(&bytes, &property_order(), 0).unwrap()).unwrap();
+
+        assert_eq!(pbo.entries[0].name.as_bytes(), b"data\\caf\xe9.paa");
+        assert_eq!(pbo.entries[0].name.to_string(), "data\\caf\u{fffd}.paa");
+        assert_eq!(pbo.properties[0].value.to_string(), "z\\caf\u{e9}");
+    }
+}
\ No newline at end of file
@@ -1,6 +1,9 @@
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 use std::path::Path;
-use std::process::Command;
+use std::time::Duration;
+use crate::json_files::path_json::model::launcher::Launcher;
+use crate::tools::tool_invocation::ToolInvocation;
+use crate::tools::tool_runner::{ToolKind, ToolRunner};
 
 pub trait PBOSigner {
     type PBOSignError;
@@ -9,7 +12,9 @@ pub trait PBOSigner {
 }
 
 pub struct ArmaToolsPBOSigner<P: AsRef<Path>> {
-    pub tool_path: P
+    pub tool_path: P,
+    pub launcher: Option<Launcher>,
+    pub timeout: Option<Duration>
 }
 
 impl<P: AsRef<Path>> PBOSigner for ArmaToolsPBOSigner<P>
@@ -21,30 +26,14 @@ impl<P: AsRef<Path>> PBOSigner for ArmaToolsPBOSigner<P>
         let private_key_path = private_key_path.as_ref();
         let output_path = output_path.as_ref();
 
-        let output = Command::new(self.tool_path.as_ref())
+        let invocation = ToolInvocation::new(self.tool_path.as_ref(), self.launcher.as_ref())
             .current_dir(output_path)
-            .arg(private_key_path.as_os_str())
-            .arg(pbo_path.as_os_str())
-            .output()?;
+            .path_arg(private_key_path)
+            .path_arg(pbo_path);
 
-        if !output.status.success() {
-            let code = output.status.code().unwrap_or(0);
-
-            let stdout = String::from_utf8(output.stdout);
-            let stderr = String::from_utf8(output.stderr);
-
-            if let Ok(stdout) = stdout {
-                println!("{}", stdout);
-            }
-
-            if let Ok(stderr) = stderr {
-                eprintln!("{}", stderr);
-            }
-
-            let output = format!("PBO Signing failed, error code: {code}");
-
-            return Err(Error::new(ErrorKind::Other, output));
-        }
+        ToolRunner::new(ToolKind::PboSigner, self.timeout)
+            .run(&invocation)
+            .map_err(Error::other)?;
 
         Ok(())
     }
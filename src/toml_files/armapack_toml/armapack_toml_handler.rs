@@ -0,0 +1,4 @@
+use crate::toml_files::toml_handler::TomlHandler;
+use crate::toml_files::armapack_toml::model::armapack_toml::ArmapackToml;
+
+pub type ArmapackTomlHandler = TomlHandler<ArmapackToml>;
\ No newline at end of file
@@ -0,0 +1,2 @@
+pub mod model;
+pub mod armapack_toml_handler;
\ No newline at end of file
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::converters::converter_registry::{BuiltinConverter, ConversionDirection};
+
+/// Project manifest describing the whole mod build, also providing the defaults for the command line.
+/// Relative paths are resolved against the folder of the armapack.toml.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ArmapackToml
+{
+    #[serde(default, rename = "mod")]
+    pub mod_info: ModSection,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_cpp: Option<ModCppSection>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_json: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+
+    #[serde(default)]
+    pub pack: PackSection,
+
+    #[serde(default)]
+    pub unpack: UnpackSection,
+
+    #[serde(default)]
+    pub signing: SigningSection,
+
+    #[serde(default)]
+    pub converters: ConvertersSection,
+
+    #[serde(default)]
+    pub hooks: HooksSection,
+
+    /// Overrides for single pbo folders, keyed by folder name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub addons: BTreeMap<String, AddonSection>
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ModSection
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Prefix pattern for pbo folders whose pbo.json sets no prefix, e.g. z\{project}\addons\{addon}.
+    /// Without {addon} it is a prefix root the folder name is appended to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+
+    /// Folders of packed mods this mod uses files from, e.g. ../@CBA_A3. File references into their pbos are checked when packing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>
+}
+
+/// Generates the mod.cpp of the packed mod, and its meta.cpp if published_id is set, replacing the ones from the mod folder.
+/// Picture paths are relative to the mod folder or in-game paths into a pbo, like in the mod.cpp itself.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ModCppSection
+{
+    /// Defaults to mod.name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_over: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overview: Option<String>,
+
+    /// Link opened from the launcher, e.g. the website of the mod
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+
+    /// Id of the Steam Workshop item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_id: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PackSection
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_signed: Option<bool>,
+
+    /// Fail packing on broken file references instead of warning about them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict_references: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<bool>,
+
+    /// JSON build report written after packing, must be outside the destination
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UnpackSection
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>
+}
+
+/// Default signing for pbo folders without a key.json of their own or in the mod root
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SigningSection
+{
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authorities: Vec<String>
+}
+
+/// Converters can be disabled, files they would convert are then copied as-is
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ConvertersSection
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paa: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rvmat: Option<bool>,
+
+    /// Additional file extensions for the converters, taking precedence over the built-in ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<ConverterRuleSection>
+}
+
+/// Converts files with the extension or matching the pattern using a built-in converter or a command,
+/// e.g. .tif images to .paa or .wav sounds to .ogg with a script
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ConverterRuleSection
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+
+    /// Glob for the file name, or for the end of the path if it contains a /
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub converter: Option<BuiltinConverter>,
+
+    /// Program and arguments, {input} and {output} are replaced by the paths of the file and the converted file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<Vec<String>>,
+
+    /// Defaults to the output extension of the converter's built-in rule, commands without one convert in place.
+    /// In place, {output} is a temporary file next to the input with a b before its extension (e.g. shot.bwav for shot.wav),
+    /// which then replaces the input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_extension: Option<String>,
+
+    #[serde(default)]
+    pub direction: ConversionDirection
+}
+
+/// Commands run around the pipeline stages when packing, each a program with its arguments.
+/// They run in the mod folder and learn about the build from ARMAPACK_* environment variables.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HooksSection
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before_copy: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_convert: Option<Vec<String>>,
+
+    /// Run once for every pbo folder, before any of them is packed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before_pbo: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_sign: Option<Vec<String>>,
+
+    /// Run once the finished mod is in the destination folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_pack: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AddonSection
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorities: Option<Vec<String>>,
+
+    /// Additional pbo headers, headers set in the folder's pbo.json win
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>
+}
+
+impl ArmapackToml
+{
+    pub const FILE_NAME: &'static str = "armapack.toml";
+
+    /// Prefix for a pbo folder without a prefix in its pbo.json: the addon override, else the mod prefix pattern filled in
+    /// with the folder name and the mod name
+    pub fn addon_prefix(&self, addon_name: &str) -> Option<String>
+    {
+        if let Some(prefix) = self.addons.get(addon_name).and_then(|addon| addon.prefix.clone())
+        {
+            return Some(prefix);
+        }
+
+        let pattern = self.mod_info.prefix.as_ref()?;
+
+        let pattern = if pattern.contains("{addon}") { pattern.clone() } else { format!("{}\\{{addon}}", pattern.trim_end_matches('\\')) };
+
+        Some(self.fill_prefix_pattern(&pattern).replace("{addon}", addon_name))
+    }
+
+    /// The part of the mod prefix pattern all derived prefixes start with, e.g. z\my_mod\addons
+    pub fn prefix_root(&self) -> Option<String>
+    {
+        let pattern = self.mod_info.prefix.as_ref()?;
+
+        let root = pattern.split("{addon}").next().unwrap_or_default().trim_end_matches('\\');
+
+        Some(self.fill_prefix_pattern(root)).filter(|root| !root.is_empty())
+    }
+
+    /// Checks the placeholders of the mod prefix pattern, the prefixes themselves are checked when packing
+    pub fn check_prefix_pattern(&self) -> Result<(), String>
+    {
+        let Some(pattern) = &self.mod_info.prefix else {
+            return Ok(());
+        };
+
+        let unknown_placeholder = pattern.split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}'))
+            .map(|(placeholder, _)| placeholder)
+            .find(|placeholder| *placeholder != "addon" && *placeholder != "project");
+
+        if let Some(placeholder) = unknown_placeholder
+        {
+            return Err(format!("Invalid mod.prefix \"{pattern}\": unknown placeholder {{{placeholder}}}, use {{project}} or {{addon}}"));
+        }
+
+        if pattern.contains("{project}") && self.mod_info.name.is_none()
+        {
+            return Err(format!("Invalid mod.prefix \"{pattern}\": {{project}} needs mod.name to be set"));
+        }
+
+        Ok(())
+    }
+
+    fn fill_prefix_pattern(&self, pattern: &str) -> String
+    {
+        match &self.mod_info.name
+        {
+            Some(project) => pattern.replace("{project}", project),
+            None => String::from(pattern)
+        }
+    }
+
+    pub fn addon_authorities(&self, addon_name: &str) -> Option<&Vec<String>>
+    {
+        self.addons.get(addon_name).and_then(|addon| addon.authorities.as_ref())
+    }
+
+    pub fn addon_headers(&self, addon_name: &str) -> Option<&BTreeMap<String, String>>
+    {
+        self.addons.get(addon_name).map(|addon| &addon.headers)
+    }
+}
\ No newline at end of file
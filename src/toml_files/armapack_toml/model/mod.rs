@@ -0,0 +1 @@
+pub mod armapack_toml;
\ No newline at end of file
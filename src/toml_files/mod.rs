@@ -0,0 +1,2 @@
+pub mod toml_handler;
+pub mod armapack_toml;
\ No newline at end of file
@@ -0,0 +1,61 @@
+use std::io;
+use std::marker::PhantomData;
+use std::path::Path;
+use serde::{Serialize};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TomlWriteError
+{
+    #[error("IO Error")]
+    IO(#[from] io::Error),
+
+    #[error("Serialization Error")]
+    Serialization(#[from] toml::ser::Error)
+}
+
+#[derive(Error, Debug)]
+pub enum TomlReadError
+{
+    #[error("IO Error")]
+    IO(#[from] io::Error),
+
+    #[error("Deserialization Error")]
+    Deserialization(#[from] toml::de::Error)
+}
+
+#[derive(Debug, Clone)]
+pub struct TomlHandler<T: Serialize + DeserializeOwned> {
+    _marker: PhantomData<T>
+}
+
+impl<T: Serialize + DeserializeOwned> Default for TomlHandler<T>
+{
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> TomlHandler<T>
+{
+    pub fn write_toml<DestinationPath: AsRef<Path>>(&self, value: &T, destination: DestinationPath) -> Result<(), TomlWriteError>
+    {
+        let content = toml::to_string_pretty(value)?;
+
+        std::fs::write(destination, content)?;
+
+        Ok(())
+    }
+
+    pub fn read_toml<SourcePath: AsRef<Path>>(&self, source: SourcePath) -> Result<T, TomlReadError>
+    {
+        let content = std::fs::read_to_string(source)?;
+
+        let value: T = toml::from_str(&content)?;
+
+        Ok(value)
+    }
+}
\ No newline at end of file
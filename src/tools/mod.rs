@@ -0,0 +1,3 @@
+pub mod tool_resolver;
+pub mod tool_invocation;
+pub mod tool_runner;
\ No newline at end of file
@@ -0,0 +1,176 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use crate::json_files::path_json::model::launcher::Launcher;
+use crate::tools::tool_resolver::{is_windows_executable, locate};
+
+const TOOL_PLACEHOLDER: &str = "{tool}";
+const WORKING_DIRECTORY_PLACEHOLDER: &str = "{cwd}";
+
+#[derive(Debug, Clone)]
+enum ToolArgument
+{
+    Text(OsString),
+
+    /// Translated to a Windows path if the launcher asks for it
+    Path(PathBuf)
+}
+
+/// A single call of an external tool, started directly or through a [Launcher]
+#[derive(Debug, Clone)]
+pub struct ToolInvocation
+{
+    tool: PathBuf,
+    launcher: Option<Launcher>,
+    arguments: Vec<ToolArgument>,
+    working_directory: Option<PathBuf>,
+    environment: Vec<(OsString, OsString)>
+}
+
+impl ToolInvocation
+{
+    /// Uses the configured launcher, or Wine for Windows executables outside of Windows if none is configured
+    pub fn new<P: AsRef<Path>>(tool: P, launcher: Option<&Launcher>) -> Self
+    {
+        let tool = PathBuf::from(tool.as_ref());
+
+        let launcher = launcher.cloned().or_else(|| Self::automatic_launcher(&tool));
+
+        Self {
+            tool,
+            launcher,
+            arguments: vec![],
+            working_directory: None,
+            environment: vec![],
+        }
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(mut self, argument: S) -> Self
+    {
+        self.arguments.push(ToolArgument::Text(OsString::from(argument.as_ref())));
+        self
+    }
+
+    pub fn path_arg<P: AsRef<Path>>(mut self, path: P) -> Self
+    {
+        self.arguments.push(ToolArgument::Path(PathBuf::from(path.as_ref())));
+        self
+    }
+
+    pub fn current_dir<P: AsRef<Path>>(mut self, working_directory: P) -> Self
+    {
+        self.working_directory = Some(PathBuf::from(working_directory.as_ref()));
+        self
+    }
+
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self
+    {
+        self.environment.push((OsString::from(key.as_ref()), OsString::from(value.as_ref())));
+        self
+    }
+
+    pub fn to_command(&self) -> Command
+    {
+        let mut command = match &self.launcher
+        {
+            Some(launcher) => {
+                let mut command = Command::new(&launcher.program);
+
+                let tool = self.translate(&self.tool);
+
+                let working_directory = match &self.working_directory
+                {
+                    Some(working_directory) => self.translate(working_directory),
+                    None => std::env::current_dir().map(|folder| self.translate(&folder)).unwrap_or_default()
+                };
+
+                let mut has_tool = false;
+
+                for argument in &launcher.arguments
+                {
+                    has_tool |= argument.contains(TOOL_PLACEHOLDER);
+
+                    let argument = argument
+                        .replace(TOOL_PLACEHOLDER, &tool.to_string_lossy())
+                        .replace(WORKING_DIRECTORY_PLACEHOLDER, &working_directory.to_string_lossy());
+
+                    command.arg(argument);
+                }
+
+                if !has_tool
+                {
+                    command.arg(&tool);
+                }
+
+                command
+            }
+            None => Command::new(&self.tool)
+        };
+
+        for argument in &self.arguments
+        {
+            match argument
+            {
+                ToolArgument::Text(text) => command.arg(text),
+                ToolArgument::Path(path) => command.arg(self.translate(path))
+            };
+        }
+
+        if let Some(working_directory) = &self.working_directory
+        {
+            command.current_dir(working_directory);
+        }
+
+        command.envs(self.environment.iter().map(|(key, value)| (key, value)));
+
+        command
+    }
+
+    fn translate(&self, path: &Path) -> OsString
+    {
+        match &self.launcher
+        {
+            Some(launcher) if launcher.uses_windows_paths() => to_windows_path(path),
+            _ => OsString::from(path)
+        }
+    }
+
+    /// Wine for Windows executables outside of Windows
+    pub fn automatic_launcher(tool: &Path) -> Option<Launcher>
+    {
+        if cfg!(windows)
+        {
+            return None;
+        }
+
+        let tool_file = locate(&tool.to_string_lossy()).ok()?;
+
+        is_windows_executable(&tool_file).then(Launcher::wine)
+    }
+}
+
+/// Translates a path to how Wine sees it: absolute paths live on drive Z:, separators become backslashes
+pub fn to_windows_path(path: &Path) -> OsString
+{
+    if cfg!(windows)
+    {
+        return OsString::from(path);
+    }
+
+    let parts: Vec<String> = path.components()
+        .filter_map(|component| match component
+        {
+            Component::RootDir => None,
+            component => Some(component.as_os_str().to_string_lossy().to_string())
+        })
+        .collect();
+
+    let joined = parts.join("\\");
+
+    if path.has_root()
+    {
+        return OsString::from(format!("Z:\\{joined}"));
+    }
+
+    OsString::from(joined)
+}
\ No newline at end of file
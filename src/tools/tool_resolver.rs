@@ -0,0 +1,184 @@
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use crate::json_files::path_json::model::launcher::Launcher;
+use crate::tools::tool_invocation::ToolInvocation;
+
+#[derive(Error, Debug)]
+pub enum ToolResolveError
+{
+    #[error("{0:?} not found")]
+    NotFound(String),
+
+    #[error("{0:?} not found on $PATH")]
+    NotOnPath(String),
+
+    #[error("{0:?} is not executable")]
+    NotExecutable(PathBuf),
+
+    #[error("{tool:?} needs launcher \"{launcher}\", but it cannot be found")]
+    LauncherMissing {
+        tool: PathBuf,
+        launcher: String
+    }
+}
+
+/// How a tool from the path.json is started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedTool
+{
+    pub path: PathBuf,
+
+    /// Set if the tool is started through a launcher, e.g. Wine for Windows executables
+    pub launcher: Option<Launcher>,
+
+    pub launcher_path: Option<PathBuf>
+}
+
+/// Outcome of starting a tool without arguments
+#[derive(Debug)]
+pub enum ProbeResult
+{
+    /// The tool started and exited on its own, most tools print their usage and exit with an error code
+    Responded(Option<i32>),
+
+    TimedOut(Duration),
+
+    Failed(std::io::Error)
+}
+
+impl ResolvedTool
+{
+    /// Resolves a configured tool path the way [ToolInvocation] starts it: paths are taken as they are, bare names are
+    /// searched on $PATH. Tools started through the configured launcher, or through Wine for Windows executables outside
+    /// of Windows, only need to exist while the launcher has to be executable.
+    pub fn resolve(tool_path: &str, launcher: Option<&Launcher>) -> Result<Self, ToolResolveError>
+    {
+        let path = locate(tool_path)?;
+
+        let launcher = launcher.cloned().or_else(|| ToolInvocation::automatic_launcher(&path));
+
+        let Some(launcher) = launcher else {
+            if !is_executable(&path)
+            {
+                return Err(ToolResolveError::NotExecutable(path));
+            }
+
+            return Ok(Self {
+                path,
+                launcher: None,
+                launcher_path: None,
+            });
+        };
+
+        let launcher_path = locate(&launcher.program)
+            .ok()
+            .filter(|launcher_path| is_executable(launcher_path))
+            .ok_or_else(|| ToolResolveError::LauncherMissing {
+                tool: path.clone(),
+                launcher: launcher.program.clone(),
+            })?;
+
+        Ok(Self {
+            path,
+            launcher: Some(launcher),
+            launcher_path: Some(launcher_path),
+        })
+    }
+
+    /// Starts the tool without arguments to check that it runs at all, killing it if it does not exit in time
+    pub fn probe(&self, timeout: Duration) -> ProbeResult
+    {
+        let child = ToolInvocation::new(&self.path, self.launcher.as_ref())
+            .to_command()
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let mut child = match child
+        {
+            Ok(child) => child,
+            Err(error) => return ProbeResult::Failed(error)
+        };
+
+        let started = Instant::now();
+
+        loop
+        {
+            match child.try_wait()
+            {
+                Ok(Some(status)) => return ProbeResult::Responded(status.code()),
+                Ok(None) if started.elapsed() >= timeout => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+
+                    return ProbeResult::TimedOut(timeout);
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(20)),
+                Err(error) => return ProbeResult::Failed(error)
+            }
+        }
+    }
+}
+
+/// The file a configured tool path points to, bare names are searched on $PATH
+pub fn locate(tool_path: &str) -> Result<PathBuf, ToolResolveError>
+{
+    let configured_path = Path::new(tool_path);
+
+    let is_bare_name = configured_path.components().count() == 1 && !configured_path.has_root();
+
+    if is_bare_name
+    {
+        return find_on_path(tool_path).ok_or_else(|| ToolResolveError::NotOnPath(String::from(tool_path)));
+    }
+
+    if !configured_path.is_file()
+    {
+        return Err(ToolResolveError::NotFound(String::from(tool_path)));
+    }
+
+    Ok(PathBuf::from(configured_path))
+}
+
+/// The first file with the name in a folder of $PATH, also trying the name with .exe appended
+pub fn find_on_path(name: &str) -> Option<PathBuf>
+{
+    let search_path = env::var_os("PATH")?;
+
+    let candidates = [String::from(name), format!("{name}.exe")];
+
+    env::split_paths(&search_path)
+        .flat_map(|folder| candidates.iter().map(move |candidate| folder.join(candidate)).collect::<Vec<_>>())
+        .find(|path| path.is_file() && (is_executable(path) || is_windows_executable(path)))
+}
+
+/// Windows executables start with the "MZ" DOS header, scripts named .exe (e.g. wrappers) are started directly
+pub fn is_windows_executable(path: &Path) -> bool
+{
+    let mut header = [0u8; 2];
+
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| header == *b"MZ")
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool
+{
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool
+{
+    path.is_file()
+}
\ No newline at end of file
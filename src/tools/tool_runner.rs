@@ -0,0 +1,449 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::Read;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use crate::console;
+use crate::tools::tool_invocation::ToolInvocation;
+
+/// Time a tool may run when the path.json sets no timeout
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Exit codes of shells and launchers for a program that cannot be executed or found
+const NOT_EXECUTABLE_EXIT_CODE: i32 = 126;
+const NOT_FOUND_EXIT_CODE: i32 = 127;
+
+/// The external tools, each with its own known failure messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind
+{
+    PaaConverter,
+    ConfigConverter,
+    PboPacker,
+    PboSigner,
+
+    /// A command from a converter rule of the armapack.toml
+    ConverterCommand,
+
+    /// A build hook from the armapack.toml
+    Hook
+}
+
+impl Display for ToolKind
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let name = match self
+        {
+            ToolKind::PaaConverter => "ImageToPAA",
+            ToolKind::ConfigConverter => "CfgConvert",
+            ToolKind::PboPacker => "pboc",
+            ToolKind::PboSigner => "DSSignFile",
+            ToolKind::ConverterCommand => "Converter command",
+            ToolKind::Hook => "Hook"
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// Everything needed to rerun a failed tool by hand
+#[derive(Debug, Clone)]
+pub struct InvocationDetails
+{
+    pub command_line: String,
+    pub working_directory: Option<String>
+}
+
+/// Captured output of a finished tool, invalid UTF-8 is replaced
+#[derive(Debug, Clone, Default)]
+pub struct ToolOutput
+{
+    pub stdout: String,
+    pub stderr: String
+}
+
+impl ToolOutput
+{
+    fn lines(&self) -> impl Iterator<Item = &str>
+    {
+        self.stdout.lines().chain(self.stderr.lines()).map(str::trim).filter(|line| !line.is_empty())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ToolError
+{
+    #[error("{tool} cannot be started, check its path and launcher in the path.json")]
+    Start {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        #[source] source: io::Error
+    },
+
+    #[error("{tool} could not be run (exit code {code}), check its path and launcher in the path.json")]
+    NotRunnable {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        code: i32,
+        output: ToolOutput
+    },
+
+    #[error("{tool} did not finish within {}s and was stopped, it may be waiting for input", timeout.as_secs())]
+    TimedOut {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        timeout: Duration
+    },
+
+    #[error("{tool} rejected the config: {message}")]
+    InvalidConfig {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        message: String,
+        output: ToolOutput
+    },
+
+    #[error("{tool} cannot convert the image: {message} (textures need power of two dimensions)")]
+    InvalidImage {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        message: String,
+        output: ToolOutput
+    },
+
+    #[error("{tool} cannot use the private key: {message}")]
+    InvalidKey {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        message: String,
+        output: ToolOutput
+    },
+
+    #[error("{tool} cannot access a file, close programs using it (e.g. Arma or PBO Manager): {message}")]
+    FileInUse {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        message: String,
+        output: ToolOutput
+    },
+
+    #[error("{tool} failed with exit code {}", code.map(|code| code.to_string()).unwrap_or_else(|| String::from("none")))]
+    Failed {
+        tool: ToolKind,
+        invocation: InvocationDetails,
+        code: Option<i32>,
+        output: ToolOutput
+    }
+}
+
+impl ToolError
+{
+    pub fn invocation(&self) -> &InvocationDetails
+    {
+        match self
+        {
+            ToolError::Start { invocation, .. } |
+            ToolError::NotRunnable { invocation, .. } |
+            ToolError::TimedOut { invocation, .. } |
+            ToolError::InvalidConfig { invocation, .. } |
+            ToolError::InvalidImage { invocation, .. } |
+            ToolError::InvalidKey { invocation, .. } |
+            ToolError::FileInUse { invocation, .. } |
+            ToolError::Failed { invocation, .. } => invocation
+        }
+    }
+
+    pub fn output(&self) -> Option<&ToolOutput>
+    {
+        match self
+        {
+            ToolError::Start { .. } |
+            ToolError::TimedOut { .. } => None,
+            ToolError::NotRunnable { output, .. } |
+            ToolError::InvalidConfig { output, .. } |
+            ToolError::InvalidImage { output, .. } |
+            ToolError::InvalidKey { output, .. } |
+            ToolError::FileInUse { output, .. } |
+            ToolError::Failed { output, .. } => Some(output)
+        }
+    }
+
+    /// The tool error inside an IO error returned by a tool wrapper, if any
+    pub fn find<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a ToolError>
+    {
+        if let Some(tool_error) = error.downcast_ref::<ToolError>()
+        {
+            return Some(tool_error);
+        }
+
+        error.downcast_ref::<io::Error>()
+            .and_then(|io_error| io_error.get_ref())
+            .and_then(|inner| inner.downcast_ref::<ToolError>())
+    }
+}
+
+/// Runs a [ToolInvocation] to completion, capturing its output and turning failures into [ToolError]s
+pub struct ToolRunner
+{
+    tool: ToolKind,
+    timeout: Duration
+}
+
+impl ToolRunner
+{
+    pub fn new(tool: ToolKind, timeout: Option<Duration>) -> Self
+    {
+        Self {
+            tool,
+            timeout: timeout.unwrap_or(DEFAULT_TOOL_TIMEOUT),
+        }
+    }
+
+    pub fn run(&self, invocation: &ToolInvocation) -> Result<ToolOutput, ToolError>
+    {
+        let mut command = invocation.to_command();
+
+        let details = InvocationDetails {
+            command_line: std::iter::once(command.get_program())
+                .chain(command.get_args())
+                .map(|part| format!("{:?}", part.to_string_lossy()))
+                .collect::<Vec<String>>()
+                .join(" "),
+            working_directory: command.get_current_dir().map(|folder| folder.display().to_string()),
+        };
+
+        console::trace(&format!("Running {}: {}", self.tool, details.command_line));
+
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        let mut child = match child
+        {
+            Ok(child) => child,
+            Err(source) => return Err(ToolError::Start {
+                tool: self.tool,
+                invocation: details,
+                source,
+            })
+        };
+
+        let deadline = Instant::now() + self.timeout;
+
+        let stdout = Self::capture(child.stdout.take());
+        let stderr = Self::capture(child.stderr.take());
+
+        let status = match Self::wait(&mut child, deadline)
+        {
+            Ok(Some(status)) => status,
+            Ok(None) => return Err(ToolError::TimedOut {
+                tool: self.tool,
+                invocation: details,
+                timeout: self.timeout,
+            }),
+            Err(source) => return Err(ToolError::Start {
+                tool: self.tool,
+                invocation: details,
+                source,
+            })
+        };
+
+        // processes started by the tool (e.g. the wineserver) may keep the pipes open after it exited, so they are only read until the deadline
+        let output = ToolOutput {
+            stdout: stdout.recv_timeout(deadline.saturating_duration_since(Instant::now())).unwrap_or_default(),
+            stderr: stderr.recv_timeout(deadline.saturating_duration_since(Instant::now())).unwrap_or_default(),
+        };
+
+        if status.success()
+        {
+            return Ok(output);
+        }
+
+        Err(self.classify(status.code(), details, output))
+    }
+
+    /// Maps exit codes and known messages of the tool to the matching error
+    fn classify(&self, code: Option<i32>, invocation: InvocationDetails, output: ToolOutput) -> ToolError
+    {
+        let tool = self.tool;
+
+        if let Some(code @ (NOT_EXECUTABLE_EXIT_CODE | NOT_FOUND_EXIT_CODE)) = code
+        {
+            return ToolError::NotRunnable { tool, invocation, code, output };
+        }
+
+        let find_line = |patterns: &[&str]| {
+            output.lines()
+                .find(|line| {
+                    let line = line.to_lowercase();
+                    patterns.iter().any(|pattern| line.contains(pattern))
+                })
+                .map(String::from)
+        };
+
+        if let Some(message) = find_line(&["being used by another process", "access denied", "access is denied", "permission denied"])
+        {
+            return ToolError::FileInUse { tool, invocation, message, output };
+        }
+
+        let known_message = match tool
+        {
+            // CfgConvert reports syntax errors as "File <path>, line <n>: <problem>"
+            ToolKind::ConfigConverter => find_line(&[", line "]),
+            ToolKind::PaaConverter => find_line(&["power of 2", "power of two", "dimension"]),
+            ToolKind::PboSigner => find_line(&["private key", "bad key", "key file"]),
+            ToolKind::PboPacker |
+            ToolKind::ConverterCommand |
+            ToolKind::Hook => None
+        };
+
+        match (tool, known_message)
+        {
+            (ToolKind::ConfigConverter, Some(message)) => ToolError::InvalidConfig { tool, invocation, message, output },
+            (ToolKind::PaaConverter, Some(message)) => ToolError::InvalidImage { tool, invocation, message, output },
+            (ToolKind::PboSigner, Some(message)) => ToolError::InvalidKey { tool, invocation, message, output },
+            _ => ToolError::Failed { tool, invocation, code, output }
+        }
+    }
+
+    /// Reads a pipe on its own thread so a tool filling one pipe cannot block while the other is read
+    fn capture<R: Read + Send + 'static>(pipe: Option<R>) -> Receiver<String>
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut bytes: Vec<u8> = vec![];
+
+            if let Some(mut pipe) = pipe
+            {
+                let _ = pipe.read_to_end(&mut bytes);
+            }
+
+            let _ = sender.send(String::from_utf8_lossy(&bytes).to_string());
+        });
+
+        receiver
+    }
+
+    /// The exit status, None if the tool was killed at the deadline
+    fn wait(child: &mut Child, deadline: Instant) -> Result<Option<ExitStatus>, io::Error>
+    {
+        loop
+        {
+            if let Some(status) = child.try_wait()?
+            {
+                return Ok(Some(status));
+            }
+
+            if Instant::now() >= deadline
+            {
+                let _ = child.kill();
+                let _ = child.wait();
+
+                return Ok(None);
+            }
+
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests
+{
+    use std::time::{Duration, Instant};
+    use crate::tools::tool_invocation::ToolInvocation;
+    use super::{ToolError, ToolKind, ToolOutput, ToolRunner};
+
+    fn run_script(tool: ToolKind, script: &str, timeout: Duration) -> Result<ToolOutput, ToolError>
+    {
+        let invocation = ToolInvocation::new("sh", None)
+            .arg("-c")
+            .arg(script);
+
+        ToolRunner::new(tool, Some(timeout)).run(&invocation)
+    }
+
+    #[test]
+    fn output_of_a_successful_tool_is_captured()
+    {
+        let output = run_script(ToolKind::Hook, "echo out; echo err >&2", Duration::from_secs(10)).unwrap();
+
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+    }
+
+    #[test]
+    fn tools_running_past_the_timeout_are_stopped()
+    {
+        let started = Instant::now();
+
+        let error = run_script(ToolKind::PboPacker, "sleep 10", Duration::from_millis(200)).unwrap_err();
+
+        assert!(matches!(error, ToolError::TimedOut { tool: ToolKind::PboPacker, .. }), "{error:?}");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn pipes_kept_open_by_other_processes_do_not_outlast_the_timeout()
+    {
+        let started = Instant::now();
+
+        // the background sleep inherits stdout and keeps it open after the shell exited
+        let output = run_script(ToolKind::Hook, "sleep 10 & echo started", Duration::from_millis(500)).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(output.stderr, "");
+    }
+
+    #[test]
+    fn not_found_and_not_executable_exit_codes_are_not_runnable()
+    {
+        for code in [126, 127]
+        {
+            let error = run_script(ToolKind::PboSigner, &format!("echo 'wine: cannot find tool' >&2; exit {code}"), Duration::from_secs(10)).unwrap_err();
+
+            match error
+            {
+                ToolError::NotRunnable { code: actual_code, output, .. } => {
+                    assert_eq!(actual_code, code);
+                    assert_eq!(output.stderr, "wine: cannot find tool\n");
+                }
+                other => panic!("expected NotRunnable, got {other:?}")
+            }
+        }
+    }
+
+    #[test]
+    fn known_messages_map_to_their_errors()
+    {
+        let message_of = |tool: ToolKind, script: &str| match run_script(tool, script, Duration::from_secs(10)).unwrap_err()
+        {
+            ToolError::InvalidConfig { message, .. } => ("config", message),
+            ToolError::InvalidImage { message, .. } => ("image", message),
+            ToolError::InvalidKey { message, .. } => ("key", message),
+            ToolError::FileInUse { message, .. } => ("in use", message),
+            ToolError::Failed { code, output, .. } => ("failed", format!("{code:?} {}", output.stdout.trim())),
+            other => panic!("unexpected error {other:?}")
+        };
+
+        assert_eq!(message_of(ToolKind::ConfigConverter, "echo 'File config.cpp, line 3: /CfgPatches/: Missing }'; exit 1"),
+            ("config", String::from("File config.cpp, line 3: /CfgPatches/: Missing }")));
+        assert_eq!(message_of(ToolKind::PaaConverter, "echo 'Texture size is not a power of 2' >&2; exit 1"),
+            ("image", String::from("Texture size is not a power of 2")));
+        assert_eq!(message_of(ToolKind::PboSigner, "echo '  Bad key file  '; exit 1"),
+            ("key", String::from("Bad key file")));
+        assert_eq!(message_of(ToolKind::PboPacker, "echo 'The file is being used by another process'; exit 1"),
+            ("in use", String::from("The file is being used by another process")));
+
+        // messages only count for the tool they belong to
+        assert_eq!(message_of(ToolKind::PboPacker, "echo 'bad key'; exit 3"), ("failed", String::from("Some(3) bad key")));
+    }
+}
\ No newline at end of file
@@ -1,7 +1,8 @@
 use std::fs;
 use std::fs::File;
-use std::io::{Error, ErrorKind};
-use std::path::Path;
+use std::io::{Error, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use sha1::{Digest, Sha1};
 
 pub fn check_source_and_destination<A: AsRef<Path>, B: AsRef<Path>>(source_folder: A, destination_folder: B, source_is_folder: bool, destination_is_folder: bool) -> Result<(), Error> {
     check_path(source_folder, source_is_folder, !source_is_folder, true, false)?;
@@ -22,7 +23,7 @@ pub fn check_path<P: AsRef<Path>>(path: P, must_be_directory: bool, must_be_file
             return fs::create_dir_all(path);
         }
 
-        let base_path = path.parent().ok_or(Error::new(ErrorKind::Other, "Cannot create parent directory"))?;
+        let base_path = path.parent().ok_or(Error::other("Cannot create parent directory"))?;
 
         fs::create_dir_all(base_path)?;
 
@@ -55,4 +56,73 @@ pub fn check_path_type<P: AsRef<Path>>(path: P, must_be_directory: bool, must_be
     }
 
     Ok(())
+}
+
+/// Hex encoded SHA1 of the file contents
+pub fn sha1_of_file<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let mut hasher = Sha1::new();
+
+    hash_file_into(path, &mut hasher)?;
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Feeds the file contents into the hasher, read in chunks so large pbos are not loaded at once
+pub fn hash_file_into<P: AsRef<Path>>(path: P, hasher: &mut Sha1) -> Result<(), Error> {
+    let mut file = File::open(path)?;
+
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(())
+}
+
+/// Lowercase hex encoding, as used for SHA1 hashes in the build cache, build report and pbo checksums
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Whether the path has the extension, ignoring case like Windows does
+pub fn has_extension<P: AsRef<Path>>(path: P, extension: &str) -> bool {
+    path.as_ref().extension().is_some_and(|file_extension| file_extension.eq_ignore_ascii_case(extension))
+}
+
+/// The files directly inside the folder with the extension, sorted, empty if the folder cannot be read
+pub fn files_with_extension(folder: &Path, extension: &str) -> Vec<PathBuf> {
+    let Ok(entries) = folder.read_dir() else {
+        return vec![];
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && has_extension(path, extension))
+        .collect();
+
+    files.sort();
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::has_extension;
+
+    #[test]
+    fn extensions_match_in_any_case() {
+        assert!(has_extension("keys/authority.biprivatekey", "biprivatekey"));
+        assert!(has_extension("keys/authority.BIPRIVATEKEY", "biprivatekey"));
+        assert!(has_extension("keys/authority.BiPrivateKey", "biprivatekey"));
+        assert!(!has_extension("keys/authority.bikey", "biprivatekey"));
+        assert!(!has_extension("keys/biprivatekey", "biprivatekey"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod mod_watcher;
\ No newline at end of file
@@ -0,0 +1,271 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use thiserror::Error;
+use crate::cache::build_cache::CACHE_FOLDER_NAME;
+use crate::console;
+use crate::diagnostics::report_error;
+use crate::ignore_rules::IgnoreRules;
+use crate::packers::mod_packer::{ArmaToolsModPacker, PackSummary};
+
+/// Time to wait for further changes before rebuilding, so saving many files at once only triggers a single rebuild
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(300);
+
+#[derive(Error, Debug)]
+pub enum WatchError
+{
+    #[error("IO Error")]
+    IO(#[from] std::io::Error),
+
+    #[error("File watcher Error")]
+    Notify(#[from] notify::Error),
+
+    #[error("File watcher stopped unexpectedly")]
+    Disconnected
+}
+
+pub struct ModWatcher<'a>
+{
+    mod_packer: &'a ArmaToolsModPacker,
+    source_folder: PathBuf,
+    destination_folder: PathBuf,
+    output_folders: Vec<PathBuf>,
+    rebuild_count: usize,
+
+    /// Only a successful build leaves a complete mod in the destination to take the unchanged pbos from
+    last_build_succeeded: bool
+}
+
+impl<'a> ModWatcher<'a>
+{
+    pub fn create<A: AsRef<Path>, B: AsRef<Path>>(mod_packer: &'a ArmaToolsModPacker, source_folder: A, destination_folder: B) -> Result<Self, WatchError>
+    {
+        let source_folder = source_folder.as_ref().canonicalize()?;
+
+        // the destination may not exist yet, so it can only be made absolute, not canonical
+        let destination_folder = std::path::absolute(destination_folder.as_ref())?;
+
+        // packing writes to the staging folder and moves the previous build aside, none of which are source changes
+        let output_folders = vec![
+            destination_folder.clone(),
+            ArmaToolsModPacker::staging_folder_for(&destination_folder)?,
+            ArmaToolsModPacker::previous_folder_for(&destination_folder)?,
+            source_folder.join(CACHE_FOLDER_NAME)
+        ];
+
+        Ok(Self {
+            mod_packer,
+            source_folder,
+            destination_folder,
+            output_folders,
+            rebuild_count: 0,
+            last_build_succeeded: false
+        })
+    }
+
+    /// Packs the mod once and then repacks it whenever a file in the source folder changes. Never returns on success.
+    pub fn run(&mut self) -> Result<(), WatchError>
+    {
+        self.rebuild(&BTreeSet::new());
+
+        let (sender, receiver) = mpsc::channel::<notify::Result<Event>>();
+
+        let mut watcher = notify::recommended_watcher(sender)?;
+
+        watcher.watch(&self.source_folder, RecursiveMode::Recursive)?;
+
+        console::info(&format!("Watching {:?} for changes", self.source_folder));
+
+        loop
+        {
+            let mut changed_paths = BTreeSet::<PathBuf>::new();
+
+            let first_event = receiver.recv().map_err(|_| WatchError::Disconnected)?;
+
+            self.add_changed_paths(first_event?, &mut changed_paths);
+
+            loop
+            {
+                match receiver.recv_timeout(DEBOUNCE_DURATION)
+                {
+                    Ok(event) => self.add_changed_paths(event?, &mut changed_paths),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return Err(WatchError::Disconnected)
+                }
+            }
+
+            let changed_paths = self.filter_mod_ignored(changed_paths);
+
+            if changed_paths.is_empty()
+            {
+                continue;
+            }
+
+            self.rebuild(&changed_paths);
+        }
+    }
+
+    fn add_changed_paths(&self, event: Event, changed_paths: &mut BTreeSet<PathBuf>)
+    {
+        if matches!(event.kind, EventKind::Access(_))
+        {
+            return;
+        }
+
+        for path in event.paths
+        {
+            if self.output_folders.iter().any(|output_folder| path.starts_with(output_folder))
+            {
+                continue;
+            }
+
+            changed_paths.insert(path);
+        }
+    }
+
+    /// Drops changes to files that are excluded by .modignore, changes to the ignore files themselves are kept
+    fn filter_mod_ignored(&self, changed_paths: BTreeSet<PathBuf>) -> BTreeSet<PathBuf>
+    {
+        let mod_ignore_rules = match IgnoreRules::load(&self.source_folder, ".modignore")
+        {
+            Ok(mod_ignore_rules) => mod_ignore_rules,
+            Err(error) => {
+                console::warning(&format!("Cannot read .modignore rules, rebuilding anyway: {error}"));
+                return changed_paths;
+            }
+        };
+
+        changed_paths.into_iter()
+            .filter(|path| !path.starts_with(&self.source_folder) || !mod_ignore_rules.is_ignored(path, path.is_dir()))
+            .collect()
+    }
+
+    fn rebuild(&mut self, changed_paths: &BTreeSet<PathBuf>)
+    {
+        self.rebuild_count += 1;
+
+        let start = Instant::now();
+
+        let pbo_folders = ArmaToolsModPacker::identify_pbo_folders(&self.source_folder).unwrap_or_default();
+
+        let affected_pbos = Self::affected_pbos(changed_paths, &pbo_folders);
+
+        let files_to_convert = self.count_files_to_convert(changed_paths);
+
+        // the first build and the build after a failed one always pack the whole mod
+        let changed_pbo_folders = if self.last_build_succeeded {
+            self.changed_pbo_folders(changed_paths, &pbo_folders)
+        }
+        else {
+            None
+        };
+
+        let result = match &changed_pbo_folders
+        {
+            Some(changed_pbo_folders) => self.mod_packer.pack_changed_pbos(&self.source_folder, &self.destination_folder, changed_pbo_folders),
+            None => self.mod_packer.pack_with_summary(&self.source_folder, &self.destination_folder)
+        };
+
+        self.last_build_succeeded = result.is_ok();
+
+        let duration = start.elapsed().as_secs_f32();
+
+        match result
+        {
+            Ok(summary) => {
+                console::info(&Self::result_line(self.rebuild_count, changed_paths.len(), files_to_convert, &affected_pbos, &summary, duration));
+            }
+            Err(error) => {
+                console::info(&format!("[build {}] failed after {duration:.2}s, {} changed files", self.rebuild_count, changed_paths.len()));
+                report_error(&error);
+            }
+        }
+    }
+
+    fn result_line(build: usize, changed_files: usize, files_to_convert: usize, affected_pbos: &BTreeSet<String>, summary: &PackSummary, duration: f32) -> String
+    {
+        let affected_pbos = if affected_pbos.is_empty()
+        {
+            String::from("-")
+        }
+        else {
+            affected_pbos.iter().cloned().collect::<Vec<String>>().join(", ")
+        };
+
+        let packed_pbos = if summary.packed_pbos.is_empty()
+        {
+            String::from("-")
+        }
+        else {
+            summary.packed_pbos.join(", ")
+        };
+
+        format!(
+            "[build {build}] ok in {duration:.2}s | changed {changed_files} ({files_to_convert} to convert) in pbos: {affected_pbos} | converted {} (reused {}) | packed {packed_pbos} (reused {}) | signed {} (reused {})",
+            summary.converted_files,
+            summary.reused_conversions,
+            summary.reused_pbos,
+            summary.signatures,
+            summary.reused_signatures
+        )
+    }
+
+    /// Names of the pbo folders (as found by [ArmaToolsModPacker::identify_pbo_folders]) containing any of the changed paths
+    fn affected_pbos(changed_paths: &BTreeSet<PathBuf>, pbo_folders: &BTreeSet<PathBuf>) -> BTreeSet<String>
+    {
+        let mut affected_pbos = BTreeSet::<String>::new();
+
+        for changed_path in changed_paths
+        {
+            for pbo_folder in pbo_folders
+            {
+                if changed_path.starts_with(pbo_folder)
+                {
+                    let pbo_name = pbo_folder.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+                    affected_pbos.insert(pbo_name);
+                }
+            }
+        }
+
+        affected_pbos
+    }
+
+    /// The pbo folders containing the changed paths, relative to the source folder, so only those are packed again.
+    /// None if a change can affect more than its own pbo: a file outside of every pbo folder or a pbo.json, which can add,
+    /// remove or rename pbos.
+    fn changed_pbo_folders(&self, changed_paths: &BTreeSet<PathBuf>, pbo_folders: &BTreeSet<PathBuf>) -> Option<BTreeSet<PathBuf>>
+    {
+        let mut changed_pbo_folders = BTreeSet::<PathBuf>::new();
+
+        for changed_path in changed_paths
+        {
+            if changed_path.file_name().is_some_and(|file_name| file_name == "pbo.json")
+            {
+                return None;
+            }
+
+            let pbo_folder = pbo_folders.iter().find(|pbo_folder| changed_path.starts_with(pbo_folder))?;
+
+            changed_pbo_folders.insert(PathBuf::from(pbo_folder.strip_prefix(&self.source_folder).ok()?));
+        }
+
+        Some(changed_pbo_folders)
+    }
+
+    fn count_files_to_convert(&self, changed_paths: &BTreeSet<PathBuf>) -> usize
+    {
+        let Ok(convert_ignore_rules) = IgnoreRules::load(&self.source_folder, ".convertignore") else {
+            return 0;
+        };
+
+        changed_paths.iter()
+            .filter(|path| path.is_file() && path.starts_with(&self.source_folder))
+            .filter(|path| path.file_name().is_some_and(|file_name| file_name != ".convertignore" && file_name != ".modignore"))
+            .filter(|path| !convert_ignore_rules.is_ignored(path, false))
+            .count()
+    }
+}
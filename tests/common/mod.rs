@@ -0,0 +1,103 @@
+//! Helpers for running the binary against throwaway projects with stub tools
+#![allow(dead_code)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A fresh folder in the temp directory, removed again when dropped
+pub struct TestFolder
+{
+    pub path: PathBuf
+}
+
+impl TestFolder
+{
+    pub fn new(name: &str) -> Self
+    {
+        let path = std::env::temp_dir().join(format!("armapack-{}-{name}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&path);
+
+        fs::create_dir_all(&path).unwrap();
+
+        Self {
+            path: path.canonicalize().unwrap(),
+        }
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf
+    {
+        self.path.join(path)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P, contents: &str) -> PathBuf
+    {
+        let path = self.join(path);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+
+        path
+    }
+
+    /// Writes a shell script and makes it executable
+    pub fn write_script<P: AsRef<Path>>(&self, path: P, script: &str) -> PathBuf
+    {
+        let path = self.write(path, &format!("#!/bin/sh\n{script}"));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        path
+    }
+
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> String
+    {
+        fs::read_to_string(self.join(path)).unwrap()
+    }
+
+    /// Runs the binary with the arguments inside this folder
+    pub fn run(&self, arguments: &[&str]) -> Output
+    {
+        Command::new(env!("CARGO_BIN_EXE_arma_mod_packing"))
+            .current_dir(&self.path)
+            .arg("--color")
+            .arg("never")
+            .args(arguments)
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for TestFolder
+{
+    fn drop(&mut self)
+    {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A path.json with every tool set to the given path, plus extra JSON fields (e.g. a launcher)
+pub fn path_json(tool: &str, overrides: &[(&str, String)], extra_fields: &str) -> String
+{
+    let fields = ["paa_converter_path", "rvmat_converter_path", "config_converter_path", "pbo_packer_path", "pbo_signer_path"];
+
+    let mut lines: Vec<String> = fields.iter()
+        .map(|field| {
+            let value = overrides.iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_else(|| String::from(tool));
+
+            format!("  \"{field}\": {value:?}")
+        })
+        .collect();
+
+    if !extra_fields.is_empty()
+    {
+        lines.push(format!("  {extra_fields}"));
+    }
+
+    format!("{{\n{}\n}}", lines.join(",\n"))
+}
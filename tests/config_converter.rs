@@ -0,0 +1,96 @@
+//! Converts configs and rvmats with stub CfgConvert executables that record which of them ran with which arguments
+#![cfg(unix)]
+
+mod common;
+
+use common::{path_json, TestFolder};
+
+/// Logs its own name and arguments, then writes the file given after -dst like CfgConvert does
+const STUB_CFG_CONVERT: &str = "printf '%s\\n' \"$(basename \"$0\")\" \"$@\" >> \"$(dirname \"$0\")/tools.log\"\n\
+    echo converted > \"$3\"\n";
+
+fn create_project(name: &str) -> TestFolder
+{
+    let folder = TestFolder::new(name);
+
+    let config_converter = folder.write_script("tools/config_stub", STUB_CFG_CONVERT);
+    let rvmat_converter = folder.write_script("tools/rvmat_stub", STUB_CFG_CONVERT);
+
+    let overrides = [
+        ("config_converter_path", config_converter.display().to_string()),
+        ("rvmat_converter_path", rvmat_converter.display().to_string()),
+    ];
+
+    folder.write("path.json", &path_json("/opt/tools/unused.exe", &overrides, ""));
+
+    folder
+}
+
+/// Runs the convert command in the project and returns the lines the stubs logged
+fn convert(folder: &TestFolder, arguments: &[&str]) -> Vec<String>
+{
+    let mut all_arguments = vec!["--path-json", "path.json", "convert"];
+    all_arguments.extend_from_slice(arguments);
+
+    let output = folder.run(&all_arguments);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    folder.read("tools/tools.log")
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn config_converter_path_is_used_for_configs()
+{
+    let folder = create_project("config-converter-bin");
+
+    let config = folder.write("config.cpp", "class CfgPatches {};");
+
+    assert_eq!(convert(&folder, &["config.cpp"]), vec![
+        String::from("config_stub"),
+        String::from("-bin"),
+        String::from("-dst"),
+        String::from("config.bin"),
+        config.display().to_string(),
+    ]);
+
+    assert_eq!(folder.read("config.bin"), "converted\n");
+}
+
+#[test]
+fn config_converter_path_is_used_for_debinarizing()
+{
+    let folder = create_project("config-converter-txt");
+
+    let config = folder.write("config.bin", "binary");
+
+    assert_eq!(convert(&folder, &["config.bin", "--debinarize"]), vec![
+        String::from("config_stub"),
+        String::from("-txt"),
+        String::from("-dst"),
+        String::from("config.cpp"),
+        config.display().to_string(),
+    ]);
+}
+
+#[test]
+fn rvmat_converter_path_is_used_for_rvmats()
+{
+    let folder = create_project("config-converter-rvmat");
+
+    let rvmat = folder.write("material.rvmat", "class Stage1 {};");
+
+    // rvmats convert in place through a temporary file
+    assert_eq!(convert(&folder, &["material.rvmat"]), vec![
+        String::from("rvmat_stub"),
+        String::from("-bin"),
+        String::from("-dst"),
+        String::from("material.brvmat"),
+        rvmat.display().to_string(),
+    ]);
+
+    assert_eq!(folder.read("material.rvmat"), "converted\n");
+}
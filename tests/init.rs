@@ -0,0 +1,29 @@
+//! Creates mod projects with the init command
+#![cfg(unix)]
+
+mod common;
+
+use common::TestFolder;
+
+#[test]
+fn init_writes_a_valid_prefix_pattern()
+{
+    let folder = TestFolder::new("init-prefix");
+
+    let output = folder.run(&["init", "@test", "--prefix", "z\\{project}\\addons"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(folder.read("@test/armapack.toml").contains(r"z\{project}\addons"));
+}
+
+#[test]
+fn init_rejects_unknown_prefix_placeholders()
+{
+    let folder = TestFolder::new("init-bad-prefix");
+
+    let output = folder.run(&["init", "@test", "--prefix", "z\\{mod}\\addons"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown placeholder {mod}"));
+    assert!(!folder.join("@test").exists());
+}
\ No newline at end of file
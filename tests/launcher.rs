@@ -0,0 +1,76 @@
+//! Runs the sign command through a fake launcher script that records how it was called
+#![cfg(unix)]
+
+mod common;
+
+use std::path::Path;
+use common::{path_json, TestFolder};
+
+const FAKE_LAUNCHER: &str = "pwd > \"$(dirname \"$0\")/launcher.log\"\n\
+    printf '%s\\n' \"$@\" >> \"$(dirname \"$0\")/launcher.log\"\n";
+
+/// A mod with one pbo and a private key, plus the fake launcher
+fn create_project(name: &str) -> TestFolder
+{
+    let folder = TestFolder::new(name);
+
+    folder.write("addons/first.pbo", "pbo");
+    folder.write("keys/test.biprivatekey", "key");
+    folder.write_script("launcher.sh", FAKE_LAUNCHER);
+
+    folder
+}
+
+/// Signs addons/first.pbo and returns the lines the launcher recorded: its working directory, then its arguments
+fn sign(folder: &TestFolder, launcher: &str) -> Vec<String>
+{
+    folder.write("path.json", &path_json("/opt/tools/DSSignFile.exe", &[], &format!("\"launcher\": {launcher}")));
+
+    let output = folder.run(&["--path-json", "path.json", "sign", "addons/first.pbo", "--key", "keys/test.biprivatekey"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    folder.read("launcher.log")
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+fn windows_path(path: &Path) -> String
+{
+    format!("Z:{}", path.display()).replace('/', "\\")
+}
+
+#[test]
+fn launcher_gets_windows_paths_and_placeholders()
+{
+    let folder = create_project("launcher-windows-paths");
+
+    let launcher = format!(r#"{{ "program": "{}", "arguments": ["--workdir", "{{cwd}}", "{{tool}}"] }}"#, folder.join("launcher.sh").display());
+
+    let addons_folder = folder.join("addons");
+
+    assert_eq!(sign(&folder, &launcher), vec![
+        addons_folder.display().to_string(),
+        String::from("--workdir"),
+        windows_path(&addons_folder),
+        String::from("Z:\\opt\\tools\\DSSignFile.exe"),
+        windows_path(&folder.join("keys/test.biprivatekey")),
+        windows_path(&addons_folder.join("first.pbo")),
+    ]);
+}
+
+#[test]
+fn launcher_without_placeholders_keeps_native_paths()
+{
+    let folder = create_project("launcher-native-paths");
+
+    let launcher = format!(r#"{{ "program": "{}", "windows_paths": false }}"#, folder.join("launcher.sh").display());
+
+    assert_eq!(sign(&folder, &launcher), vec![
+        folder.join("addons").display().to_string(),
+        String::from("/opt/tools/DSSignFile.exe"),
+        folder.join("keys/test.biprivatekey").display().to_string(),
+        folder.join("addons/first.pbo").display().to_string(),
+    ]);
+}
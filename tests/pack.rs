@@ -0,0 +1,227 @@
+//! Packs a small mod with stub packer and signer executables
+#![cfg(unix)]
+
+mod common;
+
+use std::collections::BTreeMap;
+use common::{path_json, TestFolder};
+
+/// Writes an empty pbo named after the folder into the working directory, like pboc does
+const STUB_PBO_PACKER: &str = "head -c 21 /dev/zero > \"$(basename \"$2\").pbo\"\n";
+
+/// Fails like DSSignFile if the private key cannot be found from its working directory, else writes the signature
+const STUB_PBO_SIGNER: &str = "[ -f \"$1\" ] || { echo \"key file $1 missing\"; exit 1; }\n\
+    echo signature > \"$2.$(basename \"$1\" .biprivatekey).bisign\"\n";
+
+/// A mod folder named mod with one pbo folder signed by the authority test, and the stub tools next to it
+fn create_project(name: &str) -> TestFolder
+{
+    let folder = TestFolder::new(name);
+
+    let pbo_packer = folder.write_script("tools/pbo_packer_stub", STUB_PBO_PACKER);
+    let pbo_signer = folder.write_script("tools/pbo_signer_stub", STUB_PBO_SIGNER);
+
+    let overrides = [
+        ("pbo_packer_path", pbo_packer.display().to_string()),
+        ("pbo_signer_path", pbo_signer.display().to_string()),
+    ];
+
+    folder.write("path.json", &path_json("/opt/tools/unused.exe", &overrides, ""));
+
+    folder.write("mod/addons/main/pbo.json", r#"{ "headers": [{ "name": "prefix", "value": "z\\test\\addons\\main" }] }"#);
+    folder.write("mod/addons/main/key.json", r#"{ "authority_name": "test" }"#);
+    folder.write("mod/keys/test.biprivatekey", "private key");
+    folder.write("mod/keys/test.bikey", "public key");
+    folder.write("mod/.convertignore", include_str!("../.convertignore"));
+
+    folder
+}
+
+#[test]
+fn packing_from_a_relative_source_signs_the_pbos()
+{
+    let folder = create_project("pack-relative-source");
+
+    let output = folder.run(&["--path-json", "path.json", "pack", "--source", "mod", "--destination", "out", "--no-cache"]);
+
+    assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+
+    assert!(folder.join("out/addons/main.pbo").is_file());
+    assert_eq!(folder.read("out/addons/main.pbo.test.bisign"), "signature\n");
+    assert_eq!(folder.read("out/keys/test.bikey"), "public key");
+}
+
+
+/// Appends the hook name and the ARMAPACK_* variables it got to hooks.log next to the mod
+const RECORDING_HOOK: &str = "echo \"== $ARMAPACK_HOOK\" >> ../hooks.log; env | grep ^ARMAPACK_ | sort >> ../hooks.log";
+
+/// The hooks in the order they ran, each with its ARMAPACK_* variables
+fn recorded_hooks(folder: &TestFolder) -> Vec<(String, BTreeMap<String, String>)>
+{
+    let mut hooks: Vec<(String, BTreeMap<String, String>)> = vec![];
+
+    for line in folder.read("hooks.log").lines()
+    {
+        match line.strip_prefix("== ")
+        {
+            Some(hook) => hooks.push((String::from(hook), BTreeMap::new())),
+            None => {
+                let (name, value) = line.split_once('=').unwrap();
+
+                hooks.last_mut().unwrap().1.insert(String::from(name), String::from(value));
+            }
+        }
+    }
+
+    hooks
+}
+
+#[test]
+fn hooks_get_their_variables_and_before_pbo_runs_per_addon()
+{
+    let folder = create_project("pack-hooks");
+
+    folder.write("mod/addons/second/pbo.json", r#"{ "headers": [{ "name": "prefix", "value": "z\\test\\addons\\second" }] }"#);
+    folder.write("mod/addons/second/key.json", r#"{ "authority_name": "test" }"#);
+
+    let hook = format!("[\"sh\", \"-c\", {RECORDING_HOOK:?}]");
+
+    folder.write("mod/armapack.toml", &format!(
+        "[mod]\nname = \"test\"\nversion = \"1.2\"\n\n[hooks]\nbefore_copy = {hook}\nafter_convert = {hook}\nbefore_pbo = {hook}\nafter_sign = {hook}\nafter_pack = {hook}\n"
+    ));
+
+    let output = folder.run(&["--path-json", "path.json", "pack", "--source", "mod", "--destination", "out", "--no-cache"]);
+
+    assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+
+    let hooks = recorded_hooks(&folder);
+
+    let names: Vec<&str> = hooks.iter().map(|(name, _)| name.as_str()).collect();
+
+    assert_eq!(names, vec!["before_copy", "after_convert", "before_pbo", "before_pbo", "after_sign", "after_pack"]);
+
+    let staging_folder = folder.join(".out.staging");
+
+    for (name, variables) in &hooks
+    {
+        assert_eq!(variables["ARMAPACK_HOOK"], *name);
+        assert_eq!(variables["ARMAPACK_MOD_FOLDER"], folder.join("mod").display().to_string());
+        assert_eq!(variables["ARMAPACK_DESTINATION_FOLDER"], folder.join("out").display().to_string());
+        assert_eq!(variables["ARMAPACK_MOD_NAME"], "test");
+        assert_eq!(variables["ARMAPACK_MOD_VERSION"], "1.2");
+
+        // the staging folder is gone once it replaced the destination
+        let expected_staging_folder = (name != "after_pack").then(|| staging_folder.display().to_string());
+
+        assert_eq!(variables.get("ARMAPACK_STAGING_FOLDER").cloned(), expected_staging_folder, "{name}");
+    }
+
+    let addons: Vec<(&str, &str, &str)> = hooks.iter()
+        .filter(|(name, _)| name == "before_pbo")
+        .map(|(_, variables)| (variables["ARMAPACK_ADDON"].as_str(), variables["ARMAPACK_ADDON_FOLDER"].as_str(), variables["ARMAPACK_PBO"].as_str()))
+        .collect();
+
+    let addon_folder = |addon: &str| staging_folder.join("addons").join(addon).display().to_string();
+    let pbo = |addon: &str| staging_folder.join("addons").join(format!("{addon}.pbo")).display().to_string();
+
+    assert_eq!(addons, vec![
+        ("main", addon_folder("main").as_str(), pbo("main").as_str()),
+        ("second", addon_folder("second").as_str(), pbo("second").as_str()),
+    ]);
+
+    assert!(hooks.iter().filter(|(name, _)| name != "before_pbo").all(|(_, variables)| !variables.contains_key("ARMAPACK_ADDON")));
+}
+
+#[test]
+fn failing_hooks_stop_packing_with_exit_code_7()
+{
+    let folder = create_project("pack-failing-hook");
+
+    folder.write("mod/armapack.toml", "[hooks]\nafter_sign = [\"sh\", \"-c\", \"echo upload failed; exit 3\"]\n");
+
+    let output = folder.run(&["--path-json", "path.json", "pack", "--source", "mod", "--destination", "out", "--no-cache"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(7), "{stderr}");
+    assert!(stderr.contains("Hook after_sign failed in stage sign"), "{stderr}");
+    assert!(!folder.join("out").exists());
+}
+
+#[test]
+fn report_lists_the_files_left_out_while_copying()
+{
+    let folder = create_project("pack-report");
+
+    folder.write("mod/.modignore", "# development files\n*.md\n");
+    folder.write("mod/notes.md", "notes");
+    folder.write("mod/keys/unused.bikey", "unused public key");
+
+    let output = folder.run(&["--path-json", "path.json", "pack", "--source", "mod", "--destination", "out", "--no-cache", "--report", "report.json"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let report: serde_json::Value = serde_json::from_str(&folder.read("report.json")).unwrap();
+
+    assert_eq!(report["ignored_files"], serde_json::json!([
+        { "path": "notes.md", "action": "ignore", "ignored_by": { "ignore_file": ".modignore", "line": 2, "pattern": "*.md" } },
+        { "path": "keys/unused.bikey", "action": "skip", "reason": "public key not used by any pbo" },
+    ]));
+}
+
+#[test]
+fn dry_run_fails_only_if_the_plan_has_problems()
+{
+    let folder = create_project("pack-dry-run");
+
+    let arguments = ["--path-json", "path.json", "pack", "--source", "mod", "--destination", "out", "--dry-run"];
+
+    let output = folder.run(&arguments);
+
+    assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("signed by test"));
+
+    folder.write("mod/addons/main/notes.xyz", "");
+
+    let output = folder.run(&arguments);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("UNSUPPORTED addons/main/notes.xyz"));
+    assert!(!folder.join("out").exists());
+}
+
+#[test]
+fn files_converted_to_the_same_output_fail_packing()
+{
+    let folder = create_project("pack-duplicate-output");
+
+    folder.write("mod/addons/main/data/tex_co.png", "png");
+    folder.write("mod/addons/main/data/tex_co.tga", "tga");
+
+    let output = folder.run(&["--path-json", "path.json", "pack", "--source", "mod", "--destination", "out", "--no-cache"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(4), "{stderr}");
+    assert!(stderr.contains(r#""addons/main/data/tex_co.tga" and "addons/main/data/tex_co.png" would both be converted to "addons/main/data/tex_co.paa""#), "{stderr}");
+    assert!(!folder.join("out").exists());
+}
+
+#[test]
+fn json_files_ending_in_key_json_are_shipped()
+{
+    let folder = create_project("pack-api-key-json");
+
+    folder.write("mod/.convertignore", &format!("{}\napi_key.json\n", include_str!("../.convertignore")));
+    folder.write("mod/addons/api_key.json", "{}");
+    folder.write("mod/addons/foo/data/api_key.json", "{}");
+
+    let output = folder.run(&["--path-json", "path.json", "pack", "--source", "mod", "--destination", "out", "--no-cache"]);
+
+    assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+
+    assert_eq!(folder.read("out/addons/api_key.json"), "{}");
+    assert_eq!(folder.read("out/addons/foo/data/api_key.json"), "{}");
+    assert!(!folder.join("out/addons/main_key.json").exists());
+    assert!(!folder.join("out/addons/main/key.json").exists());
+}
\ No newline at end of file